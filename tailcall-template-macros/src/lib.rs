@@ -0,0 +1,154 @@
+//! Compile-time checked templates.
+//!
+//! `mustache!("/users/{{value.id}}")` parses the template while compiling and
+//! expands to an expression that builds the parsed
+//! [`Mustache`](tailcall_template::mustache::Mustache), so no parsing happens
+//! at runtime. `jq!(".users[] | .id")` compiles the filter while compiling and
+//! expands to a [`JqTemplate`](tailcall_template::jq::JqTemplate).
+//!
+//! Invalid templates are compile errors pointing at the invalid part of the
+//! literal, or at the whole literal where the compiler cannot point into it.
+use std::ops::Range;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::LitStr;
+use tailcall_template::jq::{JqError, JqTemplate};
+use tailcall_template::mustache::{Mustache, Segment};
+
+/// Parses a mustache template at compile time.
+#[proc_macro]
+pub fn mustache(input: TokenStream) -> TokenStream {
+    expand_mustache(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Checks a jq filter at compile time.
+#[proc_macro]
+pub fn jq(input: TokenStream) -> TokenStream {
+    expand_jq(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_mustache(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
+    let lit: LitStr = syn::parse2(input)?;
+    let mustache =
+        Mustache::try_parse(&lit.value()).map_err(|err| error(&lit, err.span, &err.message))?;
+
+    let segments = mustache.segments().iter().map(|segment| match segment {
+        Segment::Literal(text) => quote! {
+            ::tailcall_template::mustache::Segment::Literal(::std::string::String::from(#text))
+        },
+        Segment::Expression(parts) => quote! {
+            ::tailcall_template::mustache::Segment::Expression(
+                ::std::vec![#(::std::string::String::from(#parts)),*]
+            )
+        },
+    });
+    Ok(quote! {
+        {
+            let segments: ::std::vec::Vec<::tailcall_template::mustache::Segment> =
+                ::std::vec![#(#segments),*];
+            ::tailcall_template::mustache::Mustache::from(segments)
+        }
+    })
+}
+
+fn expand_jq(input: proc_macro2::TokenStream) -> syn::Result<proc_macro2::TokenStream> {
+    let lit: LitStr = syn::parse2(input)?;
+    let code = lit.value();
+    match JqTemplate::<serde_json::Value>::parse(&code) {
+        Ok(_) => Ok(quote! {
+            ::tailcall_template::jq::JqTemplate::parse(#lit)
+                .expect("jq filter is checked at compile time")
+        }),
+        Err(JqError::Parse { message, span } | JqError::Compile { message, span }) => {
+            Err(error(&lit, span, &message))
+        }
+        Err(err) => Err(error(&lit, 0..code.len(), &err.to_string())),
+    }
+}
+
+/// Error about the bytes at `span` of the value of `lit`.
+fn error(lit: &LitStr, span: Range<usize>, message: &str) -> syn::Error {
+    let value = lit.value();
+    let snippet = value.get(span.clone()).unwrap_or_default();
+    let message = match snippet.is_empty() {
+        true => format!("{} at {}..{}", message, span.start, span.end),
+        false => format!("{} at {}..{}: `{}`", message, span.start, span.end, snippet),
+    };
+    syn::Error::new(subspan(lit, span).unwrap_or_else(|| lit.span()), message)
+}
+
+/// Span of the bytes at `span` of the value of `lit`. Only possible if the
+/// literal has no escapes, so that value and source offsets match.
+fn subspan(lit: &LitStr, span: Range<usize>) -> Option<proc_macro2::Span> {
+    let source = lit.token().to_string();
+    // `"` or `r#"`, closed by `"` or `"#`
+    let open = source.find('"')? + 1;
+    let close = open - usize::from(source.starts_with('r'));
+    let inner = source.get(open..source.len().checked_sub(close)?)?;
+    if inner != lit.value() {
+        return None;
+    }
+    lit.token().subspan(open + span.start..open + span.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use quote::quote;
+
+    use super::{expand_jq, expand_mustache};
+
+    #[test]
+    fn test_expand_mustache() {
+        let actual = expand_mustache(quote!("/users/{{value.id}}")).unwrap();
+        let expected = quote! {
+            {
+                let segments: ::std::vec::Vec<::tailcall_template::mustache::Segment> = ::std::vec![
+                    ::tailcall_template::mustache::Segment::Literal(::std::string::String::from("/users/")),
+                    ::tailcall_template::mustache::Segment::Expression(
+                        ::std::vec![::std::string::String::from("value"), ::std::string::String::from("id")]
+                    )
+                ];
+                ::tailcall_template::mustache::Mustache::from(segments)
+            }
+        };
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_expand_mustache_error() {
+        let err = expand_mustache(quote!("/users/{{value.1d}}")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid expression `{{value.1d}}` at 7..19: `{{value.1d}}`"
+        );
+        let err = expand_mustache(quote!(1)).unwrap_err();
+        assert_eq!(err.to_string(), "expected string literal");
+    }
+
+    #[test]
+    fn test_expand_jq() {
+        let actual = expand_jq(quote!(".a | @base64")).unwrap();
+        let expected = quote! {
+            ::tailcall_template::jq::JqTemplate::parse(".a | @base64")
+                .expect("jq filter is checked at compile time")
+        };
+        assert_eq!(actual.to_string(), expected.to_string());
+    }
+
+    #[test]
+    fn test_expand_jq_error() {
+        let err = expand_jq(quote!(".a | undefined_filter")).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "undefined filter `undefined_filter` at 5..21: `undefined_filter`"
+        );
+        assert!(expand_jq(quote!(".a |")).is_err());
+        assert!(expand_jq(quote!(r#"{"a": }"#)).is_err());
+    }
+}
@@ -0,0 +1,21 @@
+use serde_json::json;
+use tailcall_template::jq::JqTemplate;
+use tailcall_template::mustache::Mustache;
+use tailcall_template_macros::{jq, mustache};
+
+#[test]
+fn mustache_macro_matches_runtime_parse() {
+    let template = "/users/{{ value.id }}/posts?limit={{args.limit}}";
+    assert_eq!(
+        mustache!("/users/{{ value.id }}/posts?limit={{args.limit}}"),
+        Mustache::parse(template)
+    );
+    assert_eq!(mustache!(""), Mustache::parse(""));
+}
+
+#[test]
+fn jq_macro_builds_template() {
+    let template: JqTemplate<serde_json::Value> = jq!(r#"{id: .user.id, name: "\(.user.name)!"}"#);
+    let actual = template.run(json!({"user": {"id": 1, "name": "jq"}}));
+    assert_eq!(actual, vec![Ok(json!({"id": 1, "name": "jq!"}))]);
+}
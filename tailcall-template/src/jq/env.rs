@@ -0,0 +1,34 @@
+//! Per-run state shared with native jq filters.
+//!
+//! Native filters in jaq are plain function pointers and can't capture any
+//! state, so everything a run needs (sinks, trace recorders, ...) is installed
+//! into a thread local for the duration of the run.
+use std::any::Any;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+thread_local! {
+    static ENV: RefCell<Vec<Rc<dyn Any>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Keeps an environment installed until it is dropped.
+pub(crate) struct EnvGuard(());
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        ENV.with(|env| env.borrow_mut().pop());
+    }
+}
+
+/// Installs `env` as the current environment, shadowing the previous one
+/// until the returned guard is dropped.
+pub(crate) fn enter<E: Any>(env: Rc<E>) -> EnvGuard {
+    ENV.with(|stack| stack.borrow_mut().push(env));
+    EnvGuard(())
+}
+
+/// Returns the current environment if it has type `E`.
+pub(crate) fn current<E: Any>() -> Option<Rc<E>> {
+    ENV.with(|stack| stack.borrow().last().cloned())
+        .and_then(|env| env.downcast::<E>().ok())
+}
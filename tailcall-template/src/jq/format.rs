@@ -0,0 +1,408 @@
+//! `@format` filters, such as `@base64` or `@csv`.
+//!
+//! Each format is a native filter named after it, so both `@csv` and
+//! `@uri "/users/\(.id)"` work; in the latter the format is applied to every
+//! interpolated value. Escaping follows jq and does not depend on the
+//! `JsonLike` backend.
+use std::fmt::Write;
+
+use jaq_core::box_iter::box_once;
+use jaq_core::{Error, Exn, Native, ValX};
+
+use super::jq::JsonLikeHelper;
+use super::template::Fun;
+use crate::jsonlike::{JsonLike, JsonSerializer};
+
+/// Writes `value` as compact JSON, formatting numbers and strings like jq.
+pub(crate) fn write_json<'a, A: JsonLike<'a>>(out: &mut String, value: &'a A) {
+    let _ = JsonSerializer::default().write(out, value);
+}
+
+/// Writes `s` as a quoted JSON string.
+pub(crate) fn write_string(out: &mut String, s: &str) {
+    let _ = JsonSerializer::default().write_str(out, s);
+}
+
+/// Converts a value to JSON text.
+pub(crate) fn to_json<A>(value: &A) -> String
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let mut out = String::new();
+    write_json(&mut out, value);
+    out
+}
+
+/// jq's `tostring`: strings are kept as they are, everything else becomes JSON.
+fn to_text<A>(value: &A) -> String
+where
+    A: for<'a> JsonLike<'a>,
+{
+    match value.as_str() {
+        Some(s) => s.to_string(),
+        None => to_json(value),
+    }
+}
+
+/// Kind of value used in error messages, e.g. `object ({"a":1})`.
+fn describe<A>(value: &A) -> String
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let kind = if value.as_object().is_some() {
+        "object"
+    } else if value.as_array().is_some() {
+        "array"
+    } else {
+        "value"
+    };
+    format!("{} ({})", kind, to_json(value))
+}
+
+/// Escapes the elements of an array with `cell`, joining them with `separator`.
+fn row<A>(
+    value: &A,
+    format: &str,
+    separator: &str,
+    cell: impl Fn(&mut String, &str),
+) -> Result<String, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let Some(arr) = value.as_array() else {
+        return Err(format!(
+            "{} cannot be {}-formatted, only an array can be",
+            describe(value),
+            format
+        ));
+    };
+    let mut out = String::new();
+    for (i, item) in arr.iter().enumerate() {
+        if i > 0 {
+            out.push_str(separator);
+        }
+        if item.is_null() {
+            continue;
+        } else if let Some(s) = item.as_str() {
+            cell(&mut out, s);
+        } else if item.as_array().is_some() || item.as_object().is_some() {
+            return Err(format!(
+                "{} is not valid in a {} row",
+                describe(item),
+                format
+            ));
+        } else {
+            write_json(&mut out, item);
+        }
+    }
+    Ok(out)
+}
+
+fn csv<A>(value: &A) -> Result<String, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    row(value, "csv", ",", |out, s| {
+        out.push('"');
+        out.push_str(&s.replace('"', "\"\""));
+        out.push('"');
+    })
+}
+
+fn tsv<A>(value: &A) -> Result<String, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    row(value, "tsv", "\t", |out, s| {
+        for c in s.chars() {
+            match c {
+                '\\' => out.push_str("\\\\"),
+                '\t' => out.push_str("\\t"),
+                '\n' => out.push_str("\\n"),
+                '\r' => out.push_str("\\r"),
+                c => out.push(c),
+            }
+        }
+    })
+}
+
+fn sh<A>(value: &A) -> Result<String, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let quote = |out: &mut String, item: &A| {
+        if let Some(s) = item.as_str() {
+            out.push('\'');
+            out.push_str(&s.replace('\'', "'\\''"));
+            out.push('\'');
+            Ok(())
+        } else if item.as_array().is_some() || item.as_object().is_some() {
+            Err(format!("{} can not be escaped for shell", describe(item)))
+        } else {
+            write_json(out, item);
+            Ok(())
+        }
+    };
+
+    let mut out = String::new();
+    match value.as_array() {
+        Some(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push(' ');
+                }
+                quote(&mut out, item)?;
+            }
+        }
+        None => quote(&mut out, value)?,
+    }
+    Ok(out)
+}
+
+fn html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '\'' => out.push_str("&#39;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn uri(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(b as char)
+            }
+            b => {
+                let _ = write!(out, "%{:02X}", b);
+            }
+        }
+    }
+    out
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64(s: &str) -> String {
+    let mut out = String::with_capacity(s.len().div_ceil(3) * 4);
+    for chunk in s.as_bytes().chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes base64 with or without padding, replacing invalid UTF-8 like jq.
+fn base64d(s: &str) -> Result<String, String> {
+    let invalid = || {
+        let mut out = String::new();
+        write_string(&mut out, s);
+        format!("{} is not valid base64 data", out)
+    };
+    let data = s.trim_end_matches('=').as_bytes();
+    if data.len() % 4 == 1 {
+        return Err(invalid());
+    }
+
+    let mut out = Vec::with_capacity(data.len() * 3 / 4);
+    for chunk in data.chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let digit = BASE64.iter().position(|b| b == c).ok_or_else(invalid)?;
+            n |= (digit as u32) << (18 - 6 * i);
+        }
+        let bytes = n.to_be_bytes();
+        out.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+fn format<A>(value: &A, name: &str) -> Result<String, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    match name {
+        "@text" => Ok(to_text(value)),
+        "@json" => Ok(to_json(value)),
+        "@csv" => csv(value),
+        "@tsv" => tsv(value),
+        "@sh" => sh(value),
+        "@html" => Ok(html(&to_text(value))),
+        "@uri" => Ok(uri(&to_text(value))),
+        "@base64" => Ok(base64(&to_text(value))),
+        "@base64d" => base64d(&to_text(value)),
+        _ => Err(format!("{} is not a valid format", name)),
+    }
+}
+
+fn run<'a, A>(value: JsonLikeHelper<A>, name: &str) -> ValX<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    match format(&value.0, name) {
+        Ok(s) => Ok(JsonLikeHelper(A::string(s.into()))),
+        Err(message) => Err(Exn::from(Error::str(message))),
+    }
+}
+
+/// Native filters for all supported formats.
+pub(crate) fn funs<A>() -> Vec<Fun<A>>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    macro_rules! format {
+        ($name:literal) => {
+            (
+                $name,
+                Box::new([]),
+                Native::new(|_, cv| box_once(run(cv.1, $name))),
+            )
+        };
+    }
+
+    vec![
+        format!("@text"),
+        format!("@json"),
+        format!("@csv"),
+        format!("@tsv"),
+        format!("@sh"),
+        format!("@html"),
+        format!("@uri"),
+        format!("@base64"),
+        format!("@base64d"),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::jq::{JqError, JqTemplate};
+
+    fn run<A>(code: &str, input: A) -> Vec<Result<A, JqError>>
+    where
+        A: for<'a> crate::jsonlike::JsonLike<'a>
+            + std::fmt::Display
+            + std::clone::Clone
+            + std::cmp::PartialEq
+            + 'static,
+    {
+        JqTemplate::parse(code).unwrap().run(input)
+    }
+
+    fn format(code: &str, input: serde_json::Value) -> String {
+        match run(code, input).remove(0) {
+            Ok(serde_json::Value::String(s)) => s,
+            output => panic!("{code} returned {output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_text_and_json() {
+        assert_eq!(format("@text", json!("a\"b")), "a\"b");
+        assert_eq!(format("@text", json!([1, "x"])), "[1,\"x\"]");
+        assert_eq!(format("@json", json!("a\"b\n")), "\"a\\\"b\\n\"");
+        assert_eq!(
+            format("@json", json!({"a": [1.5, null, true]})),
+            "{\"a\":[1.5,null,true]}"
+        );
+    }
+
+    #[test]
+    fn test_csv_and_tsv() {
+        let row = json!([1, "a,\"b\"", null, true, "t\tn\n\\"]);
+        assert_eq!(
+            format("@csv", row.clone()),
+            "1,\"a,\"\"b\"\"\",,true,\"t\tn\n\\\""
+        );
+        assert_eq!(format("@tsv", row), "1\ta,\"b\"\t\ttrue\tt\\tn\\n\\\\");
+
+        let err = run("@csv", json!([[1]])).remove(0).unwrap_err();
+        assert_eq!(
+            err,
+            JqError::Runtime("array ([1]) is not valid in a csv row".into())
+        );
+    }
+
+    #[test]
+    fn test_sh() {
+        assert_eq!(format("@sh", json!("it's")), "'it'\\''s'");
+        assert_eq!(format("@sh", json!(["a b", 1, null])), "'a b' 1 null");
+        assert!(run("@sh", json!([{}])).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_html_and_uri() {
+        assert_eq!(
+            format("@html", json!("<a href=\"x\">'&'</a>")),
+            "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+        );
+        assert_eq!(
+            format("@uri", json!("a b/c?d=é~")),
+            "a%20b%2Fc%3Fd%3D%C3%A9~"
+        );
+    }
+
+    #[test]
+    fn test_base64() {
+        for (text, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+        ] {
+            assert_eq!(format("@base64", json!(text)), encoded);
+            assert_eq!(format("@base64d", json!(encoded)), text);
+        }
+        assert_eq!(format("@base64d", json!("Zm9vYg")), "foob");
+        assert!(run("@base64d", json!("Z!==")).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_interpolation() {
+        let input = json!({"id": "a/b c", "name": "O'Neil"});
+        assert_eq!(
+            format("@uri \"/users/\\(.id)?x=1\"", input.clone()),
+            "/users/a%2Fb%20c?x=1"
+        );
+        assert_eq!(
+            format("@sh \"echo \\(.name)\"", input.clone()),
+            "echo 'O'\\''Neil'"
+        );
+        assert_eq!(format("\"\\(.id): \\(.name)\"", input), "a/b c: O'Neil");
+    }
+
+    #[test]
+    fn test_graphql_backend() {
+        let input = async_graphql::Value::from_json(json!({"id": 1, "tags": ["a", "b"]})).unwrap();
+        let actual = run("@json, @csv \"\\(.tags)\"", input);
+        let expected = vec![
+            Ok(async_graphql::Value::from(
+                "{\"id\":1,\"tags\":[\"a\",\"b\"]}",
+            )),
+            Ok(async_graphql::Value::from("\"a\",\"b\"")),
+        ];
+        assert_eq!(actual, expected);
+    }
+}
@@ -2,6 +2,7 @@ use std::{borrow::Cow, ops::Deref};
 
 use jaq_core::ValR;
 
+use super::paths::{track, Step};
 use crate::jsonlike::{JsonLike, JsonObjectLike};
 
 #[derive(Debug, Clone, PartialEq)]
@@ -82,11 +83,11 @@ where
                 None => ValR::Ok(JsonLikeHelper(JsonLike::null())),
             }
         } else if let Some(arr) = self.0.as_array() {
-            let Some(index) = index.0.as_u64() else {
+            let Some(index) = as_index(&index.0) else {
                 return ValR::Err(jaq_core::Error::str("Index cannot be converted to u64"));
             };
 
-            match arr.get(index as usize) {
+            match abs_index(index, arr.len()).and_then(|index| arr.get(index)) {
                 Some(item) => ValR::Ok(JsonLikeHelper(item.clone())),
                 None => ValR::Ok(JsonLikeHelper(JsonLike::null())),
             }
@@ -173,18 +174,22 @@ where
         f: impl Fn(Self) -> I,
     ) -> jaq_core::ValX<'a, Self> {
         if let Some(arr) = self.0.as_array() {
-            let iter = arr.iter().map(|a| JsonLikeHelper(a.clone())).flat_map(f);
+            let iter = arr.iter().enumerate().flat_map(|(i, a)| {
+                let item = JsonLikeHelper(a.clone());
+                track(|| Step::Index(i as isize), || f(item).collect::<Vec<_>>())
+            });
             Ok(iter.collect::<Result<_, _>>()?)
         } else if let Some(obj) = self.0.as_object() {
-            let iter = obj
-                .iter()
-                .filter_map(|(k, v)| f(JsonLikeHelper(v.clone())).next().map(|v| Ok((k, v?.0))));
+            let iter = obj.iter().filter_map(|(k, v)| {
+                let item = JsonLikeHelper(v.clone());
+                track(|| Step::Key(k.to_string()), || f(item).next()).map(|v| Ok((k, v?.0)))
+            });
             let obj = A::obj(iter.collect::<Result<Vec<_>, jaq_core::Exn<_>>>()?);
             Ok(JsonLikeHelper(obj))
         } else {
-            return opt.fail(self, |_v| {
+            opt.fail(self, |_v| {
                 jaq_core::Exn::from(jaq_core::Error::str("Value is not object or array"))
-            });
+            })
         }
     }
 
@@ -201,22 +206,20 @@ where
                 });
             };
 
-            match obj.get_key(key) {
-                Some(e) => match f(JsonLikeHelper(e.clone())).next().transpose()? {
-                    Some(value) => obj.insert_key(key, value.0),
-                    None => {
-                        obj.remove_key(key);
-                    }
-                },
-                None => {
-                    if let Some(value) = f(JsonLikeHelper(JsonLike::null())).next().transpose()? {
-                        obj.insert_key(key, value.0);
-                    }
+            let item = obj.get_key(key).cloned();
+            let exists = item.is_some();
+            let item = JsonLikeHelper(item.unwrap_or_else(JsonLike::null));
+            let step = || Step::Key(key.to_string());
+            match track(step, || f(item).next()).transpose()? {
+                Some(value) => obj.insert_key(key, value.0),
+                None if exists => {
+                    obj.remove_key(key);
                 }
+                None => {}
             }
             Ok(self)
         } else if let Some(arr) = self.0.as_array_mut() {
-            let Some(index) = index.0.as_u64() else {
+            let Some(index) = as_index(&index.0) else {
                 return opt.fail(self, |_v| {
                     jaq_core::Exn::from(jaq_core::Error::str("Index cannot be converted to u64"))
                 });
@@ -225,23 +228,23 @@ where
                 abs_index(i, arr.len())
                     .ok_or(jaq_core::Error::str(format!("index {i} out of bounds")))
             };
-            // TODO: perform error handling
-            let index = match abs_or(index.try_into().unwrap()) {
+            let step = || Step::Index(index);
+            let index = match abs_or(index) {
                 Ok(index) => index,
                 Err(e) => return opt.fail(self, |_v| jaq_core::Exn::from(e)),
             };
 
-            let item = arr[index].clone();
-            if let Some(value) = f(JsonLikeHelper(item)).next().transpose()? {
+            let item = JsonLikeHelper(arr[index].clone());
+            if let Some(value) = track(step, || f(item).next()).transpose()? {
                 arr[index] = value.0;
             } else {
                 arr.remove(index);
             }
             Ok(self)
         } else {
-            return opt.fail(self, |_v| {
+            opt.fail(self, |_v| {
                 jaq_core::Exn::from(jaq_core::Error::str("Value is not object or array"))
-            });
+            })
         }
     }
 
@@ -280,6 +283,7 @@ where
                 });
             };
 
+            let step = || Step::Slice(from, upto);
             let from = abs_bound(from, len, 0);
             let upto = abs_bound(upto, len, len);
 
@@ -292,8 +296,8 @@ where
                 .map(|a| a.clone())
                 .collect::<Vec<_>>();
 
-            let new_values =
-                f(JsonLikeHelper(JsonLike::array(arr_slice))).collect::<Result<Vec<_>, _>>()?;
+            let slice = JsonLikeHelper(JsonLike::array(arr_slice));
+            let new_values = track(step, || f(slice).collect::<Result<Vec<_>, _>>())?;
 
             arr.splice(skip..skip + take, new_values.into_iter().map(|a| a.0));
             Ok(self)
@@ -343,7 +347,7 @@ where
     A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        self.0.fmt(f)
+        f.write_str(&super::format::to_json(&self.0))
     }
 }
 
@@ -351,8 +355,8 @@ impl<A> From<bool> for JsonLikeHelper<A>
 where
     A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
 {
-    fn from(_value: bool) -> Self {
-        todo!()
+    fn from(value: bool) -> Self {
+        JsonLikeHelper(A::boolean(value))
     }
 }
 
@@ -360,8 +364,8 @@ impl<A> From<isize> for JsonLikeHelper<A>
 where
     A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
 {
-    fn from(_value: isize) -> Self {
-        todo!()
+    fn from(value: isize) -> Self {
+        JsonLikeHelper(A::number_f64(value as f64))
     }
 }
 
@@ -378,8 +382,8 @@ impl<A> FromIterator<Self> for JsonLikeHelper<A>
 where
     A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
 {
-    fn from_iter<T: IntoIterator<Item = Self>>(_iter: T) -> Self {
-        todo!()
+    fn from_iter<T: IntoIterator<Item = Self>>(iter: T) -> Self {
+        JsonLikeHelper(A::array(iter.into_iter().map(|v| v.0).collect()))
     }
 }
 
@@ -465,7 +469,34 @@ where
 {
     type Output = ValR<Self>;
     fn neg(self) -> Self::Output {
-        todo!()
+        match self.0.as_f64() {
+            Some(n) => Ok(JsonLikeHelper(A::number_f64(-n))),
+            None => Err(jaq_core::Error::str(format!(
+                "{} ({}) cannot be negated",
+                self,
+                kind(&self.0)
+            ))),
+        }
+    }
+}
+
+/// Name of the jq type of `value`, as in error messages.
+pub(crate) fn kind<A>(value: &A) -> &'static str
+where
+    A: for<'b> JsonLike<'b>,
+{
+    if value.is_null() {
+        "null"
+    } else if value.as_bool().is_some() {
+        "boolean"
+    } else if value.as_str().is_some() {
+        "string"
+    } else if value.as_array().is_some() {
+        "array"
+    } else if value.as_object().is_some() {
+        "object"
+    } else {
+        "number"
     }
 }
 
@@ -479,6 +510,20 @@ fn abs_bound(i: Option<isize>, len: usize, default: usize) -> usize {
     i.map_or(default, |i| core::cmp::min(wrap(i, len).unwrap_or(0), len))
 }
 
+/// Array index of a number, floored like jq does.
+fn as_index<A>(index: &A) -> Option<isize>
+where
+    A: for<'b> JsonLike<'b>,
+{
+    match index.as_i64() {
+        Some(index) => index.try_into().ok(),
+        None => index
+            .as_f64()
+            .filter(|index| index.is_finite())
+            .map(|index| index.floor() as isize),
+    }
+}
+
 /// Absolutise an index and return result if it is inside [0, len).
 fn abs_index(i: isize, len: usize) -> Option<usize> {
     wrap(i, len).filter(|i| *i < len)
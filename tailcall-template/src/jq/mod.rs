@@ -1 +1,18 @@
+mod env;
+mod format;
 pub mod jq;
+mod optimize;
+mod order;
+mod paths;
+mod print;
+mod regex;
+mod shape;
+mod template;
+mod time;
+mod trace;
+
+pub use order::KeyOrder;
+pub use shape::*;
+pub use template::*;
+pub use time::JqClock;
+pub use trace::*;
@@ -0,0 +1,451 @@
+//! Simplification pass that runs between parsing and compiling a filter.
+//!
+//! The pass folds literal-only subexpressions, drops identity pipes and
+//! removes branches that can never be taken. It only rewrites terms whose
+//! result is known to be the same as evaluating them, everything else is kept
+//! as written.
+use std::borrow::Cow;
+
+use jaq_core::load::lex::StrPart;
+use jaq_core::load::parse::{BinaryOp, Def, Pattern, Term};
+use jaq_core::ops::Math;
+use jaq_core::path::{Part, Path};
+
+/// Simplifies a parsed filter.
+pub(crate) fn optimize(term: Term<&str>) -> Term<Cow<'_, str>> {
+    Optimizer::default().term(term)
+}
+
+/// Value of a literal-only term.
+#[derive(Debug, Clone, PartialEq)]
+enum Const {
+    Num(f64),
+    Str(String),
+    Arr(Vec<Const>),
+    /// Entries in insertion order, with later duplicates replacing earlier ones.
+    Obj(Vec<(String, Const)>),
+}
+
+impl Const {
+    fn from_term(term: &Term<Cow<str>>) -> Option<Self> {
+        match term {
+            Term::Num(n) => n
+                .parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .map(Const::Num),
+            Term::Str(None, _) => literal(term).map(Const::Str),
+            Term::Arr(None) => Some(Const::Arr(Vec::new())),
+            Term::Arr(Some(items)) => {
+                let mut arr = Vec::new();
+                commas(items, &mut |item| {
+                    arr.push(Const::from_term(item)?);
+                    Some(())
+                })?;
+                Some(Const::Arr(arr))
+            }
+            Term::Obj(entries) => {
+                let mut obj = Vec::new();
+                for (key, value) in entries {
+                    let key = literal(key)?;
+                    let value = Const::from_term(value.as_ref()?)?;
+                    Const::insert(&mut obj, key, value);
+                }
+                Some(Const::Obj(obj))
+            }
+            _ => None,
+        }
+    }
+
+    fn into_term<'s>(self) -> Term<Cow<'s, str>> {
+        match self {
+            Const::Num(n) => Term::Num(Cow::Owned(n.to_string())),
+            Const::Str(s) => Term::Str(None, Vec::from([StrPart::Str(Cow::Owned(s))])),
+            Const::Arr(arr) => {
+                let items = arr.into_iter().map(Const::into_term);
+                let items = items.reduce(|l, r| binary(l, BinaryOp::Comma, r));
+                Term::Arr(items.map(Box::new))
+            }
+            Const::Obj(obj) => Term::Obj(
+                obj.into_iter()
+                    .map(|(k, v)| (Const::Str(k).into_term(), Some(v.into_term())))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn insert(obj: &mut Vec<(String, Const)>, key: String, value: Const) {
+        match obj.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, v)) => *v = value,
+            None => obj.push((key, value)),
+        }
+    }
+
+    /// `self + other`, if the addition is defined for both values.
+    fn add(self, other: Const) -> Option<Const> {
+        match (self, other) {
+            (Const::Num(l), Const::Num(r)) => Some(Const::Num(l + r)).filter(Const::is_finite),
+            (Const::Str(l), Const::Str(r)) => Some(Const::Str(l + &r)),
+            (Const::Arr(mut l), Const::Arr(r)) => {
+                l.extend(r);
+                Some(Const::Arr(l))
+            }
+            (Const::Obj(mut l), Const::Obj(r)) => {
+                for (k, v) in r {
+                    Const::insert(&mut l, k, v);
+                }
+                Some(Const::Obj(l))
+            }
+            _ => None,
+        }
+    }
+
+    fn is_finite(&self) -> bool {
+        !matches!(self, Const::Num(n) if !n.is_finite())
+    }
+
+    /// Looks up `path` in the value, if every part is a key that exists.
+    fn get(self, path: &Path<Term<Cow<str>>>) -> Option<Const> {
+        path.0
+            .iter()
+            .try_fold(self, |value, (part, _)| match (value, part) {
+                (Const::Obj(obj), Part::Index(key)) => {
+                    let key = literal(key)?;
+                    obj.into_iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+                }
+                _ => None,
+            })
+    }
+}
+
+/// Calls `f` on every element of a comma-separated sequence of terms.
+fn commas<'a, 's>(
+    term: &'a Term<Cow<'s, str>>,
+    f: &mut impl FnMut(&'a Term<Cow<'s, str>>) -> Option<()>,
+) -> Option<()> {
+    match term {
+        Term::BinOp(l, BinaryOp::Comma, r) => {
+            commas(l, f)?;
+            commas(r, f)
+        }
+        term => f(term),
+    }
+}
+
+/// Returns the value of a string literal without interpolation.
+fn literal(term: &Term<Cow<str>>) -> Option<String> {
+    let Term::Str(None, parts) = term else {
+        return None;
+    };
+    let mut out = String::new();
+    for part in parts {
+        match part {
+            StrPart::Str(s) => out.push_str(s),
+            StrPart::Char(c) => out.push(*c),
+            StrPart::Term(_) => return None,
+        }
+    }
+    Some(out)
+}
+
+fn binary<'s>(l: Term<Cow<'s, str>>, op: BinaryOp, r: Term<Cow<'s, str>>) -> Term<Cow<'s, str>> {
+    Term::BinOp(Box::new(l), op, Box::new(r))
+}
+
+fn is_call(term: &Term<Cow<str>>, name: &str) -> bool {
+    matches!(term, Term::Call(f, args) if f == name && args.is_empty())
+}
+
+#[derive(Default)]
+struct Optimizer<'s> {
+    /// Filters defined by the program, builtins with these names are not
+    /// simplified.
+    defined: Vec<&'s str>,
+}
+
+impl<'s> Optimizer<'s> {
+    fn is_builtin(&self, term: &Term<Cow<str>>, name: &str) -> bool {
+        is_call(term, name) && !self.defined.contains(&name)
+    }
+
+    fn boxed(&mut self, term: Term<&'s str>) -> Box<Term<Cow<'s, str>>> {
+        Box::new(self.term(term))
+    }
+
+    fn term(&mut self, term: Term<&'s str>) -> Term<Cow<'s, str>> {
+        match term {
+            Term::Id => Term::Id,
+            Term::Recurse => Term::Recurse,
+            Term::Num(n) => Term::Num(Cow::Borrowed(n)),
+            Term::Str(fmt, parts) => Term::Str(fmt.map(Cow::Borrowed), self.str_parts(parts)),
+            Term::Arr(t) => Term::Arr(t.map(|t| self.boxed(*t))),
+            Term::Obj(entries) => Term::Obj(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (self.term(k), v.map(|v| self.term(v))))
+                    .collect(),
+            ),
+            Term::Neg(t) => Term::Neg(self.boxed(*t)),
+            Term::Pipe(l, pattern, r) => {
+                let (l, r) = (self.term(*l), self.term(*r));
+                match pattern {
+                    Some(pattern) => {
+                        Term::Pipe(Box::new(l), Some(self.pattern(pattern)), Box::new(r))
+                    }
+                    None => pipe(l, r),
+                }
+            }
+            Term::BinOp(l, op, r) => {
+                let (l, r) = (self.term(*l), self.term(*r));
+                self.binary_op(l, op, r)
+            }
+            Term::Label(x, t) => Term::Label(Cow::Borrowed(x), self.boxed(*t)),
+            Term::Break(x) => Term::Break(Cow::Borrowed(x)),
+            Term::Fold(name, xs, pattern, args) => Term::Fold(
+                Cow::Borrowed(name),
+                self.boxed(*xs),
+                self.pattern(pattern),
+                args.into_iter().map(|arg| self.term(arg)).collect(),
+            ),
+            Term::TryCatch(t, catch) => {
+                let t = self.term(*t);
+                match Const::from_term(&t) {
+                    // literals never fail
+                    Some(_) => t,
+                    None => Term::TryCatch(Box::new(t), catch.map(|c| self.boxed(*c))),
+                }
+            }
+            Term::IfThenElse(if_thens, else_) => self.if_then_else(if_thens, else_),
+            Term::Def(defs, t) => {
+                let len = self.defined.len();
+                self.defined.extend(defs.iter().map(|def| def.name));
+                let defs = defs.into_iter().map(|def| self.def(def)).collect();
+                let t = self.boxed(*t);
+                self.defined.truncate(len);
+                Term::Def(defs, t)
+            }
+            Term::Call(name, args) => Term::Call(
+                Cow::Borrowed(name),
+                args.into_iter().map(|arg| self.term(arg)).collect(),
+            ),
+            Term::Var(x) => Term::Var(Cow::Borrowed(x)),
+            Term::Path(head, path) => {
+                let head = self.term(*head);
+                let path = self.path(path);
+                match Const::from_term(&head).and_then(|c| c.get(&path)) {
+                    Some(value) => value.into_term(),
+                    None if path.0.is_empty() => head,
+                    None => Term::Path(Box::new(head), path),
+                }
+            }
+        }
+    }
+
+    fn binary_op(
+        &mut self,
+        l: Term<Cow<'s, str>>,
+        op: BinaryOp,
+        r: Term<Cow<'s, str>>,
+    ) -> Term<Cow<'s, str>> {
+        match op {
+            BinaryOp::Comma if self.is_builtin(&l, "empty") => r,
+            BinaryOp::Comma if self.is_builtin(&r, "empty") => l,
+            // literals are never null or false
+            BinaryOp::Alt if Const::from_term(&l).is_some() => l,
+            BinaryOp::Math(Math::Add) => {
+                let sum = Const::from_term(&l)
+                    .zip(Const::from_term(&r))
+                    .and_then(|(l, r)| l.add(r));
+                match sum {
+                    Some(sum) => sum.into_term(),
+                    None => binary(l, op, r),
+                }
+            }
+            op => binary(l, op, r),
+        }
+    }
+
+    fn if_then_else(
+        &mut self,
+        if_thens: Vec<(Term<&'s str>, Term<&'s str>)>,
+        else_: Option<Box<Term<&'s str>>>,
+    ) -> Term<Cow<'s, str>> {
+        let mut branches = Vec::new();
+        for (if_, then_) in if_thens {
+            let (if_, then_) = (self.term(if_), self.term(then_));
+            // a literal condition is always true, so later branches are dead
+            if Const::from_term(&if_).is_some() {
+                return match branches.is_empty() {
+                    true => then_,
+                    false => Term::IfThenElse(branches, Some(Box::new(then_))),
+                };
+            }
+            branches.push((if_, then_));
+        }
+        Term::IfThenElse(branches, else_.map(|e| self.boxed(*e)))
+    }
+
+    fn def(&mut self, def: Def<&'s str>) -> Def<Cow<'s, str>> {
+        let len = self.defined.len();
+        self.defined.push(def.name);
+        self.defined.extend(def.args.iter().copied());
+        let body = self.term(def.body);
+        self.defined.truncate(len);
+        Def {
+            name: Cow::Borrowed(def.name),
+            args: def.args.into_iter().map(Cow::Borrowed).collect(),
+            body,
+        }
+    }
+
+    fn path(&mut self, path: Path<Term<&'s str>>) -> Path<Term<Cow<'s, str>>> {
+        let parts = path.0.into_iter().map(|(part, opt)| {
+            let part = match part {
+                Part::Index(i) => Part::Index(self.term(i)),
+                Part::Range(from, upto) => {
+                    Part::Range(from.map(|t| self.term(t)), upto.map(|t| self.term(t)))
+                }
+            };
+            (part, opt)
+        });
+        Path(parts.collect())
+    }
+
+    fn pattern(&mut self, pattern: Pattern<&'s str>) -> Pattern<Cow<'s, str>> {
+        match pattern {
+            Pattern::Var(x) => Pattern::Var(Cow::Borrowed(x)),
+            Pattern::Arr(patterns) => {
+                Pattern::Arr(patterns.into_iter().map(|p| self.pattern(p)).collect())
+            }
+            Pattern::Obj(entries) => Pattern::Obj(
+                entries
+                    .into_iter()
+                    .map(|(k, p)| (self.term(k), self.pattern(p)))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn str_parts(
+        &mut self,
+        parts: Vec<StrPart<&'s str, Term<&'s str>>>,
+    ) -> Vec<StrPart<Cow<'s, str>, Term<Cow<'s, str>>>> {
+        let parts = parts.into_iter().map(|part| match part {
+            StrPart::Str(s) => StrPart::Str(Cow::Borrowed(s)),
+            StrPart::Char(c) => StrPart::Char(c),
+            StrPart::Term(t) => StrPart::Term(self.term(t)),
+        });
+        parts.collect()
+    }
+}
+
+/// `l | r`, without pipes from or into the identity.
+fn pipe<'s>(l: Term<Cow<'s, str>>, r: Term<Cow<'s, str>>) -> Term<Cow<'s, str>> {
+    match (l, r) {
+        (Term::Id, r) => r,
+        (l, Term::Id) => l,
+        (l, Term::Path(head, path)) if matches!(*head, Term::Id) => {
+            match Const::from_term(&l).and_then(|c| c.get(&path)) {
+                Some(value) => value.into_term(),
+                None => Term::Pipe(Box::new(l), None, Box::new(Term::Path(head, path))),
+            }
+        }
+        (l, r) => Term::Pipe(Box::new(l), None, Box::new(r)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::optimize;
+    use crate::jq::print::print;
+    use crate::jq::template::parse_term;
+    use crate::jq::{JqOptions, JqTemplate};
+
+    fn optimized(code: &str) -> String {
+        print(&optimize(parse_term(code).unwrap()))
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        assert_eq!(optimized("\"a\" + \"b\""), "\"ab\"");
+        assert_eq!(optimized("1 + 2 + 3"), "6");
+        assert_eq!(optimized("[1] + [2, \"x\"]"), "[(1 , 2) , \"x\"]");
+        assert_eq!(
+            optimized("{a: 1} + {b: 2, a: 3}"),
+            "{(\"a\"): 3, (\"b\"): 2}"
+        );
+        assert_eq!(optimized("\"a\\n\" + .b"), "\"a\\n\" + .[\"b\"]");
+    }
+
+    #[test]
+    fn test_fold_paths() {
+        assert_eq!(optimized("{x: 1} | .x"), "1");
+        assert_eq!(optimized("{x: {y: \"z\"}}.x.y"), "\"z\"");
+        assert_eq!(optimized("{x: \"q\\\"q\"} | .x"), "\"q\\\"q\"");
+        assert_eq!(optimized("{x: 1} | .y"), "{(\"x\"): 1} | .[\"y\"]");
+        assert_eq!(optimized("{x: .a} | .x"), "{(\"x\"): .[\"a\"]} | .[\"x\"]");
+    }
+
+    #[test]
+    fn test_remove_identities() {
+        assert_eq!(optimized(". | .a | ."), ".[\"a\"]");
+        assert_eq!(optimized(".a, empty"), ".[\"a\"]");
+        assert_eq!(
+            optimized("def empty: 1; .a, empty"),
+            "def empty: 1; (.[\"a\"] , empty)"
+        );
+    }
+
+    #[test]
+    fn test_remove_dead_branches() {
+        assert_eq!(optimized("if \"yes\" then .a else .b end"), ".[\"a\"]");
+        assert_eq!(
+            optimized("if .x then .a elif 1 then .b else .c end"),
+            "if .[\"x\"] then .[\"a\"] else .[\"b\"] end"
+        );
+        assert_eq!(optimized("try \"a\" catch ."), "\"a\"");
+        assert_eq!(optimized("\"a\" // .b"), "\"a\"");
+    }
+
+    /// Programs with inputs on which optimized and unoptimized filters must agree.
+    #[test]
+    fn test_differential_corpus() {
+        let input = json!({"a": 1, "b": "text", "c": [1, 2], "d": {"e": "f"}, "x": null});
+        let corpus = [
+            "\"a\" + \"b\"",
+            "{x: 1} | .x",
+            "{x: {y: [1, 2]}}.x.y",
+            ". | .a | .",
+            ".a + 1 + 2",
+            "1 + 2 + .a",
+            "[.c[], 1 + 1]",
+            "{a: 1} + {b: .b} + {a: 2}",
+            "[1] + [2] + .c",
+            "if \"yes\" then .a else .b end",
+            "if .x then .a elif \"s\" then .b else .c end",
+            "try (\"a\" + \"b\") catch .",
+            "try .b.c catch \"caught\"",
+            ".x // (\"a\" + \"b\")",
+            "\"a\" // .b",
+            "(.a, empty, .b) | .",
+            "reduce .c[] as $x (0 + 0; . + $x)",
+            "def f: {k: .} | .k; .d | f",
+            ".d as {e: $e} | {e: $e} | .e",
+            "label $out | .c[] | ., break $out",
+            "{(\"a\" + \"b\"): .d.e}",
+            "\"a\\\"\" + \"b\"",
+            "{x: \"q\\\"q\"} | .x",
+            "\"\\\\\" + \"\\n\\t\\u0001\" + .b",
+        ];
+
+        for program in corpus {
+            let plain = JqTemplate::parse(program).unwrap().run(input.clone());
+            let options = JqOptions::default().optimize(true);
+            let optimized = JqTemplate::parse_with(program, options).unwrap();
+            assert_eq!(optimized.run(input.clone()), plain, "{program}");
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Order of object keys in the outputs of a jq filter.
+//!
+//! While a filter runs, objects keep their keys in insertion order on every
+//! backend: object construction and `from_entries` add keys in the order they
+//! are produced, updating a key keeps its position, and deleting a key keeps
+//! the order of the remaining ones. `serde_json` objects are sorted maps
+//! unless its `preserve_order` feature is enabled, which this crate's
+//! `preserve_order` feature does; it is opt-in since it changes
+//! `serde_json::Map` for every crate in the build.
+//!
+//! [`KeyOrder::Sorted`] sorts keys by codepoint once the filter is done, like
+//! jq's `--sort-keys`, so filters such as `keys_unsorted` or `to_entries` still
+//! see the insertion order.
+use crate::jsonlike::{JsonLike, JsonObjectLike};
+
+/// Key order of the objects returned by a [`JqTemplate`](super::JqTemplate).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Keys are returned in the order they were inserted.
+    #[default]
+    Insertion,
+    /// Keys of every object are sorted by codepoint.
+    Sorted,
+}
+
+impl KeyOrder {
+    pub(crate) fn apply<A>(self, value: A) -> A
+    where
+        A: for<'a> JsonLike<'a>,
+    {
+        match self {
+            KeyOrder::Insertion => value,
+            KeyOrder::Sorted => sort_keys(value),
+        }
+    }
+}
+
+fn sort_keys<A>(mut value: A) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    if let Some(arr) = value.as_array_mut() {
+        for item in arr.iter_mut() {
+            *item = sort_keys(std::mem::replace(item, A::null()));
+        }
+        return value;
+    }
+
+    let mut keys = match value.as_object() {
+        Some(obj) => obj
+            .iter()
+            .map(|(key, _)| key.to_string())
+            .collect::<Vec<_>>(),
+        None => return value,
+    };
+    keys.sort();
+
+    let mut sorted = Vec::with_capacity(keys.len());
+    if let Some(obj) = value.as_object_mut() {
+        for key in &keys {
+            if let Some(item) = obj.remove_key(key) {
+                sorted.push((key.as_str(), sort_keys(item)));
+            }
+        }
+    }
+    A::obj(sorted)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::KeyOrder;
+    use crate::jq::format::to_json;
+    use crate::jq::{JqOptions, JqTemplate};
+
+    /// Outputs of `code` as compact JSON.
+    fn run<A>(code: &str, input: A, order: KeyOrder) -> Vec<String>
+    where
+        A: for<'a> crate::jsonlike::JsonLike<'a>
+            + std::fmt::Display
+            + std::clone::Clone
+            + std::cmp::PartialEq
+            + 'static,
+    {
+        let options = JqOptions::default().key_order(order);
+        let template = JqTemplate::<A>::parse_with(code, options).unwrap();
+        let outputs = template.run(input).into_iter();
+        outputs.map(|output| to_json(&output.unwrap())).collect()
+    }
+
+    const CODE: &str = "{z: 1, a: {y: [{c: 2, b: 3}], x: 4}} | .m = 5 | del(.z) | .z = 6";
+
+    #[test]
+    fn test_insertion_order() {
+        let expected = vec![r#"{"a":{"y":[{"c":2,"b":3}],"x":4},"m":5,"z":6}"#.to_string()];
+        assert_eq!(run(CODE, ConstValue::Null, KeyOrder::Insertion), expected);
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(run(CODE, json!(null), KeyOrder::Insertion), expected);
+        assert_eq!(
+            run(
+                "{b: 1, a: 2} | keys_unsorted",
+                ConstValue::Null,
+                KeyOrder::Sorted
+            ),
+            vec![r#"["b","a"]"#.to_string()]
+        );
+    }
+
+    #[test]
+    fn test_sorted_order() {
+        let expected = vec![r#"{"a":{"x":4,"y":[{"b":3,"c":2}]},"m":5,"z":6}"#.to_string()];
+        assert_eq!(run(CODE, json!(null), KeyOrder::Sorted), expected);
+        assert_eq!(run(CODE, ConstValue::Null, KeyOrder::Sorted), expected);
+        assert_eq!(
+            run(".", json!({"é": 1, "z": 2, "B": 3}), KeyOrder::Sorted),
+            vec![r#"{"B":3,"z":2,"é":1}"#.to_string()]
+        );
+    }
+}
@@ -0,0 +1,737 @@
+//! Path builtins: `path`, `paths`, `leaf_paths`, `getpath`, `setpath`,
+//! `delpaths`, `to_entries` and `from_entries`, with `paths(f)`, `del` and
+//! `with_entries` defined on top of them.
+//!
+//! A path is an array of object keys and array indices, like the ones
+//! produced by `path(f)`. Negative indices count from the end of an array.
+//!
+//! jaq has no paths of its own, so `path(f)` runs `f` as an update that
+//! leaves every value as it is. The steps it takes into arrays and objects
+//! are [`track`]ed, giving the path of every value the update reaches.
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use jaq_core::box_iter::box_once;
+use jaq_core::compile::Lut;
+use jaq_core::{Bind, Cv, Error, Exn, FilterT, Native, ValX, ValXs};
+
+use super::format;
+use super::jq::{kind, JsonLikeHelper};
+use super::template::Fun;
+use crate::jsonlike::{JsonLike, JsonObjectLike};
+
+/// Component of a path. Indices sort before keys, like numbers before
+/// strings in jq.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Key<'p> {
+    Index(isize),
+    Field(&'p str),
+}
+
+/// Step of an update into an array or object.
+pub(crate) enum Step {
+    Key(String),
+    Index(isize),
+    Slice(Option<isize>, Option<isize>),
+}
+
+impl Step {
+    fn to_value<A>(&self) -> A
+    where
+        A: for<'a> JsonLike<'a>,
+    {
+        let index = |i: &Option<isize>| i.map_or_else(A::null, |i| A::number_f64(i as f64));
+        match self {
+            Step::Key(key) => A::string(Cow::Owned(key.clone())),
+            Step::Index(index) => A::number_f64(*index as f64),
+            Step::Slice(start, end) => A::obj(vec![("start", index(start)), ("end", index(end))]),
+        }
+    }
+}
+
+thread_local! {
+    /// Steps from the input of the innermost running `path(f)` to the value
+    /// being updated, `None` if no `path(f)` is running.
+    static TRACKED: RefCell<Option<Vec<Step>>> = const { RefCell::new(None) };
+}
+
+/// Runs `f`, which updates the child of a value at `step`. While `path(f)`
+/// runs, the step is part of the path of every value updated by `f`.
+pub(crate) fn track<T>(step: impl FnOnce() -> Step, f: impl FnOnce() -> T) -> T {
+    let tracked = TRACKED.with(|tracked| match tracked.borrow_mut().as_mut() {
+        Some(steps) => {
+            steps.push(step());
+            true
+        }
+        None => false,
+    });
+    let output = f();
+    if tracked {
+        TRACKED.with(|tracked| tracked.borrow_mut().as_mut().and_then(Vec::pop));
+    }
+    output
+}
+
+/// Paths of the values that `f` updates in `cv`, see [`track`].
+fn path<'a, A>(
+    lut: &'a Lut<Native<JsonLikeHelper<A>>>,
+    mut cv: Cv<'a, JsonLikeHelper<A>>,
+) -> ValXs<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let (f, fc) = cv.0.pop_fun();
+    let found = Rc::new(RefCell::new(Vec::new()));
+    let record = found.clone();
+    let outer = TRACKED.with(|tracked| tracked.replace(Some(Vec::new())));
+    let updated = f
+        .update(
+            lut,
+            (fc, cv.1),
+            Box::new(move |value| {
+                let path = TRACKED.with(|tracked| {
+                    let tracked = tracked.borrow();
+                    let steps = tracked.iter().flatten();
+                    A::array(steps.map(Step::to_value).collect())
+                });
+                record.borrow_mut().push(Ok(JsonLikeHelper(path)));
+                box_once(Ok(value))
+            }),
+        )
+        // errors end the paths found so far, like in jq
+        .find_map(|updated| updated.err());
+    TRACKED.with(|tracked| tracked.replace(outer));
+
+    let mut found = found.take();
+    found.extend(updated.map(Err));
+    Box::new(found.into_iter())
+}
+
+fn parse_path<A>(path: &A) -> Result<Vec<Key<'_>>, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let parts = path
+        .as_array()
+        .ok_or("Path must be specified as an array")?;
+    parts
+        .iter()
+        .map(|part| match (part.as_str(), part.as_f64()) {
+            (Some(key), _) => Ok(Key::Field(key)),
+            (_, Some(index)) => Ok(Key::Index(index.floor() as isize)),
+            _ => Err(format!("Cannot index with {}", kind(part))),
+        })
+        .collect()
+}
+
+fn index_error<A>(value: &A, key: &Key) -> String
+where
+    A: for<'a> JsonLike<'a>,
+{
+    match key {
+        Key::Field(key) => format!("Cannot index {} with \"{}\"", kind(value), key),
+        Key::Index(_) => format!("Cannot index {} with number", kind(value)),
+    }
+}
+
+/// Largest index `setpath` extends an array to, the same as jq's.
+const MAX_INDEX: usize = 536_870_911;
+
+fn abs_index(index: isize, len: usize) -> Option<usize> {
+    match index {
+        ..0 => len.checked_sub(index.unsigned_abs()),
+        _ => Some(index as usize),
+    }
+}
+
+/// Child of `value` at `key`, or `None` if it does not exist.
+fn child<'v, A>(value: &'v A, key: &Key) -> Result<Option<&'v A>, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    match key {
+        _ if value.is_null() => Ok(None),
+        Key::Field(field) => match value.as_object() {
+            Some(obj) => Ok(obj.get_key(field)),
+            None => Err(index_error(value, key)),
+        },
+        Key::Index(index) => match value.as_array() {
+            Some(arr) => Ok(abs_index(*index, arr.len()).and_then(|i| arr.get(i))),
+            None => Err(index_error(value, key)),
+        },
+    }
+}
+
+fn child_mut<'v, A>(value: &'v mut A, key: &Key) -> Result<Option<&'v mut A>, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    if value.is_null() {
+        return Ok(None);
+    }
+    let error = index_error(value, key);
+    match key {
+        Key::Field(field) => match value.as_object_mut() {
+            Some(obj) => Ok(obj.get_key_mut(field)),
+            None => Err(error),
+        },
+        Key::Index(index) => match value.as_array_mut() {
+            Some(arr) => Ok(abs_index(*index, arr.len()).and_then(|i| arr.get_mut(i))),
+            None => Err(error),
+        },
+    }
+}
+
+fn getpath<A>(value: &A, path: &[Key]) -> Result<A, String>
+where
+    A: for<'a> JsonLike<'a> + Clone,
+{
+    let mut current = value;
+    for key in path {
+        match child(current, key)? {
+            Some(value) => current = value,
+            None => return Ok(A::null()),
+        }
+    }
+    Ok(current.clone())
+}
+
+/// Replaces the value at `path`, creating objects and arrays on the way.
+fn setpath<A>(mut value: A, path: &[Key], new: A) -> Result<A, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let Some((key, rest)) = path.split_first() else {
+        return Ok(new);
+    };
+    let error = index_error(&value, key);
+    match key {
+        Key::Field(field) => {
+            if value.is_null() {
+                value = A::object(JsonObjectLike::new());
+            }
+            let obj = value.as_object_mut().ok_or(error)?;
+            match obj.get_key_mut(field) {
+                Some(child) => {
+                    let old = std::mem::replace(child, A::null());
+                    *child = setpath(old, rest, new)?;
+                }
+                None => obj.insert_key(field, setpath(A::null(), rest, new)?),
+            }
+        }
+        Key::Index(index) => {
+            if value.is_null() {
+                value = A::array(Vec::new());
+            }
+            let arr = value.as_array_mut().ok_or(error)?;
+            let index = abs_index(*index, arr.len()).ok_or("Out of bounds negative array index")?;
+            if index > MAX_INDEX {
+                return Err("Array index too large".to_string());
+            }
+            if index >= arr.len() {
+                arr.resize_with(index + 1, A::null);
+            }
+            let old = std::mem::replace(&mut arr[index], A::null());
+            arr[index] = setpath(old, rest, new)?;
+        }
+    }
+    Ok(value)
+}
+
+/// Removes the value at `path`, if there is one.
+fn delpath<A>(value: &mut A, path: &[Key]) -> Result<(), String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let Some((last, parent)) = path.split_last() else {
+        *value = A::null();
+        return Ok(());
+    };
+    let mut current = value;
+    for key in parent {
+        match child_mut(current, key)? {
+            Some(child) => current = child,
+            None => return Ok(()),
+        }
+    }
+    if current.is_null() {
+        return Ok(());
+    }
+    let error = format!("Cannot delete field at index of {}", kind(current));
+    match last {
+        Key::Field(field) => {
+            current.as_object_mut().ok_or(error)?.remove_key(field);
+        }
+        Key::Index(index) => {
+            let arr = current.as_array_mut().ok_or(error)?;
+            if let Some(index) = abs_index(*index, arr.len()).filter(|i| *i < arr.len()) {
+                arr.remove(index);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes every path in `paths`, the last ones first so that removing an
+/// array element does not shift the indices of the others.
+fn delpaths<A>(mut value: A, paths: &A) -> Result<A, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let paths = paths
+        .as_array()
+        .ok_or("Paths must be specified as an array")?;
+    let mut paths = paths
+        .iter()
+        .map(parse_path)
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+    paths.dedup();
+    for path in paths.iter().rev() {
+        delpath(&mut value, path)?;
+    }
+    Ok(value)
+}
+
+/// Collects the paths of every value below `value` in pre-order, or only of
+/// those that are neither arrays nor objects if `leaves` is set.
+fn paths<A>(value: &A, leaves: bool, prefix: &mut Vec<A>, out: &mut Vec<A>)
+where
+    A: for<'a> JsonLike<'a> + Clone,
+{
+    let mut visit = |key: A, item: &A, prefix: &mut Vec<A>| {
+        prefix.push(key);
+        let is_leaf = item.as_array().is_none() && item.as_object().is_none();
+        if !leaves || is_leaf {
+            out.push(A::array(prefix.clone()));
+        }
+        paths(item, leaves, prefix, out);
+        prefix.pop();
+    };
+    if let Some(arr) = value.as_array() {
+        for (i, item) in arr.iter().enumerate() {
+            visit(A::number_f64(i as f64), item, prefix);
+        }
+    } else if let Some(obj) = value.as_object() {
+        for (key, item) in obj.iter() {
+            visit(A::string(Cow::Owned(key.to_string())), item, prefix);
+        }
+    }
+}
+
+fn to_entries<A>(value: &A) -> Result<A, String>
+where
+    A: for<'a> JsonLike<'a> + Clone,
+{
+    let entry = |key, value: &A| A::obj(vec![("key", key), ("value", value.clone())]);
+    if let Some(arr) = value.as_array() {
+        let entries = arr.iter().enumerate();
+        Ok(A::array(
+            entries
+                .map(|(i, v)| entry(A::number_f64(i as f64), v))
+                .collect(),
+        ))
+    } else if let Some(obj) = value.as_object() {
+        let entries = obj.iter();
+        Ok(A::array(
+            entries
+                .map(|(k, v)| entry(A::string(Cow::Owned(k.to_string())), v))
+                .collect(),
+        ))
+    } else {
+        Err(format!(
+            "{} ({}) has no keys",
+            format::to_json(value),
+            kind(value)
+        ))
+    }
+}
+
+/// Builds an object of entries, accepting the same key and value names as jq.
+fn from_entries<A>(value: &A) -> Result<A, String>
+where
+    A: for<'a> JsonLike<'a> + Clone,
+{
+    let entries = value
+        .as_array()
+        .ok_or_else(|| format!("Cannot iterate over {}", kind(value)))?;
+    let mut obj = A::object(JsonObjectLike::new());
+    for entry in entries {
+        let fields = entry
+            .as_object()
+            .ok_or_else(|| format!("Cannot index {} with \"key\"", kind(entry)))?;
+        let truthy = |v: &&A| !v.is_null() && v.as_bool() != Some(false);
+        // like jq's `if .key == null then .k // .name // .Name // .K // .Key
+        // else .key end`, turned into a string with `tojson`
+        let key = fields
+            .get_key("key")
+            .filter(|key| !key.is_null())
+            .or_else(|| {
+                ["k", "name", "Name", "K"]
+                    .into_iter()
+                    .find_map(|name| fields.get_key(name).filter(truthy))
+                    .or_else(|| fields.get_key("Key"))
+            });
+        let key = match key.and_then(|key| key.as_str()) {
+            Some(key) => key.to_string(),
+            None => key.map_or_else(|| "null".to_string(), format::to_json),
+        };
+        let value = fields
+            .get_key("value")
+            .or_else(|| fields.get_key("v"))
+            .cloned()
+            .unwrap_or_else(A::null);
+        if let Some(obj) = obj.as_object_mut() {
+            obj.insert_key(&key, value);
+        }
+    }
+    Ok(obj)
+}
+
+fn err<'a, A>(message: String) -> ValX<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    Err(Exn::from(Error::str(message)))
+}
+
+/// Native filters for the path builtins.
+pub(crate) fn funs<A>() -> Vec<Fun<A>>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    fn all_paths<A>(cv: Cv<'_, JsonLikeHelper<A>>, leaves: bool) -> Vec<ValX<'_, JsonLikeHelper<A>>>
+    where
+        A: for<'a> JsonLike<'a>
+            + std::fmt::Display
+            + std::clone::Clone
+            + std::cmp::PartialEq
+            + 'static,
+    {
+        let mut out = Vec::new();
+        paths(&cv.1 .0, leaves, &mut Vec::new(), &mut out);
+        out.into_iter()
+            .map(|path| Ok(JsonLikeHelper(path)))
+            .collect()
+    }
+
+    vec![
+        (
+            "paths",
+            Box::new([]),
+            Native::new(|_, cv| Box::new(all_paths(cv, false).into_iter())),
+        ),
+        (
+            "leaf_paths",
+            Box::new([]),
+            Native::new(|_, cv| Box::new(all_paths(cv, true).into_iter())),
+        ),
+        (
+            "getpath",
+            Box::new([Bind::Var(())]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let path = cv.0.pop_var();
+                let output = parse_path(&path.0).and_then(|path| getpath(&cv.1 .0, &path));
+                box_once(output.map(JsonLikeHelper).or_else(err))
+            })
+            .with_update(|_, mut cv, f| {
+                let path = cv.0.pop_var();
+                let path = match parse_path(&path.0) {
+                    Ok(path) => path,
+                    Err(message) => return box_once(err(message)),
+                };
+                let output = getpath(&cv.1 .0, &path).map_err(|e| Exn::from(Error::str(e)));
+                let output = output.and_then(|old| match f(JsonLikeHelper(old)).next() {
+                    Some(new) => {
+                        setpath(cv.1 .0, &path, new?.0).map_err(|e| Exn::from(Error::str(e)))
+                    }
+                    None => {
+                        let mut value = cv.1 .0;
+                        delpath(&mut value, &path).map_err(|e| Exn::from(Error::str(e)))?;
+                        Ok(value)
+                    }
+                });
+                box_once(output.map(JsonLikeHelper))
+            }),
+        ),
+        (
+            "setpath",
+            Box::new([Bind::Var(()), Bind::Var(())]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let new = cv.0.pop_var();
+                let path = cv.0.pop_var();
+                let output = parse_path(&path.0).and_then(|path| setpath(cv.1 .0, &path, new.0));
+                box_once(output.map(JsonLikeHelper).or_else(err))
+            }),
+        ),
+        (
+            "delpaths",
+            Box::new([Bind::Var(())]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let paths = cv.0.pop_var();
+                box_once(delpaths(cv.1 .0, &paths.0).map(JsonLikeHelper).or_else(err))
+            }),
+        ),
+        ("path", Box::new([Bind::Fun(())]), Native::new(path)),
+        (
+            "to_entries",
+            Box::new([]),
+            Native::new(|_, cv| box_once(to_entries(&cv.1 .0).map(JsonLikeHelper).or_else(err))),
+        ),
+        (
+            "from_entries",
+            Box::new([]),
+            Native::new(|_, cv| box_once(from_entries(&cv.1 .0).map(JsonLikeHelper).or_else(err))),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use crate::jq::{JqError, JqTemplate};
+
+    fn run(code: &str, input: Value) -> Vec<Result<Value, JqError>> {
+        JqTemplate::parse(code).unwrap().run(input)
+    }
+
+    fn value(code: &str, input: Value) -> Value {
+        run(code, input).remove(0).unwrap()
+    }
+
+    fn values(code: &str, input: Value) -> Vec<Value> {
+        run(code, input).into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_paths() {
+        let input = json!({"a": [1, {"b": null}], "c": "d"});
+        assert_eq!(
+            values("paths", input.clone()),
+            vec![
+                json!(["a"]),
+                json!(["a", 0.0]),
+                json!(["a", 1.0]),
+                json!(["a", 1.0, "b"]),
+                json!(["c"]),
+            ]
+        );
+        assert_eq!(
+            values("leaf_paths", input.clone()),
+            vec![json!(["a", 0.0]), json!(["a", 1.0, "b"]), json!(["c"])]
+        );
+        assert_eq!(values("[paths(. == \"d\")]", input), vec![json!([["c"]])]);
+        assert_eq!(values("paths", json!(1)), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_getpath() {
+        let input = json!({"a": {"b": [1, 2, 3]}});
+        assert_eq!(
+            value("getpath([\"a\", \"b\", -1])", input.clone()),
+            json!(3)
+        );
+        assert_eq!(
+            value("getpath([\"a\", \"x\", \"y\"])", input.clone()),
+            json!(null)
+        );
+        assert_eq!(value("getpath([])", input.clone()), input);
+        assert_eq!(
+            run("getpath([\"a\", \"b\", \"c\"])", input),
+            vec![Err(JqError::Runtime(
+                "Cannot index array with \"c\"".to_string()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_setpath() {
+        assert_eq!(
+            value("setpath([\"a\", \"b\"]; 1)", json!({"a": {"c": 2}})),
+            json!({"a": {"c": 2, "b": 1.0}})
+        );
+        assert_eq!(
+            value("setpath([\"a\", 2]; true)", json!(null)),
+            json!({"a": [null, null, true]})
+        );
+        assert_eq!(value("setpath([-1]; 0)", json!([1, 2])), json!([1, 0.0]));
+        assert_eq!(value("setpath([]; 1)", json!([1])), json!(1.0));
+        assert_eq!(
+            run("setpath([-3]; 0)", json!([1, 2])),
+            vec![Err(JqError::Runtime(
+                "Out of bounds negative array index".to_string()
+            ))]
+        );
+        assert_eq!(
+            run("setpath([100000000000000]; 1)", json!([])),
+            vec![Err(JqError::Runtime("Array index too large".to_string()))]
+        );
+        assert_eq!(
+            value("setpath([1.7]; 0)", json!([1, 2, 3])),
+            json!([1, 0.0, 3])
+        );
+        assert_eq!(value("getpath([-1.5])", json!([1, 2, 3])), json!(2));
+        assert!(run("setpath([\"a\"]; 0)", json!([])).remove(0).is_err());
+        assert!(run("setpath(\"a\"; 0)", json!({})).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_getpath_update() {
+        assert_eq!(
+            value("getpath([\"a\", \"b\"]) |= 5", json!({"a": null})),
+            json!({"a": {"b": 5.0}})
+        );
+        assert_eq!(
+            value("getpath([\"a\", 0]) |= empty", json!({"a": [1, 2]})),
+            json!({"a": [2]})
+        );
+    }
+
+    #[test]
+    fn test_delpaths() {
+        let input = json!({"a": [0, 1, 2, 3], "b": {"c": 1, "d": 2}});
+        assert_eq!(
+            value(
+                "delpaths([[\"a\", 0], [\"a\", 2], [\"b\", \"c\"], [\"x\", \"y\"]])",
+                input.clone()
+            ),
+            json!({"a": [1, 3], "b": {"d": 2}})
+        );
+        assert_eq!(
+            value("delpaths([[\"a\", -1]])", input.clone()),
+            json!({"a": [0, 1, 2], "b": {"c": 1, "d": 2}})
+        );
+        assert_eq!(value("delpaths([[]])", input.clone()), json!(null));
+        assert_eq!(value("delpaths([])", input.clone()), input);
+        assert!(run("delpaths([[\"a\", \"x\"]])", input).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_path() {
+        let input = json!({"a": [{"b": 1}, {"b": 2}], "c": null, "n": 1});
+        assert_eq!(
+            values("path(.a[0].b, .c, .)", input.clone()),
+            vec![json!(["a", 0.0, "b"]), json!(["c"]), json!([])]
+        );
+        assert_eq!(
+            values("path(.a[] | .b)", input.clone()),
+            vec![json!(["a", 0.0, "b"]), json!(["a", 1.0, "b"])]
+        );
+        assert_eq!(
+            values(".n as $n | path(.a[-1], .a[$n:])", input.clone()),
+            vec![
+                json!(["a", -1.0]),
+                json!(["a", {"start": 1.0, "end": null}])
+            ]
+        );
+        assert_eq!(
+            values(
+                "[path(.a[] | if .b > 1 then . else empty end)]",
+                input.clone()
+            ),
+            vec![json!([["a", 1.0]])]
+        );
+        assert_eq!(
+            values(
+                "[path(.a[] | if [path(.b)] == [[\"b\"]] then .b else empty end)]",
+                input.clone()
+            ),
+            vec![json!([["a", 0.0, "b"], ["a", 1.0, "b"]])]
+        );
+        assert!(run("path(1)", input).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_del() {
+        assert_eq!(value("del(.[0])", json!([1, 2, 3])), json!([2, 3]));
+        assert_eq!(value("del(.)", json!([1])), json!(null));
+        assert_eq!(value("del(.[0], .[0])", json!([1, 2, 3])), json!([2, 3]));
+        assert_eq!(
+            value("del(.a, .a.b)", json!({"a": {"b": 1}, "c": 2})),
+            json!({"c": 2})
+        );
+        assert_eq!(value("del(.[0, 1])", json!([1, 2, 3])), json!([3]));
+        assert_eq!(value("del(.[1, 0, -1])", json!([1, 2, 3, 4])), json!([3]));
+        assert_eq!(
+            value("del(.a[0], .b)", json!({"a": [1, 2], "b": 3, "c": 4})),
+            json!({"a": [2], "c": 4})
+        );
+        assert_eq!(
+            value(
+                "del(.[] | if . > 1 then . else empty end)",
+                json!([1, 2, 3])
+            ),
+            json!([1])
+        );
+    }
+
+    #[test]
+    fn test_entries() {
+        let input = json!({"a": 1, "b": [true]});
+        assert_eq!(
+            value("to_entries", input.clone()),
+            json!([{"key": "a", "value": 1}, {"key": "b", "value": [true]}])
+        );
+        assert_eq!(value("to_entries | from_entries", input.clone()), input);
+        assert_eq!(
+            value("with_entries(.value = .key)", input),
+            json!({"a": "a", "b": "b"})
+        );
+        assert_eq!(
+            value(
+                "from_entries",
+                json!([{"k": "a", "v": 1}, {"name": 1}, {"Key": false, "value": 2}, {"K": true}])
+            ),
+            json!({"a": 1, "1": null, "false": 2, "true": null})
+        );
+        assert_eq!(
+            value("to_entries", json!(["x"])),
+            json!([{"key": 0.0, "value": "x"}])
+        );
+        assert_eq!(
+            value(
+                "from_entries",
+                json!([{"value": 1}, {"key": false, "value": 2}, {"key": null, "k": "k"}])
+            ),
+            json!({"null": 1, "false": 2, "k": null})
+        );
+        assert!(run("from_entries", json!([1])).remove(0).is_err());
+        assert!(run("to_entries", json!(1)).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_graphql_backend() {
+        let input = ConstValue::from_json(json!({"a": {"b": 1}, "c": [2]})).unwrap();
+        let run = |code: &str| {
+            let outputs = JqTemplate::<ConstValue>::parse(code)
+                .unwrap()
+                .run(input.clone());
+            let outputs = outputs
+                .into_iter()
+                .map(|output| output.unwrap().into_json().unwrap());
+            outputs.collect::<Vec<_>>()
+        };
+        assert_eq!(
+            run("paths"),
+            vec![
+                json!(["a"]),
+                json!(["a", "b"]),
+                json!(["c"]),
+                json!(["c", 0.0])
+            ]
+        );
+        assert_eq!(run("getpath([\"a\", \"b\"])"), vec![json!(1)]);
+        assert_eq!(
+            run("setpath([\"c\", 1]; 3) | delpaths([[\"a\"]])"),
+            vec![json!({"c": [2, 3.0]})]
+        );
+        assert_eq!(
+            run("with_entries(.key |= . + \"!\")"),
+            vec![json!({"a!": {"b": 1}, "c!": [2]})]
+        );
+    }
+}
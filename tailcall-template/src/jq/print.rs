@@ -0,0 +1,384 @@
+use std::fmt::Write;
+
+use jaq_core::load::lex::StrPart;
+use jaq_core::load::parse::{BinaryOp, Def, Pattern, Term};
+use jaq_core::path::{Opt, Part, Path};
+
+/// Prints a parsed jq term back into jq source code.
+///
+/// Compound sub-terms are always parenthesized, so the output does not depend
+/// on operator precedence. When `nodes` is set, every filter node except
+/// literals is wrapped into a call to `_trace($id; node)` and its source is
+/// recorded at `$id`.
+pub(crate) struct Printer<'n> {
+    out: String,
+    nodes: Option<&'n mut Vec<String>>,
+}
+
+/// Prints a term as jq source code.
+pub(crate) fn print<S: AsRef<str>>(term: &Term<S>) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        nodes: None,
+    };
+    printer.term_inner(term);
+    printer.out
+}
+
+/// Prints a term as jq source code, wrapping each filter node into `_trace`.
+/// Returns the instrumented code, the source of every node is pushed to
+/// `nodes` at the index used as its trace id.
+pub(crate) fn instrument<S: AsRef<str>>(term: &Term<S>, nodes: &mut Vec<String>) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        nodes: Some(nodes),
+    };
+    printer.term(term);
+    printer.out
+}
+
+impl Printer<'_> {
+    fn term<S: AsRef<str>>(&mut self, term: &Term<S>) {
+        let id = match (&mut self.nodes, term) {
+            (None, _) | (_, Term::Id | Term::Num(_)) => None,
+            (_, Term::Str(None, parts)) if !parts.iter().any(|p| matches!(p, StrPart::Term(_))) => {
+                None
+            }
+            (Some(nodes), term) => {
+                nodes.push(print(term));
+                Some(nodes.len() - 1)
+            }
+        };
+
+        match id {
+            Some(id) => {
+                let _ = write!(self.out, "_trace({}; ", id);
+                self.term_inner(term);
+                self.out.push(')');
+            }
+            None => self.term_inner(term),
+        }
+    }
+
+    /// Prints a term that has to be atomic, adding parentheses if needed.
+    fn atom<S: AsRef<str>>(&mut self, term: &Term<S>) {
+        let atomic = matches!(
+            term,
+            Term::Id
+                | Term::Recurse
+                | Term::Num(_)
+                | Term::Str(..)
+                | Term::Arr(_)
+                | Term::Obj(_)
+                | Term::Var(_)
+                | Term::Call(..)
+                | Term::Break(_)
+                | Term::Path(..)
+        );
+        if atomic && self.nodes.is_none() {
+            self.term(term);
+        } else {
+            self.out.push('(');
+            self.term(term);
+            self.out.push(')');
+        }
+    }
+
+    fn term_inner<S: AsRef<str>>(&mut self, term: &Term<S>) {
+        match term {
+            Term::Id => self.out.push('.'),
+            Term::Recurse => self.out.push_str(".."),
+            Term::Num(n) => self.out.push_str(n.as_ref()),
+            Term::Str(fmt, parts) => {
+                if let Some(fmt) = fmt {
+                    self.out.push_str(fmt.as_ref());
+                    self.out.push(' ');
+                }
+                self.str_parts(parts);
+            }
+            Term::Arr(None) => self.out.push_str("[]"),
+            Term::Arr(Some(t)) => {
+                self.out.push('[');
+                self.term(t);
+                self.out.push(']');
+            }
+            Term::Obj(entries) => {
+                self.out.push('{');
+                for (i, (k, v)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    match v {
+                        None => self.term_inner(k),
+                        Some(v) => {
+                            self.out.push('(');
+                            self.term_inner(k);
+                            self.out.push_str("): ");
+                            self.atom(v);
+                        }
+                    }
+                }
+                self.out.push('}');
+            }
+            Term::Neg(t) => {
+                self.out.push('-');
+                self.atom(t);
+            }
+            Term::Pipe(l, pat, r) => {
+                self.atom(l);
+                if let Some(pat) = pat {
+                    self.out.push_str(" as ");
+                    self.pattern(pat);
+                }
+                self.out.push_str(" | ");
+                self.atom(r);
+            }
+            Term::BinOp(l, op, r) => {
+                self.atom(l);
+                self.out.push(' ');
+                self.out.push_str(binary_op(op));
+                self.out.push(' ');
+                self.atom(r);
+            }
+            Term::Label(x, t) => {
+                let _ = write!(self.out, "label {} | ", x.as_ref());
+                self.atom(t);
+            }
+            Term::Break(x) => {
+                let _ = write!(self.out, "break {}", x.as_ref());
+            }
+            Term::Fold(name, xs, pat, args) => {
+                self.out.push_str(name.as_ref());
+                self.out.push(' ');
+                self.atom(xs);
+                self.out.push_str(" as ");
+                self.pattern(pat);
+                self.out.push(' ');
+                self.args(args);
+            }
+            Term::TryCatch(t, catch) => {
+                self.out.push_str("try ");
+                self.atom(t);
+                if let Some(catch) = catch {
+                    self.out.push_str(" catch ");
+                    self.atom(catch);
+                }
+            }
+            Term::IfThenElse(if_thens, else_) => {
+                for (i, (if_, then_)) in if_thens.iter().enumerate() {
+                    self.out.push_str(if i == 0 { "if " } else { " elif " });
+                    self.term(if_);
+                    self.out.push_str(" then ");
+                    self.term(then_);
+                }
+                if let Some(else_) = else_ {
+                    self.out.push_str(" else ");
+                    self.term(else_);
+                }
+                self.out.push_str(" end");
+            }
+            Term::Def(defs, t) => {
+                for def in defs {
+                    self.def(def);
+                    self.out.push(' ');
+                }
+                self.atom(t);
+            }
+            Term::Call(name, args) => {
+                self.out.push_str(name.as_ref());
+                if !args.is_empty() {
+                    self.args(args);
+                }
+            }
+            Term::Var(x) => self.out.push_str(x.as_ref()),
+            Term::Path(head, path) => {
+                match **head {
+                    Term::Id => self.out.push('.'),
+                    _ => self.atom(head),
+                }
+                self.path(path);
+            }
+        }
+    }
+
+    fn args<S: AsRef<str>>(&mut self, args: &[Term<S>]) {
+        self.out.push('(');
+        for (i, arg) in args.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str("; ");
+            }
+            self.term(arg);
+        }
+        self.out.push(')');
+    }
+
+    fn def<S: AsRef<str>>(&mut self, def: &Def<S>) {
+        let _ = write!(self.out, "def {}", def.name.as_ref());
+        if !def.args.is_empty() {
+            let _ = write!(
+                self.out,
+                "({})",
+                def.args
+                    .iter()
+                    .map(AsRef::as_ref)
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            );
+        }
+        self.out.push_str(": ");
+        self.term(&def.body);
+        self.out.push(';');
+    }
+
+    fn path<S: AsRef<str>>(&mut self, path: &Path<Term<S>>) {
+        for (part, opt) in &path.0 {
+            self.out.push('[');
+            match part {
+                Part::Index(i) => self.term(i),
+                Part::Range(from, upto) => {
+                    if let Some(from) = from {
+                        self.term(from);
+                    }
+                    if from.is_some() || upto.is_some() {
+                        self.out.push(':');
+                    }
+                    if let Some(upto) = upto {
+                        self.term(upto);
+                    }
+                }
+            }
+            self.out.push(']');
+            if let Opt::Optional = opt {
+                self.out.push('?');
+            }
+        }
+    }
+
+    fn pattern<S: AsRef<str>>(&mut self, pat: &Pattern<S>) {
+        match pat {
+            Pattern::Var(x) => self.out.push_str(x.as_ref()),
+            Pattern::Arr(pats) => {
+                self.out.push('[');
+                for (i, pat) in pats.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.pattern(pat);
+                }
+                self.out.push(']');
+            }
+            Pattern::Obj(entries) => {
+                self.out.push('{');
+                for (i, (k, pat)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.out.push('(');
+                    self.term_inner(k);
+                    self.out.push_str("): ");
+                    self.pattern(pat);
+                }
+                self.out.push('}');
+            }
+        }
+    }
+
+    fn str_parts<S: AsRef<str>>(&mut self, parts: &[StrPart<S, Term<S>>]) {
+        self.out.push('"');
+        for part in parts {
+            match part {
+                StrPart::Str(s) => {
+                    for c in s.as_ref().chars() {
+                        escape_char(&mut self.out, c);
+                    }
+                }
+                StrPart::Char(c) => escape_char(&mut self.out, *c),
+                StrPart::Term(t) => {
+                    self.out.push_str("\\(");
+                    self.term(t);
+                    self.out.push(')');
+                }
+            }
+        }
+        self.out.push('"');
+    }
+}
+
+fn escape_char(out: &mut String, c: char) {
+    match c {
+        '"' => out.push_str("\\\""),
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\x08' => out.push_str("\\b"),
+        '\x0C' => out.push_str("\\f"),
+        c if c.is_control() => {
+            let _ = write!(out, "\\u{:04x}", c as u32);
+        }
+        c => out.push(c),
+    }
+}
+
+fn binary_op(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Comma => ",",
+        BinaryOp::Alt => "//",
+        BinaryOp::Or => "or",
+        BinaryOp::And => "and",
+        BinaryOp::Math(op) => op.as_str(),
+        BinaryOp::Cmp(op) => op.as_str(),
+        BinaryOp::Assign => "=",
+        BinaryOp::Update => "|=",
+        BinaryOp::UpdateMath(op) => match op.as_str() {
+            "+" => "+=",
+            "-" => "-=",
+            "*" => "*=",
+            "/" => "/=",
+            _ => "%=",
+        },
+        BinaryOp::UpdateAlt => "//=",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jaq_core::load::{parse, Lexer, Parser};
+
+    fn roundtrip(code: &str) -> String {
+        let tokens = Lexer::new(code).lex().unwrap();
+        let term = Parser::new(&tokens).parse(|p| p.term()).unwrap();
+        super::print(&term)
+    }
+
+    #[test]
+    fn test_print_is_reparsable() {
+        let programs = [
+            ".a.b[0]",
+            ".[] | select(.id == 1)",
+            "{a: 1, \"b\": .c, $x, (.k): [.v]}",
+            "reduce .[] as [$a, $b] (0; . + $a)",
+            "if . then 1 elif . == null then 2 else 3 end",
+            "def f(g; $x): g + $x; f(.; 1)",
+            "try error(\"x\") catch .",
+            "label $out | .[] | if . > 1 then ., break $out else . end",
+            "@base64 \"id: \\(.id)\\n\"",
+            ".a |= . + 1 | .b //= 2",
+            "-(.a) , .[1:] , .[:2]?",
+        ];
+
+        for program in programs {
+            let printed = roundtrip(program);
+            assert!(
+                parse(&printed, |p| p.term()).is_some(),
+                "{program} printed as {printed}"
+            );
+            assert_eq!(roundtrip(&printed), printed);
+        }
+    }
+
+    #[test]
+    fn test_print_string_escapes() {
+        assert_eq!(roundtrip(r#""a\"b\\c\td""#), r#""a\"b\\c\td""#);
+    }
+}
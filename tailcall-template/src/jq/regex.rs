@@ -0,0 +1,752 @@
+//! jq's regex builtins: `test`, `match`, `capture`, `scan`, `split`,
+//! `splits`, `sub` and `gsub`.
+//!
+//! Regexes are compiled once per filter and reused across runs, see
+//! [`RegexCache`]. Offsets and lengths are counted in codepoints, like in jq.
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+use jaq_core::box_iter::box_once;
+use jaq_core::compile::Lut;
+use jaq_core::{Bind, Cv, Error, Exn, FilterT, Native, ValX, ValXs};
+use regex::{Regex, RegexBuilder};
+
+use super::env;
+use super::jq::{kind, JsonLikeHelper};
+use super::template::{Env, Fun};
+use crate::jsonlike::JsonLike;
+
+/// A compiled regex together with the flags that are not part of it.
+#[derive(Clone)]
+struct Compiled {
+    regex: Regex,
+    /// `g`: return all matches instead of only the first one.
+    global: bool,
+    /// `n`: ignore empty matches.
+    skip_empty: bool,
+}
+
+/// Number of regexes kept by a [`RegexCache`].
+const CACHE_CAPACITY: usize = 32;
+
+/// The regexes most recently compiled by a filter, keyed by pattern and
+/// flags. Patterns built from the input could be unbounded, so only the
+/// [`CACHE_CAPACITY`] most recently used ones are kept, most recent last.
+/// The cache is shared by every run of the filter, on any thread.
+#[derive(Default)]
+pub(crate) struct RegexCache(Mutex<Vec<((String, String), Compiled)>>);
+
+impl RegexCache {
+    fn entries(&self) -> MutexGuard<'_, Vec<((String, String), Compiled)>> {
+        // entries are only pushed and removed whole, so they stay valid
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn get(&self, pattern: &str, flags: &str) -> Result<Compiled, String> {
+        {
+            let mut entries = self.entries();
+            let position = entries
+                .iter()
+                .position(|((p, f), _)| p == pattern && f == flags);
+            if let Some(position) = position {
+                let entry = entries.remove(position);
+                let compiled = entry.1.clone();
+                entries.push(entry);
+                return Ok(compiled);
+            }
+        }
+
+        // compiled without holding the lock, other runs may use the cache
+        let compiled = compile(pattern, flags)?;
+        let mut entries = self.entries();
+        if entries.len() == CACHE_CAPACITY {
+            entries.remove(0);
+        }
+        let key = (pattern.to_string(), flags.to_string());
+        entries.push((key, compiled.clone()));
+        Ok(compiled)
+    }
+
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.entries().len()
+    }
+}
+
+fn compile(pattern: &str, flags: &str) -> Result<Compiled, String> {
+    let mut builder = RegexBuilder::new(pattern);
+    // like Oniguruma's Ruby syntax used by jq, `^` and `$` match at line breaks
+    builder.multi_line(true);
+    let (mut global, mut skip_empty) = (false, false);
+    for flag in flags.chars() {
+        match flag {
+            'g' => global = true,
+            'i' => {
+                builder.case_insensitive(true);
+            }
+            'x' => {
+                builder.ignore_whitespace(true);
+            }
+            's' => {
+                builder.multi_line(false);
+            }
+            'p' => {
+                builder.multi_line(false).ignore_whitespace(true);
+            }
+            'n' => skip_empty = true,
+            // accepted for compatibility, matches are always leftmost-first
+            'l' => (),
+            _ => return Err(format!("{} is not a valid modifier string", flags)),
+        }
+    }
+
+    let regex = builder
+        .build()
+        .map_err(|err| format!("{} is not a valid regex: {}", pattern, err))?;
+    Ok(Compiled {
+        regex,
+        global,
+        skip_empty,
+    })
+}
+
+/// A capture group of a match, with codepoint offsets.
+struct Group<'s> {
+    name: Option<&'s str>,
+    /// `None` if the group did not participate in the match.
+    found: Option<(usize, &'s str)>,
+}
+
+struct Match<'s> {
+    offset: usize,
+    string: &'s str,
+    /// Byte range of the match in the input.
+    range: std::ops::Range<usize>,
+    groups: Vec<Group<'s>>,
+}
+
+/// Finds the matches of `compiled` in `input`.
+fn find<'s>(compiled: &'s Compiled, input: &'s str, all: bool) -> Vec<Match<'s>> {
+    let names = compiled.regex.capture_names().skip(1).collect::<Vec<_>>();
+    // converts byte offsets to codepoint offsets, walking the input only once
+    let mut chars = (0, 0);
+    let mut offset = |byte: usize| {
+        if byte < chars.0 {
+            chars = (0, 0);
+        }
+        chars.1 += input[chars.0..byte].chars().count();
+        chars.0 = byte;
+        chars.1
+    };
+
+    let mut matches = Vec::new();
+    for captures in compiled.regex.captures_iter(input) {
+        let whole = captures.get(0).unwrap();
+        if compiled.skip_empty && whole.is_empty() {
+            continue;
+        }
+        let start = offset(whole.start());
+        let groups = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| Group {
+                name: *name,
+                found: captures.get(i + 1).map(|m| (offset(m.start()), m.as_str())),
+            })
+            .collect();
+        matches.push(Match {
+            offset: start,
+            string: whole.as_str(),
+            range: whole.range(),
+            groups,
+        });
+        if !all {
+            break;
+        }
+    }
+    matches
+}
+
+fn number<A>(n: usize) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    A::number_f64(n as f64)
+}
+
+fn string<A>(s: &str) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    A::string(s.to_string().into())
+}
+
+fn match_object<A>(m: &Match) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let captures = m.groups.iter().map(|group| {
+        let (offset, length, value) = match group.found {
+            Some((offset, s)) => (number(offset), number(s.chars().count()), string(s)),
+            None => (A::number_f64(-1.0), number(0), A::null()),
+        };
+        let name = group.name.map_or_else(A::null, string);
+        A::obj(vec![
+            ("offset", offset),
+            ("length", length),
+            ("string", value),
+            ("name", name),
+        ])
+    });
+    A::obj(vec![
+        ("offset", number(m.offset)),
+        ("length", number(m.string.chars().count())),
+        ("string", string(m.string)),
+        ("captures", A::array(captures.collect())),
+    ])
+}
+
+/// Object of the named captures of a match, as returned by `capture`.
+fn capture_object<A>(m: &Match) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    let captures = m.groups.iter().filter_map(|group| {
+        let value = group.found.map_or_else(A::null, |(_, s)| string(s));
+        Some((group.name?, value))
+    });
+    A::obj(captures.collect())
+}
+
+fn scan_value<A>(m: &Match) -> A
+where
+    A: for<'a> JsonLike<'a>,
+{
+    if m.groups.is_empty() {
+        return string(m.string);
+    }
+    let groups = m
+        .groups
+        .iter()
+        .map(|group| group.found.map_or_else(A::null, |(_, s)| string(s)));
+    A::array(groups.collect())
+}
+
+fn err<'a, A>(message: String) -> ValX<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    Err(Exn::from(Error::str(message)))
+}
+
+fn as_str<'v, A>(value: &'v JsonLikeHelper<A>, what: &str) -> Result<&'v str, String>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    value.0.as_str().ok_or_else(|| {
+        format!(
+            "{} ({}) cannot be {}, as it is not a string",
+            value,
+            kind(&value.0),
+            what
+        )
+    })
+}
+
+/// Compiles the regex given as `re` and `flags` arguments. Like in jq, `re`
+/// may also be an array `[regex, flags]`.
+fn regex<A>(re: &JsonLikeHelper<A>, flags: Option<&JsonLikeHelper<A>>) -> Result<Compiled, String>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let (pattern, array_flags) = match re.0.as_array().map(|arr| arr.as_slice()) {
+        Some([pattern]) => (pattern.as_str(), None),
+        Some([pattern, flags]) => (
+            pattern.as_str(),
+            flags.as_str().or(flags.is_null().then_some("")),
+        ),
+        _ => (re.0.as_str(), None),
+    };
+    let pattern = pattern.ok_or_else(|| {
+        format!(
+            "{} ({}) cannot be matched, as it is not a string",
+            re,
+            kind(&re.0)
+        )
+    })?;
+    let flags = match flags {
+        Some(flags) if flags.0.is_null() => "",
+        Some(flags) => flags
+            .0
+            .as_str()
+            .ok_or_else(|| format!("{} is not a string", flags))?,
+        None => array_flags.unwrap_or_default(),
+    };
+
+    match env::current::<Env<A>>() {
+        Some(env) => env.regexes.get(pattern, flags),
+        None => compile(pattern, flags),
+    }
+}
+
+/// Runs `f` with the input string and the compiled regex, reporting errors
+/// as jq errors.
+fn with_regex<'a, A>(
+    cv: &Cv<'a, JsonLikeHelper<A>>,
+    re: &JsonLikeHelper<A>,
+    flags: Option<&JsonLikeHelper<A>>,
+    f: impl FnOnce(&str, &Compiled) -> Vec<A>,
+) -> ValXs<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let compiled = regex(re, flags);
+    let input = as_str(&cv.1, "matched");
+    match (input, compiled) {
+        (Ok(input), Ok(compiled)) => {
+            let outputs = f(input, &compiled);
+            Box::new(outputs.into_iter().map(|v| Ok(JsonLikeHelper(v))))
+        }
+        (Err(message), _) | (_, Err(message)) => box_once(err(message)),
+    }
+}
+
+fn test<'a, A>(mut cv: Cv<'a, JsonLikeHelper<A>>, with_flags: bool) -> ValXs<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let flags = with_flags.then(|| cv.0.pop_var());
+    let re = cv.0.pop_var();
+    let compiled = regex(&re, flags.as_ref());
+    let input = as_str(&cv.1, "matched");
+    match (input, compiled) {
+        (Ok(input), Ok(compiled)) => {
+            let found = match compiled.skip_empty {
+                true => compiled.regex.find_iter(input).any(|m| !m.is_empty()),
+                false => compiled.regex.is_match(input),
+            };
+            box_once(Ok(JsonLikeHelper(A::boolean(found))))
+        }
+        (Err(message), _) | (_, Err(message)) => box_once(err(message)),
+    }
+}
+
+fn substitute<'a, A>(
+    lut: &'a Lut<Native<JsonLikeHelper<A>>>,
+    mut cv: Cv<'a, JsonLikeHelper<A>>,
+    with_flags: bool,
+    global: bool,
+) -> ValXs<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let flags = with_flags.then(|| cv.0.pop_var());
+    let (f, fc) = cv.0.pop_fun();
+    let re = cv.0.pop_var();
+
+    let compiled = match regex(&re, flags.as_ref()) {
+        Ok(compiled) => compiled,
+        Err(message) => return box_once(err(message)),
+    };
+    let input = match as_str(&cv.1, "matched") {
+        Ok(input) => input,
+        Err(message) => return box_once(err(message)),
+    };
+
+    // every output of the replacement filter yields a separate result
+    let mut results = vec![String::new()];
+    let mut last = 0;
+    for m in find(&compiled, input, global || compiled.global) {
+        let mut replacements = Vec::new();
+        let captures = JsonLikeHelper(capture_object::<A>(&m));
+        for replacement in f.run(lut, (fc.clone(), captures)) {
+            match replacement.map(|r| as_str(&r, "added to a string").map(str::to_string)) {
+                Ok(Ok(replacement)) => replacements.push(replacement),
+                Ok(Err(message)) => return box_once(err(message)),
+                Err(exn) => return box_once(Err(exn)),
+            }
+        }
+        let before = &input[last..m.range.start];
+        results = results
+            .iter()
+            .flat_map(|result| {
+                replacements
+                    .iter()
+                    .map(move |r| format!("{}{}{}", result, before, r))
+            })
+            .collect();
+        last = m.range.end;
+    }
+
+    let rest = &input[last..];
+    let results = results
+        .into_iter()
+        .map(|result| Ok(JsonLikeHelper(string(&(result + rest)))));
+    Box::new(results.collect::<Vec<_>>().into_iter())
+}
+
+/// Splits the input at every match of the regex.
+fn split<'s>(compiled: &Compiled, input: &'s str) -> Vec<&'s str> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for m in find(compiled, input, true) {
+        parts.push(&input[last..m.range.start]);
+        last = m.range.end;
+    }
+    parts.push(&input[last..]);
+    parts
+}
+
+/// Native filters for the regex builtins.
+pub(crate) fn funs<A>() -> Vec<Fun<A>>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let v = || Bind::Var(());
+    let f = || Bind::Fun(());
+    vec![
+        (
+            "test",
+            Box::new([v()]),
+            Native::new(|_, cv| test(cv, false)),
+        ),
+        (
+            "test",
+            Box::new([v(), v()]),
+            Native::new(|_, cv| test(cv, true)),
+        ),
+        (
+            "match",
+            Box::new([v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, None, |input, compiled| {
+                    let matches = find(compiled, input, compiled.global);
+                    matches.iter().map(match_object).collect()
+                })
+            }),
+        ),
+        (
+            "match",
+            Box::new([v(), v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let flags = cv.0.pop_var();
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, Some(&flags), |input, compiled| {
+                    let matches = find(compiled, input, compiled.global);
+                    matches.iter().map(match_object).collect()
+                })
+            }),
+        ),
+        (
+            "capture",
+            Box::new([v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, None, |input, compiled| {
+                    let matches = find(compiled, input, compiled.global);
+                    matches.iter().map(capture_object).collect()
+                })
+            }),
+        ),
+        (
+            "capture",
+            Box::new([v(), v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let flags = cv.0.pop_var();
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, Some(&flags), |input, compiled| {
+                    let matches = find(compiled, input, compiled.global);
+                    matches.iter().map(capture_object).collect()
+                })
+            }),
+        ),
+        (
+            "scan",
+            Box::new([v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, None, |input, compiled| {
+                    find(compiled, input, true).iter().map(scan_value).collect()
+                })
+            }),
+        ),
+        (
+            "scan",
+            Box::new([v(), v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let flags = cv.0.pop_var();
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, Some(&flags), |input, compiled| {
+                    find(compiled, input, true).iter().map(scan_value).collect()
+                })
+            }),
+        ),
+        (
+            "split",
+            Box::new([v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let separator = cv.0.pop_var();
+                let parts = as_str(&cv.1, "split").and_then(|input| {
+                    let separator = as_str(&separator, "used to split")?;
+                    let parts: Vec<A> = match (input.is_empty(), separator.is_empty()) {
+                        (true, _) => Vec::new(),
+                        // like jq, an empty separator splits into characters
+                        (false, true) => input
+                            .chars()
+                            .map(|c| string(c.encode_utf8(&mut [0; 4])))
+                            .collect(),
+                        (false, false) => input.split(separator).map(string).collect(),
+                    };
+                    Ok(A::array(parts))
+                });
+                box_once(parts.map(JsonLikeHelper).or_else(err))
+            }),
+        ),
+        (
+            "split",
+            Box::new([v(), v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let flags = cv.0.pop_var();
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, Some(&flags), |input, compiled| {
+                    vec![A::array(
+                        split(compiled, input).into_iter().map(string).collect(),
+                    )]
+                })
+            }),
+        ),
+        (
+            "splits",
+            Box::new([v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, None, |input, compiled| {
+                    split(compiled, input).into_iter().map(string).collect()
+                })
+            }),
+        ),
+        (
+            "splits",
+            Box::new([v(), v()]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let flags = cv.0.pop_var();
+                let re = cv.0.pop_var();
+                with_regex(&cv, &re, Some(&flags), |input, compiled| {
+                    split(compiled, input).into_iter().map(string).collect()
+                })
+            }),
+        ),
+        (
+            "sub",
+            Box::new([v(), f()]),
+            Native::new(|lut, cv| substitute(lut, cv, false, false)),
+        ),
+        (
+            "sub",
+            Box::new([v(), f(), v()]),
+            Native::new(|lut, cv| substitute(lut, cv, true, false)),
+        ),
+        (
+            "gsub",
+            Box::new([v(), f()]),
+            Native::new(|lut, cv| substitute(lut, cv, false, true)),
+        ),
+        (
+            "gsub",
+            Box::new([v(), f(), v()]),
+            Native::new(|lut, cv| substitute(lut, cv, true, true)),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use crate::jq::{JqError, JqTemplate};
+
+    fn run(code: &str, input: Value) -> Vec<Result<Value, JqError>> {
+        JqTemplate::parse(code).unwrap().run(input)
+    }
+
+    fn values(code: &str, input: Value) -> Vec<Value> {
+        run(code, input).into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn test_test() {
+        assert_eq!(values("test(\"a.c\")", json!("xabc")), vec![json!(true)]);
+        assert_eq!(values("test(\"A.C\")", json!("xabc")), vec![json!(false)]);
+        assert_eq!(
+            values("test(\"A.C\"; \"i\")", json!("xabc")),
+            vec![json!(true)]
+        );
+        assert_eq!(
+            values("test([\"A.C\", \"xi\"])", json!("xabc")),
+            vec![json!(true)]
+        );
+        assert_eq!(values("test(\"^b$\")", json!("a\nb")), vec![json!(true)]);
+        assert_eq!(
+            values("test(\"^b$\"; \"s\")", json!("a\nb")),
+            vec![json!(false)]
+        );
+    }
+
+    #[test]
+    fn test_match() {
+        let actual = values(
+            "match(\"(?<word>[a-z]+)(\\\\d)?\"; \"g\")",
+            json!("é ab1 c"),
+        );
+        let expected = vec![
+            json!({
+                "offset": 2.0, "length": 3.0, "string": "ab1",
+                "captures": [
+                    {"offset": 2.0, "length": 2.0, "string": "ab", "name": "word"},
+                    {"offset": 4.0, "length": 1.0, "string": "1", "name": null},
+                ]
+            }),
+            json!({
+                "offset": 6.0, "length": 1.0, "string": "c",
+                "captures": [
+                    {"offset": 6.0, "length": 1.0, "string": "c", "name": "word"},
+                    {"offset": -1.0, "length": 0.0, "string": null, "name": null},
+                ]
+            }),
+        ];
+        assert_eq!(actual, expected);
+        assert_eq!(values("match(\"x\")", json!("abc")), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_capture_and_scan() {
+        let input = json!("https://api.example.com/users/42/posts/7");
+        assert_eq!(
+            values(
+                "capture(\"users/(?<user>\\\\d+)/posts/(?<post>\\\\d+)\")",
+                input.clone()
+            ),
+            vec![json!({"user": "42", "post": "7"})]
+        );
+        assert_eq!(
+            values("[scan(\"\\\\d+\")]", input.clone()),
+            vec![json!(["42", "7"])]
+        );
+        assert_eq!(
+            values("[scan(\"/([a-z]+)/(\\\\d+)\")]", input),
+            vec![json!([["users", "42"], ["posts", "7"]])]
+        );
+        assert_eq!(
+            values("[scan(\"AB\"; \"i\")]", json!("abAB")),
+            vec![json!(["ab", "AB"])]
+        );
+    }
+
+    #[test]
+    fn test_split() {
+        assert_eq!(
+            values("split(\", \")", json!("a, b, c")),
+            vec![json!(["a", "b", "c"])]
+        );
+        assert_eq!(
+            values("split(\"\")", json!("abé")),
+            vec![json!(["a", "b", "é"])]
+        );
+        assert_eq!(values("split(\"\")", json!("")), vec![json!([])]);
+        assert_eq!(
+            values("split(\", *\"; null)", json!("a,b, c")),
+            vec![json!(["a", "b", "c"])]
+        );
+        assert_eq!(
+            values("[splits(\"[0-9]\")]", json!("a1b2")),
+            vec![json!(["a", "b", ""])]
+        );
+    }
+
+    #[test]
+    fn test_sub_and_gsub() {
+        let input = json!("id:12, id:34");
+        assert_eq!(
+            values("sub(\"id:\"; \"\")", input.clone()),
+            vec![json!("12, id:34")]
+        );
+        assert_eq!(
+            values("gsub(\"id:\"; \"\")", input.clone()),
+            vec![json!("12, 34")]
+        );
+        assert_eq!(
+            values("gsub(\"id:(?<n>\\\\d+)\"; \"<\\(.n)>\")", input.clone()),
+            vec![json!("<12>, <34>")]
+        );
+        assert_eq!(
+            values("sub(\"ID\"; \"x\"; \"gi\")", input.clone()),
+            vec![json!("x:12, x:34")]
+        );
+        assert_eq!(
+            values("sub(\"(?<d>\\\\d)\"; \"a\", \"b\")", json!("1")),
+            vec![json!("a"), json!("b")]
+        );
+        assert_eq!(
+            values("gsub(\"\"; \"-\")", json!("ab")),
+            vec![json!("-a-b-")]
+        );
+        assert_eq!(
+            values("gsub(\"\"; \"-\"; \"n\")", json!("ab")),
+            vec![json!("ab")]
+        );
+        assert_eq!(
+            values("test(\"x*\"; \"n\"), test(\"a*\"; \"n\")", json!("ba")),
+            vec![json!(false), json!(true)]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("test(\"a\")", json!(1)),
+            vec![Err(JqError::Runtime(
+                "1 (number) cannot be matched, as it is not a string".to_string()
+            ))]
+        );
+        assert!(run("test(\"(\")", json!("a"))[0].is_err());
+        assert_eq!(
+            run("test(\"a\"; \"q\")", json!("a")),
+            vec![Err(JqError::Runtime(
+                "q is not a valid modifier string".to_string()
+            ))]
+        );
+    }
+
+    #[test]
+    fn test_regex_cache() {
+        let template = JqTemplate::parse("[.[] | test(\"^a\"), test(\"^a\"; \"i\")]").unwrap();
+        let input = json!(["ab", "Ab", "b"]);
+        let expected = json!([true, true, false, true, false, false]);
+
+        assert_eq!(template.run(input.clone()), vec![Ok(expected.clone())]);
+        assert_eq!(template.run(input), vec![Ok(expected)]);
+        assert_eq!(template.regexes.len(), 2);
+    }
+
+    #[test]
+    fn test_regex_cache_capacity() {
+        let template = JqTemplate::parse("[.[] as $re | \"a1\" | test($re)]").unwrap();
+        let patterns = (0..100).map(|n| json!(format!("a{}", n % 10))).collect();
+        let mut expected = vec![false; 100];
+        (0..100).step_by(10).for_each(|n| expected[n + 1] = true);
+
+        assert_eq!(
+            template.run(Value::Array(patterns)),
+            vec![Ok(json!(expected))]
+        );
+        assert_eq!(template.regexes.len(), 10);
+
+        let patterns = (0..100).map(|n| json!(format!("b{}", n))).collect();
+        assert_eq!(
+            template.run(Value::Array(patterns)),
+            vec![Ok(json!(vec![false; 100]))]
+        );
+        assert_eq!(template.regexes.len(), super::CACHE_CAPACITY);
+    }
+}
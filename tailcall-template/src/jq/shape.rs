@@ -0,0 +1,722 @@
+//! Abstract interpretation of jq filters over the shape of their input.
+//!
+//! Instead of running a filter on a value, [`infer_shape`] runs it on a
+//! [`Shape`] describing all possible inputs and returns the shape of the
+//! outputs. Everything the pass doesn't understand becomes
+//! [`Shape::Unknown`], so the result is always safe to use for typing.
+use std::fmt::Display;
+
+use indexmap::IndexMap;
+use jaq_core::load::lex::StrPart;
+use jaq_core::load::parse::{BinaryOp, Pattern, Term};
+use jaq_core::ops::Math;
+use jaq_core::path::{Opt, Part, Path};
+
+use super::template::{parse_term, JqError};
+
+/// Shape of a JSON value. Shapes are non-null unless wrapped in
+/// [`Shape::Nullable`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Shape {
+    /// Any value, including null.
+    Unknown,
+    Null,
+    Boolean,
+    Number,
+    String,
+    List(Box<Shape>),
+    Object(IndexMap<String, Shape>),
+    Nullable(Box<Shape>),
+}
+
+impl Shape {
+    pub fn list(item: Shape) -> Self {
+        Shape::List(Box::new(item))
+    }
+
+    pub fn object<K: Into<String>>(fields: impl IntoIterator<Item = (K, Shape)>) -> Self {
+        Shape::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+    }
+
+    /// Makes the shape accept null as well.
+    pub fn nullable(self) -> Self {
+        match self {
+            Shape::Unknown | Shape::Null | Shape::Nullable(_) => self,
+            shape => Shape::Nullable(Box::new(shape)),
+        }
+    }
+
+    pub fn is_nullable(&self) -> bool {
+        matches!(self, Shape::Unknown | Shape::Null | Shape::Nullable(_))
+    }
+
+    /// Returns the shape without null, or `None` for [`Shape::Null`].
+    fn non_null(&self) -> Option<&Shape> {
+        match self {
+            Shape::Null => None,
+            Shape::Nullable(shape) => Some(shape),
+            shape => Some(shape),
+        }
+    }
+
+    /// Smallest shape that accepts values of both `self` and `other`.
+    pub fn union(self, other: Shape) -> Self {
+        if self == other {
+            return self;
+        }
+        let nullable = self.is_nullable() || other.is_nullable();
+        let shape = match (self.non_null(), other.non_null()) {
+            (None, None) => Shape::Null,
+            (Some(shape), None) | (None, Some(shape)) => shape.clone(),
+            (Some(Shape::List(a)), Some(Shape::List(b))) => {
+                Shape::list(a.as_ref().clone().union(b.as_ref().clone()))
+            }
+            (Some(Shape::Object(a)), Some(Shape::Object(b))) => {
+                let mut fields = IndexMap::new();
+                for (key, shape) in a {
+                    let field = match b.get(key) {
+                        Some(other) => shape.clone().union(other.clone()),
+                        None => shape.clone().nullable(),
+                    };
+                    fields.insert(key.clone(), field);
+                }
+                for (key, shape) in b {
+                    if !a.contains_key(key) {
+                        fields.insert(key.clone(), shape.clone().nullable());
+                    }
+                }
+                Shape::Object(fields)
+            }
+            (Some(a), Some(b)) if a == b => a.clone(),
+            _ => Shape::Unknown,
+        };
+        if nullable {
+            shape.nullable()
+        } else {
+            shape
+        }
+    }
+}
+
+impl Display for Shape {
+    /// Formats the shape in GraphQL notation, with `!` marking non-null shapes.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (shape, non_null) = match self {
+            Shape::Nullable(shape) => (shape.as_ref(), false),
+            Shape::Unknown | Shape::Null => (self, false),
+            shape => (shape, true),
+        };
+        match shape {
+            Shape::Unknown => write!(f, "Unknown")?,
+            Shape::Null => write!(f, "Null")?,
+            Shape::Boolean => write!(f, "Boolean")?,
+            Shape::Number => write!(f, "Number")?,
+            Shape::String => write!(f, "String")?,
+            Shape::List(item) => write!(f, "[{}]", item)?,
+            Shape::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (key, shape)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: {}", key, shape)?;
+                }
+                write!(f, "}}")?;
+            }
+            Shape::Nullable(shape) => write!(f, "{}", shape)?,
+        }
+        if non_null {
+            write!(f, "!")?;
+        }
+        Ok(())
+    }
+}
+
+/// Problem found while inferring the output shape of a filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeWarning {
+    /// The filter reads a field that is not part of the input shape.
+    MissingField { path: String },
+    /// The filter indexes or iterates a value that does not support it.
+    TypeMismatch { path: String, shape: Shape },
+}
+
+impl Display for ShapeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShapeWarning::MissingField { path } => write!(f, "missing field {}", path),
+            ShapeWarning::TypeMismatch { path, shape } => {
+                write!(f, "cannot index {} of shape {}", path, shape)
+            }
+        }
+    }
+}
+
+/// Result of [`infer_shape`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeInference {
+    /// Shape of every output of the filter, [`Shape::Null`] if the filter
+    /// never yields a value.
+    pub shape: Shape,
+    pub warnings: Vec<ShapeWarning>,
+}
+
+/// Infers the shape of the outputs of the jq filter `code` when it runs on
+/// inputs of shape `input`.
+pub fn infer_shape(code: &str, input: &Shape) -> Result<ShapeInference, JqError> {
+    let term = parse_term(code)?;
+    let mut infer = Infer::default();
+    let input = Abstract {
+        shape: input.clone(),
+        path: Some(String::new()),
+    };
+    let shape = infer.term(&term, &input).map(|output| output.shape);
+    Ok(ShapeInference {
+        shape: shape.unwrap_or(Shape::Null),
+        warnings: infer.warnings,
+    })
+}
+
+/// Abstract value: a shape and, if the value was read from the input, the
+/// path it was read from.
+#[derive(Debug, Clone)]
+struct Abstract {
+    shape: Shape,
+    path: Option<String>,
+}
+
+impl Abstract {
+    fn new(shape: Shape) -> Self {
+        Self { shape, path: None }
+    }
+
+    fn unknown() -> Self {
+        Self::new(Shape::Unknown)
+    }
+
+    fn child(&self, shape: Shape, segment: &str) -> Self {
+        let path = self
+            .path
+            .as_ref()
+            .map(|path| format!("{}{}", path, segment));
+        Self { shape, path }
+    }
+
+    fn path(&self) -> String {
+        match self.path.as_deref() {
+            None | Some("") => ".".to_string(),
+            Some(path) => path.to_string(),
+        }
+    }
+}
+
+/// Union of two optional outputs, where `None` stands for no output at all.
+fn union(a: Option<Abstract>, b: Option<Abstract>) -> Option<Abstract> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            let path = if a.path == b.path { a.path } else { None };
+            Some(Abstract {
+                shape: a.shape.union(b.shape),
+                path,
+            })
+        }
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
+
+/// Returns the literal value of a string term without interpolation.
+fn literal(term: &Term<&str>) -> Option<String> {
+    let Term::Str(None, parts) = term else {
+        return None;
+    };
+    let mut out = String::new();
+    for part in parts {
+        match part {
+            StrPart::Str(s) => out.push_str(s),
+            StrPart::Char(c) => out.push(*c),
+            StrPart::Term(_) => return None,
+        }
+    }
+    Some(out)
+}
+
+#[derive(Default)]
+struct Infer<'s> {
+    vars: Vec<(&'s str, Abstract)>,
+    /// Names and arities of the filters defined in the program, which shadow
+    /// the builtins.
+    defs: Vec<(&'s str, usize)>,
+    warnings: Vec<ShapeWarning>,
+}
+
+impl<'s> Infer<'s> {
+    fn warn(&mut self, warning: ShapeWarning) {
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    fn term(&mut self, term: &Term<&'s str>, input: &Abstract) -> Option<Abstract> {
+        match term {
+            Term::Id => Some(input.clone()),
+            Term::Recurse => Some(Abstract::unknown()),
+            Term::Num(_) => Some(Abstract::new(Shape::Number)),
+            Term::Str(_, parts) => {
+                for part in parts {
+                    if let StrPart::Term(t) = part {
+                        self.term(t, input);
+                    }
+                }
+                Some(Abstract::new(Shape::String))
+            }
+            Term::Arr(None) => Some(Abstract::new(Shape::list(Shape::Unknown))),
+            Term::Arr(Some(t)) => {
+                let item = self
+                    .term(t, input)
+                    .map_or(Shape::Unknown, |item| item.shape);
+                Some(Abstract::new(Shape::list(item)))
+            }
+            Term::Obj(entries) => self.object(entries, input),
+            Term::Neg(t) => {
+                self.term(t, input)?;
+                Some(Abstract::new(Shape::Number))
+            }
+            Term::Pipe(l, None, r) => {
+                let l = self.term(l, input)?;
+                self.term(r, &l)
+            }
+            Term::Pipe(l, Some(pattern), r) => {
+                let l = self.term(l, input)?;
+                let len = self.vars.len();
+                self.bind(pattern, &l);
+                let output = self.term(r, input);
+                self.vars.truncate(len);
+                output
+            }
+            Term::BinOp(l, op, r) => self.binary_op(l, op, r, input),
+            Term::Label(_, t) => self.term(t, input),
+            Term::Break(_) => None,
+            Term::Fold(name, xs, pattern, args) => {
+                let x = self.term(xs, input);
+                let (init, update) = match &args[..] {
+                    [init, update] | [init, update, _] => (init, update),
+                    _ => return Some(Abstract::unknown()),
+                };
+                let init = self.term(init, input)?;
+                let len = self.vars.len();
+                self.bind(pattern, &x.unwrap_or_else(Abstract::unknown));
+                let update = self.term(update, &init);
+                self.vars.truncate(len);
+                match (*name, update) {
+                    ("reduce", Some(update)) if update.shape == init.shape => Some(init),
+                    _ => Some(Abstract::unknown()),
+                }
+            }
+            Term::TryCatch(t, catch) => {
+                let output = self.term(t, input);
+                match catch {
+                    Some(catch) => {
+                        let caught = self.term(catch, &Abstract::unknown());
+                        union(output, caught)
+                    }
+                    None => output,
+                }
+            }
+            Term::IfThenElse(if_thens, else_) => {
+                let mut output = None;
+                for (if_, then_) in if_thens {
+                    self.term(if_, input);
+                    let then_ = self.term(then_, input);
+                    output = union(output, then_);
+                }
+                let else_ = match else_ {
+                    Some(else_) => self.term(else_, input),
+                    None => Some(input.clone()),
+                };
+                union(output, else_)
+            }
+            Term::Def(defs, t) => {
+                let len = self.defs.len();
+                self.defs
+                    .extend(defs.iter().map(|def| (def.name, def.args.len())));
+                let output = self.term(t, input);
+                self.defs.truncate(len);
+                output
+            }
+            Term::Call(name, args) => self.call(name, args, input),
+            Term::Var(name) => {
+                let var = self.vars.iter().rev().find(|(var, _)| var == name);
+                Some(var.map_or_else(Abstract::unknown, |(_, value)| value.clone()))
+            }
+            Term::Path(head, path) => {
+                let head = self.term(head, input)?;
+                self.path(path, head, input)
+            }
+        }
+    }
+
+    fn object(
+        &mut self,
+        entries: &[(Term<&'s str>, Option<Term<&'s str>>)],
+        input: &Abstract,
+    ) -> Option<Abstract> {
+        let mut fields = IndexMap::new();
+        let mut known = true;
+        for (key, value) in entries {
+            let (key, value) = match (key, value) {
+                (Term::Var(name), None) => {
+                    let value = self.term(key, input)?;
+                    (Some(name.trim_start_matches('$').to_string()), value)
+                }
+                (key, None) => {
+                    let name = literal(key);
+                    let value = match &name {
+                        Some(name) => self.field(&input.clone(), name, Opt::Essential),
+                        None => Some(Abstract::unknown()),
+                    };
+                    (name, value?)
+                }
+                (key, Some(value)) => {
+                    self.term(key, input)?;
+                    (literal(key), self.term(value, input)?)
+                }
+            };
+            match key {
+                Some(key) => {
+                    fields.insert(key, value.shape);
+                }
+                None => known = false,
+            }
+        }
+
+        match known {
+            true => Some(Abstract::new(Shape::Object(fields))),
+            false => Some(Abstract::unknown()),
+        }
+    }
+
+    fn bind(&mut self, pattern: &Pattern<&'s str>, value: &Abstract) {
+        match pattern {
+            Pattern::Var(name) => self.vars.push((name, value.clone())),
+            Pattern::Arr(patterns) => {
+                let item = match value.shape.non_null() {
+                    Some(Shape::List(item)) => value.child(item.as_ref().clone().nullable(), "[]"),
+                    _ => Abstract::unknown(),
+                };
+                for pattern in patterns {
+                    self.bind(pattern, &item);
+                }
+            }
+            Pattern::Obj(entries) => {
+                for (key, pattern) in entries {
+                    let field = match literal(key) {
+                        Some(name) => self.field(value, &name, Opt::Essential),
+                        None => Some(Abstract::unknown()),
+                    };
+                    let field = field.unwrap_or_else(Abstract::unknown);
+                    if let Term::Var(name) = key {
+                        self.vars.push((name, field.clone()));
+                    }
+                    self.bind(pattern, &field);
+                }
+            }
+        }
+    }
+
+    fn binary_op(
+        &mut self,
+        l: &Term<&'s str>,
+        op: &BinaryOp,
+        r: &Term<&'s str>,
+        input: &Abstract,
+    ) -> Option<Abstract> {
+        match op {
+            BinaryOp::Comma => {
+                let l = self.term(l, input);
+                let r = self.term(r, input);
+                union(l, r)
+            }
+            BinaryOp::Alt => {
+                let l = self.term(l, input).and_then(|l| match l.shape.non_null() {
+                    Some(shape) => Some(Abstract {
+                        shape: shape.clone(),
+                        path: l.path,
+                    }),
+                    None => None,
+                });
+                let r = self.term(r, input);
+                union(l, r)
+            }
+            BinaryOp::Or | BinaryOp::And | BinaryOp::Cmp(_) => {
+                self.term(l, input)?;
+                self.term(r, input)?;
+                Some(Abstract::new(Shape::Boolean))
+            }
+            BinaryOp::Math(op) => {
+                let l = self.term(l, input)?.shape;
+                let r = self.term(r, input)?.shape;
+                let shape = match (op, l, r) {
+                    (Math::Add, Shape::Null, shape) | (Math::Add, shape, Shape::Null) => shape,
+                    (_, Shape::Number, Shape::Number) => Shape::Number,
+                    (Math::Add, Shape::String, Shape::String) => Shape::String,
+                    (Math::Add, Shape::List(l), Shape::List(r)) => Shape::list(l.union(*r)),
+                    (Math::Add, Shape::Object(mut l), Shape::Object(r)) => {
+                        l.extend(r);
+                        Shape::Object(l)
+                    }
+                    _ => Shape::Unknown,
+                };
+                Some(Abstract::new(shape))
+            }
+            BinaryOp::Assign | BinaryOp::Update | BinaryOp::UpdateMath(_) | BinaryOp::UpdateAlt => {
+                self.term(l, input)?;
+                Some(Abstract::unknown())
+            }
+        }
+    }
+
+    fn call(&mut self, name: &str, args: &[Term<&'s str>], input: &Abstract) -> Option<Abstract> {
+        if self.defs.contains(&(name, args.len())) {
+            return Some(Abstract::unknown());
+        }
+        let shape = match (name, args) {
+            ("empty" | "error", _) => return None,
+            ("null", []) => Shape::Null,
+            ("true" | "false" | "not", []) => Shape::Boolean,
+            ("length" | "tonumber", []) => Shape::Number,
+            ("tostring" | "tojson" | "type" | "ascii_downcase" | "ascii_upcase", []) => {
+                Shape::String
+            }
+            ("keys", []) => Shape::list(Shape::String),
+            ("debug" | "stderr", []) | ("debug", [_]) => return Some(input.clone()),
+            ("select", [f]) => {
+                self.term(f, input);
+                return Some(input.clone());
+            }
+            ("map", [f]) => match input.shape.non_null() {
+                Some(Shape::List(item)) => {
+                    let item = input.child(item.as_ref().clone(), "[]");
+                    let shape = self
+                        .term(f, &item)
+                        .map_or(Shape::Unknown, |item| item.shape);
+                    Shape::list(shape)
+                }
+                _ => Shape::list(Shape::Unknown),
+            },
+            ("add", []) => match input.shape.non_null() {
+                Some(Shape::List(item)) => item.as_ref().clone().nullable(),
+                _ => Shape::Unknown,
+            },
+            _ => Shape::Unknown,
+        };
+        Some(Abstract::new(shape))
+    }
+
+    fn path(
+        &mut self,
+        path: &Path<Term<&'s str>>,
+        head: Abstract,
+        input: &Abstract,
+    ) -> Option<Abstract> {
+        path.0
+            .iter()
+            .try_fold(head, |value, (part, opt)| match part {
+                Part::Index(index) => match literal(index) {
+                    Some(name) => self.field(&value, &name, *opt),
+                    None => {
+                        let index = self.term(index, input);
+                        self.index(&value, index, *opt)
+                    }
+                },
+                Part::Range(None, None) => self.iterate(&value, *opt),
+                Part::Range(from, upto) => {
+                    for bound in [from, upto].into_iter().flatten() {
+                        self.term(bound, input);
+                    }
+                    match value.shape.non_null() {
+                        Some(Shape::List(_) | Shape::String) | None => Some(value),
+                        Some(Shape::Unknown) => Some(Abstract::unknown()),
+                        Some(shape) => self.mismatch(&value, shape.clone(), *opt),
+                    }
+                }
+            })
+    }
+
+    /// `.name` on `value`.
+    fn field(&mut self, value: &Abstract, name: &str, opt: Opt) -> Option<Abstract> {
+        let segment = format!(".{}", name);
+        match value.shape.non_null() {
+            None => Some(value.child(Shape::Null, &segment)),
+            Some(Shape::Unknown) => Some(value.child(Shape::Unknown, &segment)),
+            Some(Shape::Object(fields)) => {
+                let child = match fields.get(name) {
+                    Some(field) if value.shape.is_nullable() => field.clone().nullable(),
+                    Some(field) => field.clone(),
+                    None => {
+                        let child = value.child(Shape::Null, &segment);
+                        self.warn(ShapeWarning::MissingField { path: child.path() });
+                        return Some(child);
+                    }
+                };
+                Some(value.child(child, &segment))
+            }
+            Some(shape) => self.mismatch(value, shape.clone(), opt),
+        }
+    }
+
+    /// `.[index]` on `value`, with an index that is not a string literal.
+    fn index(&mut self, value: &Abstract, index: Option<Abstract>, opt: Opt) -> Option<Abstract> {
+        let index = index?;
+        match (value.shape.non_null(), &index.shape) {
+            (None, _) => Some(value.child(Shape::Null, "[]")),
+            (Some(Shape::List(item)), Shape::Number) => {
+                Some(value.child(item.as_ref().clone().nullable(), "[]"))
+            }
+            (Some(Shape::Object(_)), Shape::String) => Some(Abstract::unknown()),
+            (Some(Shape::Unknown), _) | (_, Shape::Unknown) => Some(Abstract::unknown()),
+            (Some(shape), _) => self.mismatch(value, shape.clone(), opt),
+        }
+    }
+
+    /// `.[]` on `value`.
+    fn iterate(&mut self, value: &Abstract, opt: Opt) -> Option<Abstract> {
+        match value.shape.non_null() {
+            Some(Shape::List(item)) => Some(value.child(item.as_ref().clone(), "[]")),
+            Some(Shape::Object(fields)) => {
+                let shape = fields.values().cloned().reduce(Shape::union)?;
+                Some(Abstract::new(shape))
+            }
+            Some(Shape::Unknown) => Some(Abstract::unknown()),
+            Some(shape) => self.mismatch(value, shape.clone(), opt),
+            None => self.mismatch(value, Shape::Null, opt),
+        }
+    }
+
+    fn mismatch(&mut self, value: &Abstract, shape: Shape, opt: Opt) -> Option<Abstract> {
+        if let Opt::Essential = opt {
+            self.warn(ShapeWarning::TypeMismatch {
+                path: value.path(),
+                shape,
+            });
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{infer_shape, Shape, ShapeWarning};
+
+    fn user() -> Shape {
+        Shape::object([
+            ("id", Shape::Number),
+            ("name", Shape::String),
+            ("email", Shape::String.nullable()),
+            (
+                "posts",
+                Shape::list(Shape::object([
+                    ("title", Shape::String),
+                    ("tags", Shape::list(Shape::String)),
+                ])),
+            ),
+            (
+                "address",
+                Shape::object([("city", Shape::String)]).nullable(),
+            ),
+        ])
+    }
+
+    fn infer(code: &str) -> (String, Vec<String>) {
+        let inference = infer_shape(code, &user()).unwrap();
+        let warnings = inference.warnings.iter().map(|w| w.to_string()).collect();
+        (inference.shape.to_string(), warnings)
+    }
+
+    #[test]
+    fn test_infer_paths() {
+        assert_eq!(infer(".name").0, "String!");
+        assert_eq!(infer(".email").0, "String");
+        assert_eq!(infer(".address.city").0, "String");
+        assert_eq!(infer(".posts[].title").0, "String!");
+        assert_eq!(infer(".posts[0].tags").0, "[String!]");
+        assert_eq!(infer("[.posts[].tags[]]").0, "[String!]!");
+    }
+
+    #[test]
+    fn test_infer_constructors() {
+        assert_eq!(
+            infer("{id, title: .name, count: (.posts | length)}").0,
+            "{id: Number!, title: String!, count: Number!}!"
+        );
+        assert_eq!(infer(".posts | map({title})").0, "[{title: String!}!]!");
+        assert_eq!(infer("\"\\(.id): \\(.name)\"").0, "String!");
+        assert_eq!(infer(".name + \"!\"").0, "String!");
+        assert_eq!(infer(".id > 1").0, "Boolean!");
+    }
+
+    #[test]
+    fn test_infer_branches() {
+        assert_eq!(infer(".email // \"none\"").0, "String!");
+        assert_eq!(infer("if .id > 1 then .name else null end").0, "String");
+        assert_eq!(infer(".name, .id").0, "Unknown");
+        assert_eq!(
+            infer(".posts[] | select(.title == \"a\")").0,
+            "{title: String!, tags: [String!]!}!"
+        );
+        assert_eq!(infer(".name as $n | {n: $n}").0, "{n: String!}!");
+        assert_eq!(infer("empty").0, "Null");
+    }
+
+    #[test]
+    fn test_infer_unknown() {
+        assert_eq!(infer(".. | .id").0, "Unknown");
+        assert_eq!(infer("custom(.)").0, "Unknown");
+        assert_eq!(infer(".name |= 1").0, "Unknown");
+        assert_eq!(infer("def length: \"x\"; length").0, "Unknown");
+        assert_eq!(infer("def empty: 1; [empty]").0, "[Unknown]!");
+        assert_eq!(infer("def length(f): f; length").0, "Number!");
+    }
+
+    #[test]
+    fn test_missing_field_warnings() {
+        let (shape, warnings) = infer("{a: .nmae, b: .posts[].titel, c: .address.zip}");
+        assert_eq!(shape, "{a: Null, b: Null, c: Null}!");
+        assert_eq!(
+            warnings,
+            vec![
+                "missing field .nmae",
+                "missing field .posts[].titel",
+                "missing field .address.zip",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_mismatch_warnings() {
+        let inference = infer_shape(".name.first, .id[]?", &user()).unwrap();
+        assert_eq!(
+            inference.warnings,
+            vec![ShapeWarning::TypeMismatch {
+                path: ".name".to_string(),
+                shape: Shape::String
+            }]
+        );
+    }
+
+    #[test]
+    fn test_union() {
+        let a = Shape::object([("a", Shape::Number), ("b", Shape::String)]);
+        let b = Shape::object([("a", Shape::Number)]);
+        assert_eq!(a.union(b).to_string(), "{a: Number!, b: String}!");
+        assert_eq!(Shape::Null.union(Shape::Number), Shape::Number.nullable());
+        assert_eq!(Shape::String.union(Shape::Number), Shape::Unknown);
+    }
+
+    #[test]
+    fn test_parse_error() {
+        assert!(infer_shape(".a |", &Shape::Unknown).is_err());
+    }
+}
@@ -0,0 +1,526 @@
+use std::cell::RefCell;
+use std::fmt::Display;
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use jaq_core::load::lex::Token;
+use jaq_core::load::parse::Term;
+use jaq_core::load::{self, Arena, File, Lexer, Loader, Parser};
+use jaq_core::{Bind, Compiler, Ctx, Filter, Native, RcIter};
+
+use super::env;
+use super::format;
+use super::jq::JsonLikeHelper;
+use super::optimize;
+use super::order::KeyOrder;
+use super::paths;
+use super::print;
+use super::regex::{self, RegexCache};
+use super::time::{self, JqClock};
+use super::trace::{self, JqSink, JqTrace, Recorder, TraceEvent};
+use crate::jsonlike::JsonLike;
+
+/// Definitions available to every filter, on top of the native filters.
+const DEFS: &str = include_str!("defs.jq");
+
+/// Name, arguments and implementation of a native filter.
+pub(crate) type Fun<A> = (&'static str, Box<[Bind]>, Native<JsonLikeHelper<A>>);
+
+/// Per-run state that is visible to native filters, see [`env`].
+pub(crate) struct Env<A> {
+    pub(crate) sink: Option<Arc<dyn JqSink>>,
+    pub(crate) trace: Option<RefCell<Recorder<A>>>,
+    pub(crate) regexes: Arc<RegexCache>,
+    pub(crate) clock: Option<Arc<dyn JqClock>>,
+}
+
+/// Error produced while compiling or evaluating a jq filter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JqError {
+    /// The filter is not syntactically valid; `span` is a byte range in the
+    /// filter source.
+    Parse { message: String, span: Range<usize> },
+    /// The filter refers to undefined filters, variables or labels.
+    Compile { message: String, span: Range<usize> },
+    /// The filter failed while evaluating an input.
+    Runtime(String),
+}
+
+impl Display for JqError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JqError::Parse { message, span } | JqError::Compile { message, span } => {
+                write!(f, "{} at {}..{}", message, span.start, span.end)
+            }
+            JqError::Runtime(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for JqError {}
+
+impl JqError {
+    fn runtime<A>(err: jaq_core::Error<JsonLikeHelper<A>>) -> Self
+    where
+        A: for<'a> JsonLike<'a>
+            + std::fmt::Display
+            + std::clone::Clone
+            + std::cmp::PartialEq
+            + 'static,
+    {
+        let value = err.into_val();
+        match value.0.as_str() {
+            Some(message) => JqError::Runtime(message.to_string()),
+            None => JqError::Runtime(value.to_string()),
+        }
+    }
+}
+
+/// Byte range of `part` within `code`, or the end of `code` if `part` does not
+/// point into it.
+fn span(code: &str, part: &str) -> Range<usize> {
+    let start = code.as_ptr() as usize;
+    let offset = (part.as_ptr() as usize).wrapping_sub(start);
+    if offset <= code.len() {
+        load::span(code, part)
+    } else {
+        code.len()..code.len()
+    }
+}
+
+fn syntax_error(code: &str, err: load::Error<&str>) -> JqError {
+    let parse = |message: String, found: &str| JqError::Parse {
+        message,
+        span: span(code, found),
+    };
+    match err {
+        load::Error::Lex(errs) => match errs.first() {
+            Some((expect, found)) => parse(format!("expected {}", expect.as_str()), found),
+            None => parse("invalid token".to_string(), ""),
+        },
+        load::Error::Parse(errs) => match errs.first() {
+            Some((expect, found)) if found.is_empty() => parse(
+                format!("expected {}, found end of input", expect.as_str()),
+                found,
+            ),
+            Some((expect, found)) => parse(
+                format!("expected {}, found {}", expect.as_str(), found),
+                found,
+            ),
+            None => parse("invalid syntax".to_string(), ""),
+        },
+        load::Error::Io(errs) => match errs.first() {
+            Some((path, err)) => parse(format!("cannot load {}: {}", path, err), path),
+            None => parse("cannot load module".to_string(), ""),
+        },
+    }
+}
+
+fn load_error(code: &str, errs: load::Errors<&str, ()>) -> JqError {
+    match errs.into_iter().next() {
+        Some((_, err)) => syntax_error(code, err),
+        None => JqError::Parse {
+            message: "invalid filter".to_string(),
+            span: 0..0,
+        },
+    }
+}
+
+/// Parses `code` into a single jq term, for passes that work on the syntax
+/// tree instead of the compiled filter.
+pub(crate) fn parse_term(code: &str) -> Result<Term<&str>, JqError> {
+    let tokens = Lexer::new(code)
+        .lex()
+        .map_err(|errs| syntax_error(code, load::Error::Lex(errs)))?;
+    Parser::new(&tokens).parse(|p| p.term()).map_err(|errs| {
+        let errs = errs
+            .into_iter()
+            .map(|(expect, found)| (expect, Token::opt_as_str(found, code)))
+            .collect();
+        syntax_error(code, load::Error::Parse(errs))
+    })
+}
+
+fn compile_error(code: &str, errs: jaq_core::compile::Errors<&str, ()>) -> JqError {
+    let err = errs.into_iter().flat_map(|(_, errs)| errs).next();
+    match err {
+        Some((name, undefined)) => JqError::Compile {
+            message: format!("undefined {} `{}`", undefined.as_str(), name),
+            span: span(code, name),
+        },
+        None => JqError::Compile {
+            message: "invalid filter".to_string(),
+            span: 0..0,
+        },
+    }
+}
+
+fn compile<A>(code: &str) -> Result<Filter<Native<JsonLikeHelper<A>>>, JqError>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let defs = load::parse(DEFS, |p| p.defs()).unwrap_or_default();
+    let loader = Loader::new(defs);
+    let arena = Arena::default();
+    let modules = loader
+        .load(&arena, File { code, path: () })
+        .map_err(|errs| load_error(code, errs))?;
+
+    Compiler::default()
+        .with_funs(
+            trace::funs::<A>()
+                .into_iter()
+                .chain(format::funs::<A>())
+                .chain(paths::funs::<A>())
+                .chain(regex::funs::<A>())
+                .chain(time::funs::<A>()),
+        )
+        .compile(modules)
+        .map_err(|errs| compile_error(code, errs))
+}
+
+/// Prints `term` back to source, wrapping every node into `_trace` if `trace`
+/// is set. Returns the code and the source of every traced node.
+fn rewrite<S: AsRef<str>>(term: &Term<S>, trace: bool) -> (String, Vec<String>) {
+    let mut nodes = Vec::new();
+    let code = match trace {
+        true => print::instrument(term, &mut nodes),
+        false => print::print(term),
+    };
+    (code, nodes)
+}
+
+/// Options used while compiling a [`JqTemplate`].
+#[derive(Clone, Default)]
+pub struct JqOptions {
+    trace: bool,
+    optimize: bool,
+    sink: Option<Arc<dyn JqSink>>,
+    clock: Option<Arc<dyn JqClock>>,
+    key_order: KeyOrder,
+}
+
+impl JqOptions {
+    /// Compiles the filter with tracing support, see [`JqTemplate::run_traced`].
+    pub fn trace(mut self, trace: bool) -> Self {
+        self.trace = trace;
+        self
+    }
+
+    /// Simplifies the filter before compiling it, see [`optimize`].
+    pub fn optimize(mut self, optimize: bool) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Routes the output of `debug` and `stderr` to `sink`.
+    pub fn sink(mut self, sink: impl JqSink + 'static) -> Self {
+        self.sink = Some(Arc::new(sink));
+        self
+    }
+
+    /// Reads the time returned by `now` from `clock` instead of the system.
+    pub fn clock(mut self, clock: impl JqClock + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    /// Sets the key order of returned objects, see [`KeyOrder`].
+    pub fn key_order(mut self, key_order: KeyOrder) -> Self {
+        self.key_order = key_order;
+        self
+    }
+
+    /// Sorts the keys of returned objects, like jq's `--sort-keys`.
+    pub fn sort_keys(self, sort_keys: bool) -> Self {
+        match sort_keys {
+            true => self.key_order(KeyOrder::Sorted),
+            false => self.key_order(KeyOrder::Insertion),
+        }
+    }
+}
+
+/// A compiled jq filter that can be evaluated on any `JsonLike` value.
+#[derive(Clone)]
+pub struct JqTemplate<A>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    filter: Filter<Native<JsonLikeHelper<A>>>,
+    nodes: Vec<String>,
+    sink: Option<Arc<dyn JqSink>>,
+    clock: Option<Arc<dyn JqClock>>,
+    key_order: KeyOrder,
+    pub(crate) regexes: Arc<RegexCache>,
+}
+
+impl<A> JqTemplate<A>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    pub fn parse(code: &str) -> Result<Self, JqError> {
+        Self::parse_with(code, JqOptions::default())
+    }
+
+    pub fn parse_with(code: &str, options: JqOptions) -> Result<Self, JqError> {
+        // errors are always reported against the original source
+        let mut filter = compile(code)?;
+        let mut nodes = Vec::new();
+
+        if options.trace || options.optimize {
+            let term = parse_term(code)?;
+            let (code, trace_nodes) = match options.optimize {
+                true => rewrite(&optimize::optimize(term), options.trace),
+                false => rewrite(&term, options.trace),
+            };
+            filter = compile(&code)?;
+            nodes = trace_nodes;
+        }
+
+        Ok(Self {
+            filter,
+            nodes,
+            sink: options.sink,
+            clock: options.clock,
+            key_order: options.key_order,
+            regexes: Arc::default(),
+        })
+    }
+
+    /// Evaluates the filter on `input`, returning every output in order.
+    pub fn run(&self, input: A) -> Vec<Result<A, JqError>> {
+        self.eval(input, None).0
+    }
+
+    /// Evaluates the filter on `input` and records every visited node.
+    /// The trace is empty unless the filter was compiled with
+    /// [`JqOptions::trace`].
+    pub fn run_traced(&self, input: A) -> (Vec<Result<A, JqError>>, JqTrace<A>) {
+        let (outputs, events) = self.eval(input, Some(RefCell::default()));
+        let trace = JqTrace {
+            nodes: self.nodes.clone(),
+            events,
+        };
+        (outputs, trace)
+    }
+
+    fn eval(
+        &self,
+        input: A,
+        trace: Option<RefCell<Recorder<A>>>,
+    ) -> (Vec<Result<A, JqError>>, Vec<TraceEvent<A>>) {
+        let env = Rc::new(Env {
+            sink: self.sink.clone(),
+            trace,
+            regexes: self.regexes.clone(),
+            clock: self.clock.clone(),
+        });
+        let outputs = {
+            let _guard = env::enter(env.clone());
+            let inputs = RcIter::new(core::iter::empty());
+            self.filter
+                .run((Ctx::new([], &inputs), JsonLikeHelper(input)))
+                .map(|output| output.map(|value| self.key_order.apply(value.0)))
+                .map(|output| output.map_err(JqError::runtime))
+                .collect()
+        };
+
+        let events = Rc::try_unwrap(env)
+            .ok()
+            .and_then(|env| env.trace)
+            .map(|recorder| recorder.into_inner().events)
+            .unwrap_or_default();
+        (outputs, events)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{JqError, JqOptions, JqTemplate};
+    use crate::jq::JqStream;
+
+    fn run(code: &str, input: serde_json::Value) -> Vec<Result<serde_json::Value, JqError>> {
+        JqTemplate::parse(code).unwrap().run(input)
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_send_sync() {
+        assert_send_sync::<JqTemplate<serde_json::Value>>();
+
+        let template = Arc::new(JqTemplate::parse("[.[] | test(\"^a\")]").unwrap());
+        let threads = (0..4).map(|_| {
+            let template = template.clone();
+            std::thread::spawn(move || template.run(json!(["ab", "b"])))
+        });
+        for thread in threads.collect::<Vec<_>>() {
+            assert_eq!(thread.join().unwrap(), vec![Ok(json!([true, false]))]);
+        }
+    }
+
+    #[test]
+    fn test_run() {
+        let actual = run(
+            ".user.name, .user.age",
+            json!({"user": {"name": "Alice", "age": 30}}),
+        );
+        assert_eq!(actual, vec![Ok(json!("Alice")), Ok(json!(30))]);
+    }
+
+    #[test]
+    fn test_parse_error_span() {
+        let err = JqTemplate::<serde_json::Value>::parse(".a | )")
+            .err()
+            .unwrap();
+        let JqError::Parse { span, .. } = err else {
+            panic!("expected a parse error, got {:?}", err);
+        };
+        assert_eq!(span, 5..6);
+    }
+
+    #[test]
+    fn test_compile_error_span() {
+        let err = JqTemplate::<serde_json::Value>::parse(".a | foo")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            JqError::Compile {
+                message: "undefined filter `foo`".to_string(),
+                span: 5..8
+            }
+        );
+    }
+
+    #[test]
+    fn test_runtime_error() {
+        let actual = run(".a.b", json!({"a": "text"}));
+        assert_eq!(
+            actual,
+            vec![Err(JqError::Runtime("Value is not object or array".into()))]
+        );
+    }
+
+    #[test]
+    fn test_debug_and_stderr_sink() {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let sink = {
+            let messages = messages.clone();
+            move |stream: JqStream, message: &str| {
+                messages.lock().unwrap().push((stream, message.to_string()))
+            }
+        };
+        let options = JqOptions::default().sink(sink);
+        let template =
+            JqTemplate::parse_with(".a | debug | .b | stderr | debug(\"done\")", options).unwrap();
+
+        let actual = template.run(json!({"a": {"b": "text"}}));
+
+        assert_eq!(actual, vec![Ok(json!("text"))]);
+        assert_eq!(
+            messages.lock().unwrap().clone(),
+            vec![
+                (JqStream::Debug, r#"["DEBUG:",{"b":"text"}]"#.to_string()),
+                (JqStream::Stderr, "text".to_string()),
+                (JqStream::Debug, r#"["DEBUG:","done"]"#.to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_traced() {
+        let options = JqOptions::default().trace(true);
+        let template = JqTemplate::parse_with(".user | .name, .age", options).unwrap();
+        let input = json!({"user": {"name": "Alice", "age": 30}});
+
+        let (outputs, trace) = template.run_traced(input.clone());
+
+        assert_eq!(outputs, template.run(input.clone()));
+        assert_eq!(outputs, vec![Ok(json!("Alice")), Ok(json!(30))]);
+
+        let visits = trace
+            .events
+            .iter()
+            .map(|event| (trace.source(event), event.depth, event.outputs.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            visits,
+            vec![
+                (
+                    ".[\"user\"] | (.[\"name\"] , .[\"age\"])",
+                    0,
+                    vec![json!("Alice"), json!(30)]
+                ),
+                (".[\"user\"]", 1, vec![json!({"name": "Alice", "age": 30})]),
+                (
+                    ".[\"name\"] , .[\"age\"]",
+                    1,
+                    vec![json!("Alice"), json!(30)]
+                ),
+                (".[\"name\"]", 2, vec![json!("Alice")]),
+                (".[\"age\"]", 2, vec![json!(30)]),
+            ]
+        );
+        assert_eq!(trace.events[0].input, input);
+    }
+
+    #[test]
+    fn test_traced_outputs_match_untraced() {
+        let programs = [
+            "label $out | .[] | ., break $out",
+            "reduce .[] as $x (0; . + $x)",
+            "def f(g): [g, g]; f(.[0])",
+            ".[1:] | {a: .[0], \"b\": [.[]]}",
+            ".[0] as $x | [$x, .[2]]",
+        ];
+        let input = json!([1, 2, 3]);
+
+        for program in programs {
+            let traced = JqTemplate::parse_with(program, JqOptions::default().trace(true));
+            let traced = traced.unwrap().run(input.clone());
+            let untraced = JqTemplate::parse(program).unwrap().run(input.clone());
+            assert_eq!(traced, untraced, "{program}");
+        }
+    }
+
+    #[test]
+    fn test_trace_failed_node() {
+        let options = JqOptions::default().trace(true);
+        let template = JqTemplate::parse_with("try .a.b catch \"failed\"", options).unwrap();
+
+        let (outputs, trace) = template.run_traced(json!({"a": 1}));
+
+        assert_eq!(outputs, vec![Ok(json!("failed"))]);
+        let failed = trace
+            .events
+            .iter()
+            .find(|event| trace.source(event) == ".[\"a\"][\"b\"]");
+        assert!(failed.unwrap().failed);
+    }
+
+    #[test]
+    fn test_traced_update() {
+        let options = JqOptions::default().trace(true);
+        let template = JqTemplate::parse_with(".a |= \"x\"", options).unwrap();
+
+        let (outputs, trace) = template.run_traced(json!({"a": 1, "b": 2}));
+
+        assert_eq!(outputs, vec![Ok(json!({"a": "x", "b": 2}))]);
+        assert!(!trace.events.is_empty());
+    }
+
+    #[test]
+    fn test_untraced_template_has_no_events() {
+        let template = JqTemplate::parse(".a").unwrap();
+        let (outputs, trace) = template.run_traced(json!({"a": 1}));
+        assert_eq!(outputs, vec![Ok(json!(1))]);
+        assert!(trace.events.is_empty());
+    }
+}
@@ -0,0 +1,693 @@
+//! jq's date and time builtins: `now`, `mktime`, `gmtime`, `strftime` and
+//! `strptime`, with `todate` and `fromdate` defined on top of them.
+//!
+//! All times are UTC. Like in jq, a "broken down time" is an array
+//! `[year, month (0-11), day of month, hours, minutes, seconds, day of week,
+//! day of year]`, and `strptime` accepts `%z` without applying the offset.
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use jaq_core::box_iter::box_once;
+use jaq_core::{Bind, Cv, Error, Exn, Native, ValX};
+
+use super::env;
+use super::jq::JsonLikeHelper;
+use super::template::{Env, Fun};
+use crate::jsonlike::JsonLike;
+
+/// Source of the current time for `now`.
+/// Without a clock the system time is used. Like sinks, clocks are shared by
+/// every run of a [`JqTemplate`](super::JqTemplate), on any thread.
+pub trait JqClock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+impl<F: Fn() -> SystemTime + Send + Sync> JqClock for F {
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+const DAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+/// Broken down UTC time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Tm {
+    year: i64,
+    /// 0-11
+    month: i64,
+    /// 1-31
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: f64,
+    /// 0-6, starting on Sunday
+    weekday: i64,
+    /// 0-365
+    yearday: i64,
+}
+
+/// Largest absolute timestamp, in seconds, that times can be converted from
+/// and to. Beyond it, floats can't hold every second anyway.
+const MAX_TIMESTAMP: f64 = 9007199254740992.0;
+
+fn out_of_range() -> String {
+    "time out of range".to_string()
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar, or
+/// `None` if they overflow.
+fn days_from_civil(year: i64, month: i64, day: i64) -> Option<i64> {
+    // normalise months outside of 1-12 like `timegm` does
+    let year = year.checked_add(month.checked_sub(1)?.div_euclid(12))?;
+    let month = (month - 1).rem_euclid(12) + 1;
+    let year = if month <= 2 {
+        year.checked_sub(1)?
+    } else {
+        year
+    };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = ((153 * ((month + 9) % 12) + 2) / 5).checked_add(day.checked_sub(1)?)?;
+    let doe = (yoe * 365 + yoe / 4 - yoe / 100).checked_add(doy)?;
+    era.checked_mul(146097)?
+        .checked_add(doe)?
+        .checked_sub(719468)
+}
+
+/// Year, month (1-12) and day of days since 1970-01-01, or `None` if they
+/// overflow.
+fn civil_from_days(days: i64) -> Option<(i64, i64, i64)> {
+    let z = days.checked_add(719468)?;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = era
+        .checked_mul(400)?
+        .checked_add(yoe + i64::from(month <= 2))?;
+    Some((year, month, day))
+}
+
+impl Tm {
+    /// Fails on timestamps beyond [`MAX_TIMESTAMP`] and on NaN.
+    fn from_timestamp(timestamp: f64) -> Result<Self, String> {
+        if timestamp.is_nan() || timestamp.abs() > MAX_TIMESTAMP {
+            return Err(out_of_range());
+        }
+        let seconds = timestamp.floor();
+        let days = (seconds / 86400.0).floor() as i64;
+        let rest = seconds as i64 - days * 86400;
+        let (year, month, day) = civil_from_days(days).ok_or_else(out_of_range)?;
+        let first_day = days_from_civil(year, 1, 1).ok_or_else(out_of_range)?;
+        Ok(Tm {
+            year,
+            month: month - 1,
+            day,
+            hour: rest / 3600,
+            minute: rest % 3600 / 60,
+            second: (rest % 60) as f64 + (timestamp - seconds),
+            weekday: (days + 4).rem_euclid(7),
+            yearday: days - first_day,
+        })
+    }
+
+    /// Seconds since the epoch, ignoring the fractional part of `second`
+    /// like jq's `mktime`. Fails if they are beyond [`MAX_TIMESTAMP`].
+    fn timestamp(&self) -> Result<i64, String> {
+        let days = days_from_civil(self.year, self.month.saturating_add(1), self.day);
+        let timestamp = days
+            .and_then(|days| days.checked_mul(86400))
+            .and_then(|n| n.checked_add(self.hour.checked_mul(3600)?))
+            .and_then(|n| n.checked_add(self.minute.checked_mul(60)?))
+            .and_then(|n| n.checked_add(self.second.floor() as i64))
+            .filter(|n| n.unsigned_abs() <= MAX_TIMESTAMP as u64);
+        timestamp.ok_or_else(out_of_range)
+    }
+
+    /// Recomputes the day of week and year from the date.
+    fn normalized(self) -> Result<Self, String> {
+        let fraction = self.second - self.second.floor();
+        Tm::from_timestamp(self.timestamp()? as f64 + fraction)
+    }
+
+    fn from_value<A>(value: &A) -> Option<Self>
+    where
+        A: for<'a> JsonLike<'a>,
+    {
+        let arr = value.as_array()?;
+        let n = |i: usize| arr.get(i).and_then(|v| v.as_f64());
+        if arr.len() < 6 || (0..arr.len()).any(|i| n(i).is_none()) {
+            return None;
+        }
+        Some(Tm {
+            year: n(0)? as i64,
+            month: n(1)? as i64,
+            day: n(2)? as i64,
+            hour: n(3)? as i64,
+            minute: n(4)? as i64,
+            second: n(5)?,
+            weekday: n(6).unwrap_or_default() as i64,
+            yearday: n(7).unwrap_or_default() as i64,
+        })
+    }
+
+    fn to_value<A>(self) -> A
+    where
+        A: for<'a> JsonLike<'a>,
+    {
+        let fields = [self.year, self.month, self.day, self.hour, self.minute];
+        let mut arr: Vec<A> = fields.iter().map(|n| A::number_f64(*n as f64)).collect();
+        arr.push(A::number_f64(self.second));
+        arr.push(A::number_f64(self.weekday as f64));
+        arr.push(A::number_f64(self.yearday as f64));
+        A::array(arr)
+    }
+}
+
+fn strftime(tm: &Tm, format: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let hour12 = match tm.hour % 12 {
+            0 => 12,
+            hour => hour,
+        };
+        // names are only looked up when needed, fields out of range are errors
+        let month = || MONTHS.get(usize::try_from(tm.month).ok()?).copied();
+        let weekday = || DAYS.get(usize::try_from(tm.weekday).ok()?).copied();
+        let _ = match chars.next() {
+            Some('Y') => write!(out, "{}", tm.year),
+            Some('C') => write!(out, "{:02}", tm.year.div_euclid(100)),
+            Some('y') => write!(out, "{:02}", tm.year.rem_euclid(100)),
+            Some('m') => write!(out, "{:02}", tm.month + 1),
+            Some('d') => write!(out, "{:02}", tm.day),
+            Some('e') => write!(out, "{:2}", tm.day),
+            Some('H') => write!(out, "{:02}", tm.hour),
+            Some('k') => write!(out, "{:2}", tm.hour),
+            Some('I') => write!(out, "{:02}", hour12),
+            Some('l') => write!(out, "{:2}", hour12),
+            Some('M') => write!(out, "{:02}", tm.minute),
+            Some('S') => write!(out, "{:02}", tm.second.floor() as i64),
+            Some('j') => write!(out, "{:03}", tm.yearday + 1),
+            Some('a') => write!(out, "{}", &weekday().ok_or_else(out_of_range)?[..3]),
+            Some('A') => write!(out, "{}", weekday().ok_or_else(out_of_range)?),
+            Some('b' | 'h') => write!(out, "{}", &month().ok_or_else(out_of_range)?[..3]),
+            Some('B') => write!(out, "{}", month().ok_or_else(out_of_range)?),
+            Some('p') => write!(out, "{}", if tm.hour < 12 { "AM" } else { "PM" }),
+            Some('u') => write!(out, "{}", if tm.weekday == 0 { 7 } else { tm.weekday }),
+            Some('w') => write!(out, "{}", tm.weekday),
+            Some('s') => write!(out, "{}", tm.timestamp()?),
+            Some('Z') => write!(out, "UTC"),
+            Some('z') => write!(out, "+0000"),
+            Some('D') => write!(out, "{}", strftime(tm, "%m/%d/%y")?),
+            Some('F') => write!(out, "{}", strftime(tm, "%Y-%m-%d")?),
+            Some('T') => write!(out, "{}", strftime(tm, "%H:%M:%S")?),
+            Some('R') => write!(out, "{}", strftime(tm, "%H:%M")?),
+            Some('c') => write!(out, "{}", strftime(tm, "%a %b %e %H:%M:%S %Y")?),
+            Some('n') => writeln!(out),
+            Some('t') => write!(out, "\t"),
+            Some('%') => write!(out, "%"),
+            Some(c) => return Err(format!("strftime/1: unsupported format %{}", c)),
+            None => return Err("strftime/1: format ends with %".to_string()),
+        };
+    }
+    Ok(out)
+}
+
+/// Parses `input` according to `format`, in the style of C's `strptime`.
+fn strptime(input: &str, format: &str) -> Result<Tm, String> {
+    let mismatch = || format!("date \"{}\" does not match format \"{}\"", input, format);
+    let mut tm = Tm {
+        year: 1900,
+        month: 0,
+        day: 1,
+        hour: 0,
+        minute: 0,
+        second: 0.0,
+        weekday: 0,
+        yearday: 0,
+    };
+    let mut yearday = None;
+    let mut pm = None;
+    let mut timestamp = None;
+
+    let mut rest = input;
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            rest = rest.trim_start();
+            continue;
+        }
+        if c != '%' {
+            rest = rest.strip_prefix(c).ok_or_else(mismatch)?;
+            continue;
+        }
+
+        let directive = chars.next().ok_or_else(mismatch)?;
+        let mut number = |max_digits: usize, min: i64, max: i64| -> Result<i64, String> {
+            let trimmed = rest.trim_start();
+            let (sign, digits) = match trimmed.strip_prefix(['+', '-']) {
+                Some(digits) if min < 0 => (&trimmed[..1], digits),
+                _ => ("", trimmed),
+            };
+            let len = digits
+                .char_indices()
+                .take(max_digits)
+                .take_while(|(_, c)| c.is_ascii_digit())
+                .count();
+            let n: i64 = digits[..len].parse().map_err(|_| mismatch())?;
+            let n = if sign == "-" { -n } else { n };
+            if n < min || n > max {
+                return Err(mismatch());
+            }
+            rest = &digits[len..];
+            Ok(n)
+        };
+
+        match directive {
+            'Y' => tm.year = number(4, -9999, 9999)?,
+            'y' => {
+                let year = number(2, 0, 99)?;
+                tm.year = if year < 69 { 2000 + year } else { 1900 + year };
+            }
+            'C' => tm.year = number(2, 0, 99)? * 100 + tm.year.rem_euclid(100),
+            'm' => tm.month = number(2, 1, 12)? - 1,
+            'd' | 'e' => tm.day = number(2, 1, 31)?,
+            'H' | 'k' => tm.hour = number(2, 0, 23)?,
+            'I' | 'l' => tm.hour = number(2, 1, 12)? % 12,
+            'M' => tm.minute = number(2, 0, 59)?,
+            'S' => tm.second = number(2, 0, 60)? as f64,
+            'j' => yearday = Some(number(3, 1, 366)? - 1),
+            's' => timestamp = Some(number(19, i64::MIN, i64::MAX)?),
+            'u' | 'w' => {
+                number(1, 0, 7)?;
+            }
+            'a' | 'A' => rest = name(rest, &DAYS).ok_or_else(mismatch)?.1,
+            'b' | 'B' | 'h' => {
+                let (month, tail) = name(rest, &MONTHS).ok_or_else(mismatch)?;
+                tm.month = month as i64;
+                rest = tail;
+            }
+            'p' => {
+                let upper = rest.get(..2).map(str::to_ascii_uppercase);
+                pm = match upper.as_deref() {
+                    Some("AM") => Some(false),
+                    Some("PM") => Some(true),
+                    _ => return Err(mismatch()),
+                };
+                rest = &rest[2..];
+            }
+            'z' => {
+                // the offset is validated but, like in jq, not applied
+                rest = match rest.strip_prefix('Z') {
+                    Some(tail) => tail,
+                    None => {
+                        let tail = rest.strip_prefix(['+', '-']).ok_or_else(mismatch)?;
+                        let digits = tail.chars().take_while(|c| c.is_ascii_digit() || *c == ':');
+                        let len = digits.map(char::len_utf8).sum::<usize>();
+                        if !matches!(tail[..len].replace(':', "").len(), 2 | 4) {
+                            return Err(mismatch());
+                        }
+                        &tail[len..]
+                    }
+                };
+            }
+            'Z' => rest = rest.trim_start_matches(|c: char| c.is_ascii_alphabetic()),
+            'T' => tm = strptime_into(tm, &mut rest, "%H:%M:%S").map_err(|_| mismatch())?,
+            'D' => tm = strptime_into(tm, &mut rest, "%m/%d/%y").map_err(|_| mismatch())?,
+            'F' => tm = strptime_into(tm, &mut rest, "%Y-%m-%d").map_err(|_| mismatch())?,
+            'R' => tm = strptime_into(tm, &mut rest, "%H:%M").map_err(|_| mismatch())?,
+            'n' | 't' => rest = rest.trim_start(),
+            '%' => rest = rest.strip_prefix('%').ok_or_else(mismatch)?,
+            c => return Err(format!("strptime/1: unsupported format %{}", c)),
+        }
+    }
+
+    if !rest.is_empty() {
+        return Err(mismatch());
+    }
+    if let Some(timestamp) = timestamp {
+        return Tm::from_timestamp(timestamp as f64);
+    }
+    if pm == Some(true) {
+        tm.hour += 12;
+    }
+    if let Some(yearday) = yearday {
+        let days = days_from_civil(tm.year, 1, 1).and_then(|days| days.checked_add(yearday));
+        let (_, month, day) = days.and_then(civil_from_days).ok_or_else(out_of_range)?;
+        tm.month = month - 1;
+        tm.day = day;
+    }
+    tm.normalized()
+}
+
+/// Parses a composite directive such as `%T`, continuing from `tm`.
+fn strptime_into(tm: Tm, rest: &mut &str, format: &str) -> Result<Tm, String> {
+    let len = format.len() + 8;
+    let end = (0..=rest.len().min(len))
+        .rev()
+        .filter(|end| rest.is_char_boundary(*end))
+        .find(|end| strptime(&rest[..*end], format).is_ok())
+        .ok_or_else(String::new)?;
+    let parsed = strptime(&rest[..end], format)?;
+    *rest = &rest[end..];
+    let mut tm = tm;
+    for directive in format.split('%').skip(1).filter_map(|s| s.chars().next()) {
+        match directive {
+            'H' => tm.hour = parsed.hour,
+            'M' => tm.minute = parsed.minute,
+            'S' => tm.second = parsed.second,
+            'm' => tm.month = parsed.month,
+            'd' => tm.day = parsed.day,
+            'y' | 'Y' => tm.year = parsed.year,
+            _ => (),
+        }
+    }
+    Ok(tm)
+}
+
+/// Matches a full or abbreviated name, case-insensitively, returning its index.
+fn name<'s>(input: &'s str, names: &[&str]) -> Option<(usize, &'s str)> {
+    names.iter().enumerate().find_map(|(i, name)| {
+        [*name, &name[..3]].into_iter().find_map(|name| {
+            let prefix = input.get(..name.len())?;
+            prefix
+                .eq_ignore_ascii_case(name)
+                .then(|| (i, &input[name.len()..]))
+        })
+    })
+}
+
+fn err<'a, A>(message: String) -> ValX<'a, JsonLikeHelper<A>>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    Err(Exn::from(Error::str(message)))
+}
+
+fn now<A>() -> f64
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let clock = env::current::<Env<A>>().and_then(|env| env.clock.clone());
+    let now = clock.map_or_else(SystemTime::now, |clock| clock.now());
+    match now.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs_f64(),
+        Err(err) => -err.duration().as_secs_f64(),
+    }
+}
+
+/// Broken down time of a number or broken down time input.
+fn tm_of<A>(value: &A, name: &str) -> Result<Tm, String>
+where
+    A: for<'a> JsonLike<'a>,
+{
+    match value.as_f64() {
+        Some(timestamp) => Tm::from_timestamp(timestamp),
+        None => {
+            Tm::from_value(value).ok_or_else(|| format!("{} requires parsed datetime inputs", name))
+        }
+    }
+}
+
+/// Native filters for the date and time builtins.
+pub(crate) fn funs<A>() -> Vec<Fun<A>>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    vec![
+        (
+            "now",
+            Box::new([]),
+            Native::new(|_, _| box_once(Ok(JsonLikeHelper(A::number_f64(now::<A>()))))),
+        ),
+        (
+            "mktime",
+            Box::new([]),
+            Native::new(|_, cv| {
+                let output = match Tm::from_value(&cv.1 .0) {
+                    Some(tm) => match tm.timestamp() {
+                        Ok(timestamp) => Ok(JsonLikeHelper(A::number_f64(timestamp as f64))),
+                        Err(message) => err(message),
+                    },
+                    None => err("mktime requires array of 6 numbers".to_string()),
+                };
+                box_once(output)
+            }),
+        ),
+        (
+            "gmtime",
+            Box::new([]),
+            Native::new(|_, cv| {
+                let output = match cv.1 .0.as_f64() {
+                    Some(timestamp) => match Tm::from_timestamp(timestamp) {
+                        Ok(tm) => Ok(JsonLikeHelper(tm.to_value())),
+                        Err(message) => err(message),
+                    },
+                    None => err("gmtime() requires a number".to_string()),
+                };
+                box_once(output)
+            }),
+        ),
+        (
+            "strftime",
+            Box::new([Bind::Var(())]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let format = cv.0.pop_var();
+                let output = tm_of(&cv.1 .0, "strftime/1").and_then(|tm| {
+                    let format = format
+                        .0
+                        .as_str()
+                        .ok_or("strftime/1 requires a string format")?;
+                    strftime(&tm, format)
+                });
+                box_once(
+                    output
+                        .map(|s| JsonLikeHelper(A::string(s.into())))
+                        .or_else(err),
+                )
+            }),
+        ),
+        (
+            "strptime",
+            Box::new([Bind::Var(())]),
+            Native::new(|_, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let format = cv.0.pop_var();
+                let output = match (cv.1 .0.as_str(), format.0.as_str()) {
+                    (Some(input), Some(format)) => strptime(input, format),
+                    _ => Err("strptime/1 requires string inputs and arguments".to_string()),
+                };
+                box_once(output.map(|tm| JsonLikeHelper(tm.to_value())).or_else(err))
+            }),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{civil_from_days, days_from_civil};
+    use crate::jq::{JqError, JqOptions, JqTemplate};
+
+    fn run(code: &str, input: Value) -> Vec<Result<Value, JqError>> {
+        JqTemplate::parse(code).unwrap().run(input)
+    }
+
+    fn value(code: &str, input: Value) -> Value {
+        run(code, input).remove(0).unwrap()
+    }
+
+    #[test]
+    fn test_civil_days_roundtrip() {
+        for days in [-719468, -1, 0, 59, 11016, 16499, 2932896] {
+            let (y, m, d) = civil_from_days(days).unwrap();
+            assert_eq!(days_from_civil(y, m, d), Some(days));
+        }
+        assert_eq!(civil_from_days(0), Some((1970, 1, 1)));
+        assert_eq!(days_from_civil(2000, 3, 1), Some(11017));
+        assert_eq!(civil_from_days(i64::MAX), None);
+        assert_eq!(days_from_civil(i64::MAX, 1, 1), None);
+        assert_eq!(days_from_civil(2000, 1, i64::MIN), None);
+    }
+
+    #[test]
+    fn test_now_uses_clock() {
+        let clock = || UNIX_EPOCH + Duration::from_millis(1425599621500);
+        let template =
+            JqTemplate::parse_with("now, (now | todate)", JqOptions::default().clock(clock));
+        let actual = template.unwrap().run(json!(null));
+        assert_eq!(
+            actual,
+            vec![Ok(json!(1425599621.5)), Ok(json!("2015-03-05T23:53:41Z"))]
+        );
+    }
+
+    #[test]
+    fn test_gmtime_and_mktime() {
+        assert_eq!(
+            value("gmtime", json!(1425599621.25)),
+            json!([2015.0, 2.0, 5.0, 23.0, 53.0, 41.25, 4.0, 63.0])
+        );
+        assert_eq!(
+            value("gmtime | mktime", json!(1425599621.25)),
+            json!(1425599621.0)
+        );
+        assert_eq!(
+            value("gmtime", json!(-1)),
+            json!([1969.0, 11.0, 31.0, 23.0, 59.0, 59.0, 3.0, 364.0])
+        );
+        assert_eq!(
+            value("mktime", json!([2024, 1, 29, 12, 0, 0])),
+            json!(1709208000.0)
+        );
+        assert!(run("mktime", json!("2015")).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_out_of_range() {
+        let cases = [
+            ("todate", json!(1e20)),
+            ("gmtime", json!(1e30)),
+            ("mktime", json!([1e15, 0, 0, 0, 0, 0, 0, 0])),
+            ("mktime", json!([2000, 0, -9.3e18, 0, 0, 0])),
+            ("strftime(\"%s\")", json!([1e15, 0, 1, 0, 0, 0])),
+        ];
+        for (code, input) in cases {
+            let actual = run(code, input.clone()).remove(0).unwrap_err();
+            assert!(
+                actual.to_string().contains("time out of range"),
+                "{} {}: {}",
+                code,
+                input,
+                actual
+            );
+        }
+        assert_eq!(value("gmtime | mktime", json!(-1e15)), json!(-1e15));
+    }
+
+    #[test]
+    fn test_todate_and_fromdate() {
+        assert_eq!(
+            value("todate", json!(1425599621)),
+            json!("2015-03-05T23:53:41Z")
+        );
+        assert_eq!(
+            value("fromdate", json!("2015-03-05T23:53:41Z")),
+            json!(1425599621.0)
+        );
+        assert_eq!(
+            value("todateiso8601", json!(0)),
+            json!("1970-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            value("fromdateiso8601", json!("1970-01-02T00:00:00Z")),
+            json!(86400.0)
+        );
+        assert!(run("fromdate", json!("2015-03-05")).remove(0).is_err());
+    }
+
+    #[test]
+    fn test_strftime() {
+        let format = "strftime(\"%A, %B %d, %Y %I:%M %p (%a %b %j %u %e %T %D %Z)\")";
+        assert_eq!(
+            value(format, json!(1425599621)),
+            json!("Thursday, March 05, 2015 11:53 PM (Thu Mar 064 4  5 23:53:41 03/05/15 UTC)")
+        );
+        assert_eq!(
+            value("gmtime | strftime(\"%F %s\")", json!(86400)),
+            json!("1970-01-02 86400")
+        );
+        assert!(run("strftime(\"%Y\")", json!("x")).remove(0).is_err());
+
+        let out_of_range = Err(JqError::Runtime("time out of range".to_string()));
+        assert_eq!(
+            run("strftime(\"%b\")", json!([2024, 20, 1, 0, 0, 0, 0, 0])),
+            vec![out_of_range.clone()]
+        );
+        assert_eq!(
+            run("strftime(\"%a\")", json!([2024, 1, 1, 0, 0, 0, 9, 0])),
+            vec![out_of_range.clone()]
+        );
+        assert_eq!(
+            run("strftime(\"%A\")", json!([2024, 1, 1, 0, 0, 0, -1, 0])),
+            vec![out_of_range]
+        );
+        assert_eq!(
+            value("strftime(\"%Y %d\")", json!([2024, 20, 1, 0, 0, 0, 9, 0])),
+            json!("2024 01")
+        );
+    }
+
+    #[test]
+    fn test_strptime() {
+        assert_eq!(
+            value(
+                "strptime(\"%Y-%m-%dT%H:%M:%SZ\")",
+                json!("2015-03-05T23:51:47Z")
+            ),
+            json!([2015.0, 2.0, 5.0, 23.0, 51.0, 47.0, 4.0, 63.0])
+        );
+        assert_eq!(
+            value(
+                "strptime(\"%d %b %Y %I:%M %p\") | mktime",
+                json!("5 mar 2015 11:51 pm")
+            ),
+            json!(1425599460.0)
+        );
+        assert_eq!(
+            value(
+                "strptime(\"%a, %d %B %y %T %z\") | todate",
+                json!("Thu, 05 March 15 23:51:47 +0100")
+            ),
+            json!("2015-03-05T23:51:47Z")
+        );
+        assert_eq!(
+            value("strptime(\"%s\") | todate", json!("86400")),
+            json!("1970-01-02T00:00:00Z")
+        );
+        assert_eq!(
+            value("strptime(\"%Y %j\") | todate", json!("2024 60")),
+            json!("2024-02-29T00:00:00Z")
+        );
+        assert_eq!(
+            run("strptime(\"%Y-%m-%d\")", json!("2015-13-01")),
+            vec![Err(JqError::Runtime(
+                "date \"2015-13-01\" does not match format \"%Y-%m-%d\"".to_string()
+            ))]
+        );
+    }
+}
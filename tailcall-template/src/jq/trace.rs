@@ -0,0 +1,229 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use jaq_core::box_iter::box_once;
+use jaq_core::{Bind, Cv, FilterT, Native, ValX, ValXs};
+
+use super::env;
+use super::jq::JsonLikeHelper;
+use super::template::{Env, Fun};
+use crate::jsonlike::JsonLike;
+
+/// The stream a message of a jq builtin is written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JqStream {
+    /// Output of `debug`, formatted as `["DEBUG:",<value>]`.
+    Debug,
+    /// Output of `stderr`, the raw input string or its compact JSON.
+    Stderr,
+}
+
+/// Destination for the output of the `debug` and `stderr` builtins.
+/// Without a sink the messages are written to the process stderr.
+/// Sinks are shared by every run of a [`JqTemplate`](super::JqTemplate), on
+/// any thread.
+pub trait JqSink: Send + Sync {
+    fn write(&self, stream: JqStream, message: &str);
+}
+
+impl<F: Fn(JqStream, &str) + Send + Sync> JqSink for F {
+    fn write(&self, stream: JqStream, message: &str) {
+        self(stream, message)
+    }
+}
+
+/// A single visit of a filter node during a traced run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent<A> {
+    /// Index of the visited node in [`JqTrace::nodes`].
+    pub node: usize,
+    /// Number of traced nodes that were being evaluated when this one started.
+    pub depth: usize,
+    pub input: A,
+    pub outputs: Vec<A>,
+    /// Whether the node stopped with an error (including errors caught by `try`).
+    pub failed: bool,
+    /// Time spent evaluating the node, including its children.
+    pub elapsed: Duration,
+}
+
+/// Record of a traced run, with events in the order the nodes were visited.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JqTrace<A> {
+    /// Source code of every traced node of the filter.
+    pub nodes: Vec<String>,
+    pub events: Vec<TraceEvent<A>>,
+}
+
+impl<A> JqTrace<A> {
+    /// Returns the source code of the node visited by `event`.
+    pub fn source(&self, event: &TraceEvent<A>) -> &str {
+        &self.nodes[event.node]
+    }
+}
+
+pub(crate) struct Recorder<A> {
+    depth: usize,
+    pub(crate) events: Vec<TraceEvent<A>>,
+}
+
+impl<A> Default for Recorder<A> {
+    fn default() -> Self {
+        Self {
+            depth: 0,
+            events: Vec::new(),
+        }
+    }
+}
+
+/// Outputs of a traced node, recording every output into its event.
+struct Traced<'a, A>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    env: Rc<Env<A>>,
+    event: usize,
+    outputs: ValXs<'a, JsonLikeHelper<A>>,
+}
+
+impl<'a, A> Iterator for Traced<'a, A>
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    type Item = ValX<'a, JsonLikeHelper<A>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let recorder = self.env.trace.as_ref()?;
+        recorder.borrow_mut().depth += 1;
+        let start = Instant::now();
+        let output = self.outputs.next();
+        let elapsed = start.elapsed();
+
+        let mut recorder = recorder.borrow_mut();
+        recorder.depth -= 1;
+        let event = &mut recorder.events[self.event];
+        event.elapsed += elapsed;
+        match &output {
+            Some(Ok(value)) => event.outputs.push(value.0.clone()),
+            Some(Err(_)) => event.failed = true,
+            None => (),
+        }
+        output
+    }
+}
+
+fn run_traced<'a, A>(
+    recorder: &RefCell<Recorder<A>>,
+    node: usize,
+    input: &A,
+    run: impl FnOnce() -> ValXs<'a, JsonLikeHelper<A>>,
+) -> (usize, ValXs<'a, JsonLikeHelper<A>>)
+where
+    A: for<'b> JsonLike<'b> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let event = {
+        let mut recorder = recorder.borrow_mut();
+        let depth = recorder.depth;
+        recorder.events.push(TraceEvent {
+            node,
+            depth,
+            input: input.clone(),
+            outputs: Vec::new(),
+            failed: false,
+            elapsed: Duration::ZERO,
+        });
+        recorder.depth += 1;
+        recorder.events.len() - 1
+    };
+
+    let start = Instant::now();
+    let outputs = run();
+    let elapsed = start.elapsed();
+
+    let mut recorder = recorder.borrow_mut();
+    recorder.depth -= 1;
+    recorder.events[event].elapsed += elapsed;
+    (event, outputs)
+}
+
+fn emit<A>(stream: JqStream, value: &JsonLikeHelper<A>)
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    let message = match stream {
+        JqStream::Debug => format!("[\"DEBUG:\",{}]", value),
+        JqStream::Stderr => match value.0.as_str() {
+            Some(s) => s.to_string(),
+            None => value.to_string(),
+        },
+    };
+
+    match env::current::<Env<A>>().and_then(|env| env.sink.clone()) {
+        Some(sink) => sink.write(stream, &message),
+        None if stream == JqStream::Debug => eprintln!("{}", message),
+        None => eprint!("{}", message),
+    }
+}
+
+/// Native filters for `debug`, `stderr` and the `_trace` wrapper that is
+/// inserted around every node of a traced filter.
+pub(crate) fn funs<A>() -> Vec<Fun<A>>
+where
+    A: for<'a> JsonLike<'a> + std::fmt::Display + std::clone::Clone + std::cmp::PartialEq + 'static,
+{
+    vec![
+        (
+            "debug",
+            Box::new([]),
+            Native::new(|_, cv| {
+                emit(JqStream::Debug, &cv.1);
+                box_once(Ok(cv.1))
+            })
+            .with_update(|_, cv, f| {
+                emit(JqStream::Debug, &cv.1);
+                f(cv.1)
+            }),
+        ),
+        (
+            "stderr",
+            Box::new([]),
+            Native::new(|_, cv| {
+                emit(JqStream::Stderr, &cv.1);
+                box_once(Ok(cv.1))
+            })
+            .with_update(|_, cv, f| {
+                emit(JqStream::Stderr, &cv.1);
+                f(cv.1)
+            }),
+        ),
+        (
+            "_trace",
+            Box::new([Bind::Var(()), Bind::Fun(())]),
+            Native::new(|lut, mut cv: Cv<'_, JsonLikeHelper<A>>| {
+                let (f, fc) = cv.0.pop_fun();
+                let node = cv.0.pop_var().0.as_f64().unwrap_or_default() as usize;
+                let env = env::current::<Env<A>>().filter(|env| env.trace.is_some());
+                match env {
+                    Some(env) => {
+                        let recorder = env.trace.as_ref().unwrap();
+                        let input = cv.1 .0.clone();
+                        let (event, outputs) =
+                            run_traced(recorder, node, &input, || f.run(lut, (fc, cv.1)));
+                        Box::new(Traced {
+                            env,
+                            event,
+                            outputs,
+                        })
+                    }
+                    None => f.run(lut, (fc, cv.1)),
+                }
+            })
+            .with_update(|lut, mut cv, update| {
+                let (f, fc) = cv.0.pop_fun();
+                cv.0.pop_var();
+                f.update(lut, (fc, cv.1), update)
+            }),
+        ),
+    ]
+}
@@ -1,9 +1,135 @@
 use std::borrow::Cow;
 
+use serde::de::value::{Error, MapDeserializer, SeqDeserializer};
+use serde::de::{IntoDeserializer, Visitor};
+use serde::{forward_to_deserialize_any, Deserialize, Deserializer};
 use serde_json_borrow::{ObjectAsVec, Value};
 
 use super::{gather_path_matches, group_by_key, JsonLike, JsonObjectLike};
 
+/// Builds an object from its entries, keeping their order and duplicate keys.
+///
+/// `ObjectAsVec` can only be built from borrowed keys or by deserializing, so
+/// entries with owned keys are moved into it with [`Replay`].
+fn build_object<'ctx>(entries: Vec<(Cow<'ctx, str>, Value<'ctx>)>) -> ObjectAsVec<'ctx> {
+    if entries
+        .iter()
+        .all(|(key, _)| matches!(key, Cow::Borrowed(_)))
+    {
+        let entries = entries.into_iter().filter_map(|(key, value)| match key {
+            Cow::Borrowed(key) => Some((key, value)),
+            Cow::Owned(_) => None,
+        });
+        return ObjectAsVec::from(entries.collect::<Vec<_>>());
+    }
+    let entries = entries.into_iter().map(|(key, value)| (key, Replay(value)));
+    match Value::deserialize(MapDeserializer::<_, Error>::new(entries)) {
+        Ok(Value::Object(object)) => object,
+        _ => unreachable!("replaying entries builds an object"),
+    }
+}
+
+/// Copies every borrowed string and key of `value`, so that it no longer
+/// borrows the input it was parsed from.
+pub fn into_owned(value: Value<'_>) -> Value<'static> {
+    match Value::deserialize(ReplayOwned(value)) {
+        Ok(value) => value,
+        Err(_) => unreachable!("replaying a value can't fail"),
+    }
+}
+
+/// Deserializer that hands a value back as it is, moving its strings and
+/// keeping the borrowed ones borrowed.
+struct Replay<'ctx>(Value<'ctx>);
+
+impl<'ctx> IntoDeserializer<'ctx, Error> for Replay<'ctx> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'ctx> Deserializer<'ctx> for Replay<'ctx> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'ctx>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Str(Cow::Borrowed(s)) => visitor.visit_borrowed_str(s),
+            Value::Str(Cow::Owned(s)) => visitor.visit_string(s),
+            Value::Array(arr) => {
+                visitor.visit_seq(SeqDeserializer::new(arr.into_iter().map(Replay)))
+            }
+            Value::Object(obj) => visitor.visit_map(MapDeserializer::new(
+                obj.into_vec()
+                    .into_iter()
+                    .map(|(key, value)| (key, Replay(value))),
+            )),
+            value => visit_scalar(value, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        <W: Visitor<'ctx>>
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Like [`Replay`], copying the borrowed strings.
+struct ReplayOwned<'ctx>(Value<'ctx>);
+
+impl<'de> IntoDeserializer<'de, Error> for ReplayOwned<'_> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> Deserializer<'de> for ReplayOwned<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.0 {
+            Value::Str(s) => visitor.visit_string(s.into_owned()),
+            Value::Array(arr) => {
+                visitor.visit_seq(SeqDeserializer::new(arr.into_iter().map(ReplayOwned)))
+            }
+            Value::Object(obj) => visitor.visit_map(MapDeserializer::new(
+                obj.into_vec()
+                    .into_iter()
+                    .map(|(key, value)| (key.into_owned(), ReplayOwned(value))),
+            )),
+            value => visit_scalar(value, visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+/// Visits `null`, a boolean or a number.
+fn visit_scalar<'de, V: Visitor<'de>>(value: Value<'_>, visitor: V) -> Result<V::Value, Error> {
+    match value {
+        Value::Bool(b) => visitor.visit_bool(b),
+        Value::Number(_) => {
+            if let Some(n) = value.as_u64() {
+                visitor.visit_u64(n)
+            } else if let Some(n) = value.as_i64() {
+                visitor.visit_i64(n)
+            } else {
+                visitor.visit_f64(value.as_f64().unwrap_or_default())
+            }
+        }
+        _ => visitor.visit_unit(),
+    }
+}
+
 // BorrowedValue
 impl<'ctx> JsonObjectLike<'ctx> for ObjectAsVec<'ctx> {
     type Value = Value<'ctx>;
@@ -21,13 +147,39 @@ impl<'ctx> JsonObjectLike<'ctx> for ObjectAsVec<'ctx> {
         self.get(key)
     }
 
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self::Value> {
+        self.get_mut(key)
+    }
+
     fn insert_key(&mut self, key: &'ctx str, value: Self::Value) {
         self.insert(key, value);
     }
 
-    fn remove_key(&mut self, _key: &'ctx str) -> Option<Self::Value> {
-        // TODO: implement it
-        unimplemented!()
+    fn insert_key_cow(&mut self, key: Cow<'ctx, str>, value: Self::Value) {
+        match key {
+            Cow::Borrowed(key) => self.insert_key(key, value),
+            Cow::Owned(key) => match self.get_mut(&key) {
+                Some(slot) => *slot = value,
+                None => {
+                    // `ObjectAsVec` has no way to add an owned key, so the
+                    // entries are moved into a new object.
+                    let mut entries = std::mem::take(self).into_vec();
+                    entries.push((Cow::Owned(key), value));
+                    *self = build_object(entries);
+                }
+            },
+        }
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self::Value> {
+        if !self.contains_key(key) {
+            return None;
+        }
+        let mut entries = std::mem::take(self).into_vec();
+        let index = entries.iter().position(|(k, _)| k == key)?;
+        let (_, removed) = entries.remove(index);
+        *self = build_object(entries);
+        Some(removed)
     }
 
     fn iter(&'ctx self) -> impl Iterator<Item = (&'ctx str, &'ctx Self::Value)> {
@@ -50,6 +202,10 @@ impl<'ctx> JsonLike<'ctx> for Value<'ctx> {
         Value::Object(ObjectAsVec::from(pairs))
     }
 
+    fn obj_cow(pairs: Vec<(Cow<'ctx, str>, Self)>) -> Self {
+        Value::Object(build_object(pairs))
+    }
+
     fn array(arr: Vec<Self>) -> Self {
         Value::Array(arr)
     }
@@ -58,6 +214,18 @@ impl<'ctx> JsonLike<'ctx> for Value<'ctx> {
         Value::Str(s)
     }
 
+    fn boolean(b: bool) -> Self {
+        Value::Bool(b)
+    }
+
+    fn number_i64(n: i64) -> Self {
+        Value::Number(n.into())
+    }
+
+    fn number_u64(n: u64) -> Self {
+        Value::Number(n.into())
+    }
+
     fn number_f64(n: f64) -> Self {
         Value::Number(n.into())
     }
@@ -152,3 +320,51 @@ impl<'ctx> JsonLike<'ctx> for Value<'ctx> {
         group_by_key(src)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use pretty_assertions::assert_eq;
+    use serde_json_borrow::Value;
+
+    use super::into_owned;
+    use crate::jsonlike::{JsonLike, JsonObjectLike};
+
+    #[test]
+    fn test_remove_key_keeps_duplicates() {
+        let cases = [
+            (r#"{"k":1,"k":2,"z":3}"#, "k"),
+            (r#"{"k\n":1,"k\n":2,"z":3}"#, "k\n"),
+        ];
+        for (input, key) in cases {
+            let mut value: Value = serde_json::from_str(input).unwrap();
+            let obj = value.as_object_mut().unwrap();
+            assert_eq!(obj.remove_key("z").and_then(|z| z.as_u64()), Some(3));
+            assert_eq!(obj.remove_key("z"), None);
+            let expected = vec![(key, Some(1)), (key, Some(2))];
+            assert_eq!(entries(&value), expected);
+            assert_eq!(entries(&into_owned(value)), expected);
+        }
+    }
+
+    #[test]
+    fn test_insert_owned_key() {
+        let mut value: Value = serde_json::from_str(r#"{"k":1,"k":2,"s":"x"}"#).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        obj.insert_key_cow(Cow::Owned("k".into()), Value::from(3u64));
+        obj.insert_key_cow(Cow::Owned("n".into()), Value::from(4u64));
+        assert_eq!(
+            entries(&value),
+            vec![("k", Some(3)), ("k", Some(2)), ("s", None), ("n", Some(4))]
+        );
+        assert!(matches!(value.get("s"), Value::Str(Cow::Borrowed("x"))));
+    }
+
+    fn entries<'a>(value: &'a Value<'_>) -> Vec<(&'a str, Option<u64>)> {
+        let obj = value.as_object().unwrap();
+        obj.iter()
+            .map(|(key, value)| (key, value.as_u64()))
+            .collect()
+    }
+}
@@ -0,0 +1,304 @@
+//! Canonical JSON text for any [`JsonLike`] value, following the JSON
+//! Canonicalization Scheme ([RFC 8785](https://www.rfc-editor.org/rfc/rfc8785)).
+//!
+//! Object keys are sorted by their UTF-16 code units, numbers are written like
+//! ECMAScript's `Number.prototype.toString` and strings only escape what JSON
+//! requires. Values are written as [`JsonSerializer`](super::JsonSerializer)
+//! writes them otherwise: GraphQL enums as strings and binary values as
+//! arrays of bytes. All numbers are IEEE 754 doubles in the scheme, so
+//! integers that don't fit exactly are rounded.
+use std::fmt::Write;
+
+use super::pointer::escape_token;
+use super::{ConvertError, JsonLike, JsonObjectLike, Unrepresentable};
+
+/// Canonical JSON text of `value`. Fails on NaN and infinities, which have no
+/// JSON representation.
+pub fn to_canonical_string<'a, A: JsonLike<'a>>(value: &'a A) -> Result<String, ConvertError> {
+    let mut out = String::new();
+    write_value(&mut out, value, &mut String::new())?;
+    Ok(out)
+}
+
+fn write_value<'a, A: JsonLike<'a>>(
+    out: &mut String,
+    value: &'a A,
+    path: &mut String,
+) -> Result<(), ConvertError> {
+    if value.is_null() {
+        out.push_str("null");
+    } else if let Some(b) = value.as_bool() {
+        out.push_str(if b { "true" } else { "false" });
+    } else if let Some(s) = value.as_str().or_else(|| value.as_enum()) {
+        write_quoted(out, s);
+    } else if let Some(arr) = value.as_array() {
+        out.push('[');
+        for (index, item) in arr.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            let len = path.len();
+            let _ = write!(path, "/{}", index);
+            write_value(out, item, path)?;
+            path.truncate(len);
+        }
+        out.push(']');
+    } else if let Some(obj) = value.as_object() {
+        let mut entries: Vec<_> = obj.iter().collect();
+        entries.sort_by(|(l, _), (r, _)| l.encode_utf16().cmp(r.encode_utf16()));
+        out.push('{');
+        for (index, (key, item)) in entries.into_iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            write_quoted(out, key);
+            out.push(':');
+            let len = path.len();
+            path.push('/');
+            path.push_str(&escape_token(key));
+            write_value(out, item, path)?;
+            path.truncate(len);
+        }
+        out.push('}');
+    } else if let Some(n) = value.as_i64() {
+        write_number(out, n as f64);
+    } else if let Some(n) = value.as_u64() {
+        write_number(out, n as f64);
+    } else if let Some(n) = value.as_f64() {
+        if !n.is_finite() {
+            return Err(ConvertError {
+                path: path.clone(),
+                value: Unrepresentable::NonFinite(n),
+            });
+        }
+        write_number(out, n);
+    } else if let Some(bytes) = value.as_binary() {
+        out.push('[');
+        for (index, byte) in bytes.iter().enumerate() {
+            if index > 0 {
+                out.push(',');
+            }
+            let _ = write!(out, "{}", byte);
+        }
+        out.push(']');
+    } else {
+        out.push_str("null");
+    }
+    Ok(())
+}
+
+fn write_quoted(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\x08' => out.push_str("\\b"),
+            '\x0C' => out.push_str("\\f"),
+            c if c < ' ' => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Writes a finite `n` as ECMAScript does, from its shortest round-trip
+/// digits.
+pub(super) fn write_number(out: &mut String, n: f64) {
+    if n == 0.0 {
+        // Also for -0.
+        out.push('0');
+        return;
+    }
+    if n < 0.0 {
+        out.push('-');
+    }
+    let (digits, exponent) = shortest_digits(n.abs());
+    let k = digits.len() as i32;
+    // The value is 0.digits * 10^n.
+    let n = exponent + 1;
+
+    if k <= n && n <= 21 {
+        out.push_str(&digits);
+        out.extend(std::iter::repeat_n('0', (n - k) as usize));
+    } else if 0 < n && n <= 21 {
+        let (int, frac) = digits.split_at(n as usize);
+        out.push_str(int);
+        out.push('.');
+        out.push_str(frac);
+    } else if -6 < n && n <= 0 {
+        out.push_str("0.");
+        out.extend(std::iter::repeat_n('0', -n as usize));
+        out.push_str(&digits);
+    } else {
+        let (first, rest) = digits.split_at(1);
+        out.push_str(first);
+        if !rest.is_empty() {
+            out.push('.');
+            out.push_str(rest);
+        }
+        let _ = write!(out, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs());
+    }
+}
+
+/// Shortest digits that round-trip to a finite positive `n`, and the exponent
+/// of the first one. When two candidates are as close to `n`, ECMAScript
+/// takes the even one, which Rust's formatting doesn't guarantee.
+fn shortest_digits(n: f64) -> (String, i32) {
+    let sci = format!("{:e}", n);
+    let (mantissa, exponent) = sci.split_once('e').unwrap_or((&sci, "0"));
+    let mut digits = mantissa.replace('.', "");
+    let exponent = exponent.parse::<i32>().unwrap_or(0);
+
+    let last = digits.as_bytes()[digits.len() - 1] - b'0';
+    if !last.is_multiple_of(2) {
+        let exact = format!("{:.800e}", n);
+        let exact = exact.split_once('e').map_or("", |(mantissa, _)| mantissa);
+        let exact = exact.replace('.', "");
+        let (head, tail) = exact.split_at(digits.len().min(exact.len()));
+        let tie = tail.starts_with('5') && tail[1..].bytes().all(|b| b == b'0');
+        if tie {
+            // `n` is halfway between `head` and the digits above it, and the
+            // even one of the two is `head` if its last digit is even.
+            let head_last = head.as_bytes()[head.len() - 1] - b'0';
+            let candidate = if head_last.is_multiple_of(2) {
+                head.to_string()
+            } else if head_last == 9 {
+                // Rounding up would carry into a shorter candidate, which
+                // would already have been chosen.
+                digits.clone()
+            } else {
+                let mut up = head[..head.len() - 1].to_string();
+                up.push((b'0' + head_last + 1) as char);
+                up
+            };
+            let parsed = format!("0.{}e{}", candidate, exponent + 1).parse::<f64>();
+            if candidate != digits && parsed == Ok(n) {
+                digits = candidate;
+            }
+        }
+    }
+    (digits, exponent)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::to_canonical_string;
+    use crate::jsonlike::{convert, JsonLike, Unrepresentable};
+
+    fn all_backends(value: &Value) -> String {
+        let expected = to_canonical_string(value).unwrap();
+        let text = value.to_string();
+        let borrowed: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(to_canonical_string(&borrowed).unwrap(), expected);
+        let graphql: ConstValue = convert(value);
+        assert_eq!(to_canonical_string(&graphql).unwrap(), expected);
+        let val: Val = convert(value);
+        assert_eq!(to_canonical_string(&val).unwrap(), expected);
+        expected
+    }
+
+    #[test]
+    #[allow(clippy::excessive_precision)]
+    fn test_rfc_example() {
+        // Section 3.2.2 of RFC 8785
+        let value = json!({
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u{20ac}$\u{000F}\u{000a}A'\u{0042}\u{0022}\u{005c}\\\"/",
+            "literals": [null, true, false],
+        });
+        assert_eq!(
+            all_backends(&value),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn test_rfc_sorting() {
+        // Section 3.2.3 of RFC 8785
+        let value = json!({
+            "\u{20ac}": "Euro Sign",
+            "\r": "Carriage Return",
+            "\u{fb33}": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\u{1f600}": "Emoji: Grinning Face",
+            "\u{0080}": "Control",
+            "\u{00f6}": "Latin Small Letter O With Diaeresis",
+        });
+        assert_eq!(
+            all_backends(&value),
+            "{\"\\r\":\"Carriage Return\",\"1\":\"One\",\"\u{80}\":\"Control\",\
+             \"ö\":\"Latin Small Letter O With Diaeresis\",\"€\":\"Euro Sign\",\
+             \"😀\":\"Emoji: Grinning Face\",\"\u{fb33}\":\"Hebrew Letter Dalet With Dagesh\"}"
+        );
+    }
+
+    #[test]
+    fn test_rfc_numbers() {
+        // Appendix B of RFC 8785
+        let cases = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "9.999999999999997e-7"),
+            (0x3eb0c6f7a0b5ed8d, "0.000001"),
+            (0x3eb0c6f7a0b5ed8e, "0.0000010000000000000002"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in cases {
+            let n = f64::from_bits(bits);
+            assert_eq!(
+                to_canonical_string(&json!(n)).unwrap(),
+                expected,
+                "{:x}",
+                bits
+            );
+            assert_eq!(
+                to_canonical_string(&Val::Float(n)).unwrap(),
+                expected,
+                "{:x}",
+                bits
+            );
+        }
+        assert_eq!(
+            to_canonical_string(&json!(u64::MAX)).unwrap(),
+            "18446744073709552000"
+        );
+
+        for bits in [0x7fffffffffffffff, 0x7ff0000000000000] {
+            let mut value: Val = convert(&json!({"a": [null]}));
+            let _ = value.set_path(&["a", "0"], Val::Float(f64::from_bits(bits)));
+            let err = to_canonical_string(&value).unwrap_err();
+            assert_eq!(err.path, "/a/0");
+            assert!(matches!(err.value, Unrepresentable::NonFinite(_)));
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Conversion between [`JsonLike`] backends.
+//!
+//! Integers are converted as integers, so numbers only go through `f64` when
+//! the source stores them as floats. Values the target can't represent follow
+//! a fixed policy:
+//!
+//! | value              | [`convert`]            | [`try_convert`] |
+//! |--------------------|------------------------|-----------------|
+//! | GraphQL enum       | its name as a string   | error           |
+//! | binary             | an array of its bytes  | error           |
+//! | NaN and infinities | null                   | error           |
+//!
+//! Enums and binary values are kept as they are when the target supports them.
+//! Strings and keys are borrowed from the source when the target can borrow.
+use std::borrow::Cow;
+use std::fmt::{Display, Write};
+
+use super::pointer::escape_token;
+use super::{JsonLike, JsonObjectLike};
+
+/// Converts `value` into another backend, replacing values the target can't
+/// represent as described in the [module docs](self).
+pub fn convert<'a, A, B>(value: &'a A) -> B
+where
+    A: JsonLike<'a>,
+    B: JsonLike<'a>,
+{
+    // Coercing never fails.
+    transcode(value, Policy::Coerce, &mut String::new()).unwrap_or_else(|_| B::null())
+}
+
+/// Converts `value` into another backend, failing on the first value the
+/// target can't represent.
+pub fn try_convert<'a, A, B>(value: &'a A) -> Result<B, ConvertError>
+where
+    A: JsonLike<'a>,
+    B: JsonLike<'a>,
+{
+    transcode(value, Policy::Reject, &mut String::new())
+}
+
+/// A value that [`try_convert`] could not represent in the target backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertError {
+    /// JSON Pointer to the value.
+    pub path: String,
+    pub value: Unrepresentable,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Unrepresentable {
+    Enum(String),
+    Binary(Vec<u8>),
+    NonFinite(f64),
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.value {
+            Unrepresentable::Enum(name) => write!(f, "enum value {}", name)?,
+            Unrepresentable::Binary(bytes) => write!(f, "binary value of {} bytes", bytes.len())?,
+            Unrepresentable::NonFinite(n) => write!(f, "number {}", n)?,
+        }
+        write!(f, " at \"{}\" can't be represented", self.path)
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+#[derive(Clone, Copy)]
+enum Policy {
+    Coerce,
+    Reject,
+}
+
+fn transcode<'a, A, B>(value: &'a A, policy: Policy, path: &mut String) -> Result<B, ConvertError>
+where
+    A: JsonLike<'a>,
+    B: JsonLike<'a>,
+{
+    if value.is_null() {
+        return Ok(B::null());
+    }
+    if let Some(b) = value.as_bool() {
+        return Ok(B::boolean(b));
+    }
+    if let Some(n) = value.as_i64() {
+        return Ok(B::number_i64(n));
+    }
+    if let Some(n) = value.as_u64() {
+        return Ok(B::number_u64(n));
+    }
+    if let Some(n) = value.as_f64() {
+        return match policy {
+            _ if n.is_finite() => Ok(B::number_f64(n)),
+            Policy::Coerce => Ok(B::null()),
+            Policy::Reject => Err(error(path, Unrepresentable::NonFinite(n))),
+        };
+    }
+    if let Some(s) = value.as_str() {
+        return Ok(B::string(Cow::Borrowed(s)));
+    }
+    if let Some(arr) = value.as_array() {
+        let mut items = Vec::with_capacity(arr.len());
+        for (i, item) in arr.iter().enumerate() {
+            let len = path.len();
+            let _ = write!(path, "/{}", i);
+            items.push(transcode(item, policy, path)?);
+            path.truncate(len);
+        }
+        return Ok(B::array(items));
+    }
+    if let Some(obj) = value.as_object() {
+        let mut pairs = Vec::new();
+        for (key, item) in obj.iter() {
+            let len = path.len();
+            path.push('/');
+            path.push_str(&escape_token(key));
+            pairs.push((key, transcode(item, policy, path)?));
+            path.truncate(len);
+        }
+        return Ok(B::obj(pairs));
+    }
+    if let Some(name) = value.as_enum() {
+        return match (B::enum_value(name), policy) {
+            (Some(value), _) => Ok(value),
+            (None, Policy::Coerce) => Ok(B::string(Cow::Borrowed(name))),
+            (None, Policy::Reject) => Err(error(path, Unrepresentable::Enum(name.to_string()))),
+        };
+    }
+    if let Some(bytes) = value.as_binary() {
+        return match (B::binary(bytes), policy) {
+            (Some(value), _) => Ok(value),
+            (None, Policy::Coerce) => Ok(B::array(
+                bytes.iter().map(|b| B::number_u64(u64::from(*b))).collect(),
+            )),
+            (None, Policy::Reject) => Err(error(path, Unrepresentable::Binary(bytes.to_vec()))),
+        };
+    }
+    Ok(B::null())
+}
+
+fn error(path: &str, value: Unrepresentable) -> ConvertError {
+    ConvertError {
+        path: path.to_string(),
+        value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+    use std::rc::Rc;
+
+    use async_graphql::Name;
+    use async_graphql_value::ConstValue;
+    use indexmap::IndexMap;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{convert, try_convert, ConvertError, Unrepresentable};
+    use crate::jsonlike::into_owned;
+
+    fn sample() -> serde_json::Value {
+        json!({
+            "a": [1, -2, 2.5, "x", true, null],
+            "big": u64::MAX,
+            "small": i64::MIN,
+            "o": {"k/~": {}}
+        })
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let value = sample();
+
+        let graphql: ConstValue = convert(&value);
+        assert_eq!(convert::<_, serde_json::Value>(&graphql), value);
+
+        let val: Val = convert(&value);
+        assert_eq!(convert::<_, serde_json::Value>(&val), value);
+
+        let input = value.to_string();
+        let borrowed: serde_json_borrow::Value = serde_json::from_str(&input).unwrap();
+        assert_eq!(convert::<_, serde_json::Value>(&borrowed), value);
+        let borrowed: serde_json_borrow::Value = convert(&value);
+        assert_eq!(convert::<_, ConstValue>(&borrowed), graphql);
+    }
+
+    #[test]
+    fn test_large_integers_are_lossless() {
+        let value = json!([u64::MAX, i64::MIN, 9007199254740993u64]);
+        let graphql: ConstValue = convert(&value);
+        let val: Val = convert(&graphql);
+        let back: serde_json::Value = convert(&val);
+        assert_eq!(
+            back.to_string(),
+            "[18446744073709551615,-9223372036854775808,9007199254740993]"
+        );
+    }
+
+    #[test]
+    fn test_borrows_strings() {
+        let value = json!({"s": "text"});
+        let borrowed: serde_json_borrow::Value = convert(&value);
+        assert!(matches!(
+            borrowed.get("s"),
+            serde_json_borrow::Value::Str(Cow::Borrowed("text"))
+        ));
+    }
+
+    #[test]
+    fn test_enum_and_binary() {
+        let mut map = IndexMap::new();
+        map.insert(Name::new("e"), ConstValue::Enum(Name::new("ADMIN")));
+        map.insert(Name::new("b"), ConstValue::Binary(vec![1, 255].into()));
+        let value = ConstValue::Object(map);
+
+        assert_eq!(convert::<_, ConstValue>(&value), value);
+        assert_eq!(
+            convert::<_, serde_json::Value>(&value),
+            json!({"e": "ADMIN", "b": [1, 255]})
+        );
+        assert_eq!(
+            try_convert::<_, serde_json::Value>(&value),
+            Err(ConvertError {
+                path: "/e".to_string(),
+                value: Unrepresentable::Enum("ADMIN".to_string())
+            })
+        );
+
+        let value = ConstValue::List(vec![ConstValue::Binary(vec![1].into())]);
+        let err = try_convert::<_, Val>(&value).unwrap_err();
+        assert_eq!(err.value, Unrepresentable::Binary(vec![1]));
+        assert_eq!(
+            err.to_string(),
+            "binary value of 1 bytes at \"/0\" can't be represented"
+        );
+    }
+
+    #[test]
+    fn test_non_finite_numbers() {
+        let value = Val::from(json!({"a": [1]}));
+        let value = Val::Arr(Rc::new(vec![
+            value,
+            Val::Float(f64::NAN),
+            Val::Float(f64::INFINITY),
+        ]));
+        assert_eq!(
+            convert::<_, serde_json::Value>(&value),
+            json!([{"a": [1]}, null, null])
+        );
+        assert_eq!(
+            convert::<_, ConstValue>(&value),
+            convert::<_, ConstValue>(&json!([{"a": [1]}, null, null]))
+        );
+        let err = try_convert::<_, serde_json::Value>(&value).unwrap_err();
+        assert_eq!(err.path, "/1");
+        assert!(matches!(err.value, Unrepresentable::NonFinite(n) if n.is_nan()));
+    }
+
+    #[test]
+    fn test_into_owned() {
+        let owned = {
+            let input = r#"{"a": ["x", {"b": 1}], "c": "y"}"#.to_string();
+            let value: serde_json_borrow::Value = serde_json::from_str(&input).unwrap();
+            into_owned(value)
+        };
+        assert_eq!(
+            convert::<_, serde_json::Value>(&owned),
+            json!({"a": ["x", {"b": 1}], "c": "y"})
+        );
+        assert!(matches!(
+            owned.get("c"),
+            serde_json_borrow::Value::Str(Cow::Owned(_))
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+//! Structural comparison of [`JsonLike`] values, possibly of different
+//! backends, reporting the paths at which they differ.
+use super::pointer::escape_token;
+use super::{json_eq, JsonLike, JsonObjectLike};
+
+/// A difference found by [`JsonDiff::diff`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference<'a, 'b, A, B> {
+    /// JSON Pointer of the value that differs.
+    pub path: String,
+    pub kind: ChangeKind,
+    /// The value on the left, unless it was added.
+    pub old: Option<&'a A>,
+    /// The value on the right, unless it was removed.
+    pub new: Option<&'b B>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The value only exists on the right.
+    Added,
+    /// The value only exists on the left.
+    Removed,
+    /// The values are different scalars, or have different types.
+    Changed,
+}
+
+/// Compares two values: objects key by key and arrays item by item, and
+/// everything else by value. Numbers are compared regardless of how they are
+/// stored, and enums like strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JsonDiff {
+    float_tolerance: f64,
+    ignore_array_order: bool,
+    ignored_paths: Vec<String>,
+}
+
+impl JsonDiff {
+    /// Numbers that differ by at most `tolerance` are equal, unless both are
+    /// integers, which are always compared exactly.
+    pub fn float_tolerance(self, tolerance: f64) -> Self {
+        Self {
+            float_tolerance: tolerance,
+            ..self
+        }
+    }
+
+    /// Array items are matched with equal items anywhere in the other array.
+    /// Differences in such arrays are only reported as added or removed
+    /// items, at their index in their own array.
+    pub fn ignore_array_order(self, ignore: bool) -> Self {
+        Self {
+            ignore_array_order: ignore,
+            ..self
+        }
+    }
+
+    /// Skips the value at `pointer` and everything below it. A `*` token
+    /// stands for any key or index, as in `/users/*/updatedAt`.
+    pub fn ignore_path(mut self, pointer: impl Into<String>) -> Self {
+        self.ignored_paths.push(pointer.into());
+        self
+    }
+
+    /// Differences between `left` and `right`, in document order.
+    pub fn diff<'a, 'b, A: JsonLike<'a>, B: JsonLike<'b>>(
+        &self,
+        left: &'a A,
+        right: &'b B,
+    ) -> Vec<Difference<'a, 'b, A, B>> {
+        let mut differences = Vec::new();
+        self.compare(left, right, "", &mut differences);
+        differences
+    }
+
+    fn compare<'a, 'b, A: JsonLike<'a>, B: JsonLike<'b>>(
+        &self,
+        left: &'a A,
+        right: &'b B,
+        path: &str,
+        out: &mut Vec<Difference<'a, 'b, A, B>>,
+    ) {
+        if self.is_ignored(path) {
+            return;
+        }
+        if let (Some(left), Some(right)) = (left.as_object(), right.as_object()) {
+            for (key, old) in left.iter() {
+                let path = format!("{}/{}", path, escape_token(key));
+                match right.get_key(key) {
+                    Some(new) => self.compare(old, new, &path, out),
+                    None => self.push(out, path, ChangeKind::Removed, Some(old), None),
+                }
+            }
+            for (key, new) in right.iter() {
+                if left.get_key(key).is_none() {
+                    let path = format!("{}/{}", path, escape_token(key));
+                    self.push(out, path, ChangeKind::Added, None, Some(new));
+                }
+            }
+        } else if let (Some(left), Some(right)) = (left.as_array(), right.as_array()) {
+            if self.ignore_array_order {
+                self.compare_unordered(left, right, path, out);
+                return;
+            }
+            for (index, (old, new)) in left.iter().zip(right).enumerate() {
+                self.compare(old, new, &format!("{}/{}", path, index), out);
+            }
+            for (index, old) in left.iter().enumerate().skip(right.len()) {
+                let path = format!("{}/{}", path, index);
+                self.push(out, path, ChangeKind::Removed, Some(old), None);
+            }
+            for (index, new) in right.iter().enumerate().skip(left.len()) {
+                let path = format!("{}/{}", path, index);
+                self.push(out, path, ChangeKind::Added, None, Some(new));
+            }
+        } else if !self.scalar_equal(left, right) {
+            let path = path.to_string();
+            self.push(out, path, ChangeKind::Changed, Some(left), Some(right));
+        }
+    }
+
+    fn compare_unordered<'a, 'b, A: JsonLike<'a>, B: JsonLike<'b>>(
+        &self,
+        left: &'a [A],
+        right: &'b [B],
+        path: &str,
+        out: &mut Vec<Difference<'a, 'b, A, B>>,
+    ) {
+        let mut matched = vec![false; right.len()];
+        let mut scratch = Vec::new();
+        for (index, old) in left.iter().enumerate() {
+            let path = format!("{}/{}", path, index);
+            let found = right.iter().enumerate().position(|(j, new)| {
+                scratch.clear();
+                !matched[j] && {
+                    self.compare(old, new, &path, &mut scratch);
+                    scratch.is_empty()
+                }
+            });
+            match found {
+                Some(j) => matched[j] = true,
+                None => self.push(out, path, ChangeKind::Removed, Some(old), None),
+            }
+        }
+        for (index, new) in right.iter().enumerate() {
+            if !matched[index] {
+                let path = format!("{}/{}", path, index);
+                self.push(out, path, ChangeKind::Added, None, Some(new));
+            }
+        }
+    }
+
+    fn push<'a, 'b, A, B>(
+        &self,
+        out: &mut Vec<Difference<'a, 'b, A, B>>,
+        path: String,
+        kind: ChangeKind,
+        old: Option<&'a A>,
+        new: Option<&'b B>,
+    ) {
+        if !self.is_ignored(&path) {
+            out.push(Difference {
+                path,
+                kind,
+                old,
+                new,
+            });
+        }
+    }
+
+    fn is_ignored(&self, path: &str) -> bool {
+        self.ignored_paths.iter().any(|pattern| {
+            let mut pattern = pattern.split('/');
+            let mut path = path.split('/');
+            loop {
+                match (pattern.next(), path.next()) {
+                    (None, None) => return true,
+                    (Some(expected), Some(token)) if expected == "*" || expected == token => {}
+                    _ => return false,
+                }
+            }
+        })
+    }
+
+    fn scalar_equal<'a, 'b, A: JsonLike<'a>, B: JsonLike<'b>>(
+        &self,
+        left: &'a A,
+        right: &'b B,
+    ) -> bool {
+        if json_eq(left, right) {
+            return true;
+        }
+        // Integers are compared exactly, the tolerance is for floats only.
+        if integer(left).is_some() && integer(right).is_some() {
+            return false;
+        }
+        match (left.as_f64(), right.as_f64()) {
+            (Some(l), Some(r)) => (l - r).abs() <= self.float_tolerance,
+            _ => false,
+        }
+    }
+}
+
+fn integer<'a, A: JsonLike<'a>>(value: &A) -> Option<i128> {
+    value
+        .as_i64()
+        .map(i128::from)
+        .or_else(|| value.as_u64().map(i128::from))
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::{ConstValue, Name};
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{ChangeKind, JsonDiff};
+    use crate::jsonlike::{convert, JsonLike};
+
+    /// Path, kind, old and new values of the differences, for comparison.
+    fn summary<'a, 'b, A: JsonLike<'a>, B: JsonLike<'b>>(
+        diff: &JsonDiff,
+        left: &'a A,
+        right: &'b B,
+    ) -> Vec<(String, ChangeKind, Option<Value>, Option<Value>)> {
+        diff.diff(left, right)
+            .into_iter()
+            .map(|d| (d.path, d.kind, d.old.map(convert), d.new.map(convert)))
+            .collect()
+    }
+
+    #[test]
+    fn test_diff() {
+        // keys are sorted, so the order of the differences doesn't depend on
+        // whether `serde_json` preserves the order of keys
+        let left = json!({
+            "id": 1,
+            "old": null,
+            "score": 1.5,
+            "user": {"a/b": true, "name": "ann", "tags": ["x", "y"]}
+        });
+        let right = json!({
+            "id": 1.0,
+            "new": [1],
+            "score": "1.5",
+            "user": {"a/b": true, "name": "bob", "tags": ["x"]}
+        });
+        assert_eq!(
+            summary(&JsonDiff::default(), &left, &right),
+            vec![
+                (
+                    "/old".to_string(),
+                    ChangeKind::Removed,
+                    Some(json!(null)),
+                    None
+                ),
+                (
+                    "/score".to_string(),
+                    ChangeKind::Changed,
+                    Some(json!(1.5)),
+                    Some(json!("1.5"))
+                ),
+                (
+                    "/user/name".to_string(),
+                    ChangeKind::Changed,
+                    Some(json!("ann")),
+                    Some(json!("bob"))
+                ),
+                (
+                    "/user/tags/1".to_string(),
+                    ChangeKind::Removed,
+                    Some(json!("y")),
+                    None
+                ),
+                (
+                    "/new".to_string(),
+                    ChangeKind::Added,
+                    None,
+                    Some(json!([1]))
+                ),
+            ]
+        );
+        assert_eq!(summary(&JsonDiff::default(), &left, &left), vec![]);
+        assert_eq!(
+            summary(&JsonDiff::default(), &json!({"a": 1}), &json!([1])),
+            vec![(
+                "".to_string(),
+                ChangeKind::Changed,
+                Some(json!({"a": 1})),
+                Some(json!([1]))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_across_backends() {
+        let left = json!({"status": "ACTIVE", "ids": [1, 2], "ratio": 0.1});
+        let mut right: ConstValue = convert(&json!({"ids": [1, 3], "ratio": 0.30000000000000004}));
+        if let ConstValue::Object(obj) = &mut right {
+            obj.insert(Name::new("status"), ConstValue::Enum(Name::new("ACTIVE")));
+        }
+        let diff = JsonDiff::default();
+        assert_eq!(
+            summary(&diff, &left, &right),
+            vec![
+                (
+                    "/ids/1".to_string(),
+                    ChangeKind::Changed,
+                    Some(json!(2)),
+                    Some(json!(3))
+                ),
+                (
+                    "/ratio".to_string(),
+                    ChangeKind::Changed,
+                    Some(json!(0.1)),
+                    Some(json!(0.30000000000000004))
+                ),
+            ]
+        );
+        let differences = diff.diff(&left, &right);
+        assert_eq!(differences[0].old, Some(&json!(2)));
+        assert_eq!(differences[0].new, Some(&ConstValue::from(3)));
+    }
+
+    #[test]
+    fn test_float_tolerance() {
+        let left = json!({"a": 0.1, "b": 1, "c": [1.0000001]});
+        let right = json!({"a": 0.10000000000000002, "b": 1.0000001, "c": [1]});
+        assert_eq!(summary(&JsonDiff::default(), &left, &right).len(), 3);
+        assert_eq!(
+            summary(&JsonDiff::default().float_tolerance(1e-6), &left, &right),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_large_integers() {
+        let left = json!({"id": 9007199254740993u64, "n": -9007199254740993i64});
+        let right = json!({"id": 9007199254740992u64, "n": -9007199254740993i64});
+        let diff = JsonDiff::default().float_tolerance(1e-6);
+        assert_eq!(
+            summary(&diff, &left, &right),
+            vec![(
+                "/id".to_string(),
+                ChangeKind::Changed,
+                Some(json!(9007199254740993u64)),
+                Some(json!(9007199254740992u64))
+            )]
+        );
+    }
+
+    #[test]
+    fn test_ignore_array_order() {
+        let left = json!({"users": [{"id": 1, "n": "a"}, {"id": 2, "n": "b"}, {"id": 3}]});
+        let right = json!({"users": [{"id": 2, "n": "b"}, {"id": 4}, {"id": 1, "n": "a"}]});
+        let diff = JsonDiff::default().ignore_array_order(true);
+        assert_eq!(
+            summary(&diff, &left, &right),
+            vec![
+                (
+                    "/users/2".to_string(),
+                    ChangeKind::Removed,
+                    Some(json!({"id": 3})),
+                    None
+                ),
+                (
+                    "/users/1".to_string(),
+                    ChangeKind::Added,
+                    None,
+                    Some(json!({"id": 4}))
+                ),
+            ]
+        );
+        assert_eq!(summary(&diff, &json!([1, 1, 2]), &json!([2, 1, 1])), vec![]);
+        assert_eq!(summary(&diff, &json!([1, 1]), &json!([1])).len(), 1);
+    }
+
+    #[test]
+    fn test_ignored_paths() {
+        let left = json!({
+            "users": [{"id": 1, "updatedAt": 10}, {"id": 2, "updatedAt": 20}],
+            "meta": {"requestId": "a", "count": 2},
+            "x~y": 1
+        });
+        let right = json!({
+            "users": [{"id": 1, "updatedAt": 11}, {"id": 2}],
+            "meta": {"requestId": "b", "count": 2, "trace": []},
+            "x~y": 2
+        });
+        let diff = JsonDiff::default()
+            .ignore_path("/users/*/updatedAt")
+            .ignore_path("/meta/requestId")
+            .ignore_path("/meta/trace")
+            .ignore_path("/x~0y");
+        assert_eq!(summary(&diff, &left, &right), vec![]);
+
+        let diff = JsonDiff::default().ignore_path("/meta");
+        let paths: Vec<_> = diff
+            .diff(&left, &right)
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        assert_eq!(
+            paths,
+            vec!["/users/0/updatedAt", "/users/1/updatedAt", "/x~0y"]
+        );
+    }
+}
@@ -22,12 +22,20 @@ impl<'obj, Value: JsonLike<'obj> + Clone> JsonObjectLike<'obj> for IndexMap<Name
         self.get(key)
     }
 
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self::Value> {
+        self.get_mut(key)
+    }
+
     fn insert_key(&mut self, key: &'obj str, value: Self::Value) {
         self.insert(Name::new(key), value);
     }
 
-    fn remove_key(&mut self, key: &'obj str) -> Option<Self::Value> {
-        self.swap_remove(key)
+    fn insert_key_cow(&mut self, key: Cow<'obj, str>, value: Self::Value) {
+        self.insert(Name::new(key), value);
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self::Value> {
+        self.shift_remove(key)
     }
 
     fn iter(&'obj self) -> impl Iterator<Item = (&'obj str, &'obj Self::Value)> {
@@ -98,6 +106,20 @@ impl<'json> JsonLike<'json> for ConstValue {
         matches!(self, ConstValue::Null)
     }
 
+    fn as_enum(&self) -> Option<&str> {
+        match self {
+            ConstValue::Enum(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn as_binary(&self) -> Option<&[u8]> {
+        match self {
+            ConstValue::Binary(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
     fn get_path<T: AsRef<str>>(&self, path: &[T]) -> Option<&Self> {
         let mut val = self;
         for token in path {
@@ -162,6 +184,11 @@ impl<'json> JsonLike<'json> for ConstValue {
         ConstValue::Object(map)
     }
 
+    fn obj_cow(pairs: Vec<(Cow<'json, str>, Self)>) -> Self {
+        let map = pairs.into_iter().map(|(k, v)| (Name::new(k), v));
+        ConstValue::Object(map.collect())
+    }
+
     fn array(arr: Vec<Self>) -> Self {
         ConstValue::List(arr)
     }
@@ -170,7 +197,27 @@ impl<'json> JsonLike<'json> for ConstValue {
         ConstValue::String(s.to_string())
     }
 
+    fn boolean(b: bool) -> Self {
+        ConstValue::Boolean(b)
+    }
+
+    fn number_i64(n: i64) -> Self {
+        ConstValue::Number(Number::from(n))
+    }
+
+    fn number_u64(n: u64) -> Self {
+        ConstValue::Number(Number::from(n))
+    }
+
     fn number_f64(n: f64) -> Self {
-        ConstValue::Number(Number::from_f64(n).unwrap())
+        Number::from_f64(n).map_or(ConstValue::Null, ConstValue::Number)
+    }
+
+    fn enum_value(name: &str) -> Option<Self> {
+        Some(ConstValue::Enum(Name::new(name)))
+    }
+
+    fn binary(bytes: &[u8]) -> Option<Self> {
+        Some(ConstValue::Binary(bytes.to_vec().into()))
     }
 }
@@ -0,0 +1,220 @@
+//! Grouping of [`JsonLike`] values by typed, possibly composite keys, keeping
+//! the order in which keys are first seen.
+use indexmap::IndexMap;
+
+use super::{gather_segment_matches, JsonLike, PathSegment};
+
+/// Key of a group built by [`group_by_keys`]. Unlike the stringified keys of
+/// [`JsonLike::group_by`], `1` and `"1"` are different keys.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GroupKey {
+    Bool(bool),
+    Int(i64),
+    /// Integers above `i64::MAX`.
+    UInt(u64),
+    String(String),
+    /// One key per path, when grouping by several paths.
+    Composite(Vec<GroupKey>),
+}
+
+impl GroupKey {
+    /// Key of a scalar value. Integral floats have the key of the same
+    /// integer, like in [`json_eq`](super::json_eq). Other floats, `null`,
+    /// arrays and objects have no key.
+    pub fn from_value<'json, J: JsonLike<'json>>(value: &'json J) -> Option<Self> {
+        if let Some(s) = value.as_str().or_else(|| value.as_enum()) {
+            Some(GroupKey::String(s.to_string()))
+        } else if let Some(b) = value.as_bool() {
+            Some(GroupKey::Bool(b))
+        } else if let Some(n) = value.as_i64() {
+            Some(GroupKey::Int(n))
+        } else if let Some(n) = value.as_u64() {
+            Some(GroupKey::UInt(n))
+        } else {
+            value.as_f64().and_then(GroupKey::from_f64)
+        }
+    }
+
+    fn from_f64(n: f64) -> Option<Self> {
+        const I64_END: f64 = 9_223_372_036_854_775_808.0;
+        if n.fract() != 0.0 {
+            None
+        } else if (-I64_END..I64_END).contains(&n) {
+            Some(GroupKey::Int(n as i64))
+        } else if (0.0..2.0 * I64_END).contains(&n) {
+            Some(GroupKey::UInt(n as u64))
+        } else {
+            None
+        }
+    }
+}
+
+/// Groups the items of `root` by the values at `paths` in each of them.
+///
+/// Items are `root` itself or, if it is an array, its items, walking through
+/// nested arrays. With a single path the keys are the values found there,
+/// with several paths they are [`GroupKey::Composite`]. An item is added to
+/// one group per combination of keys found, and skipped if a path has no
+/// value with a key. Groups and their items are in the order first seen.
+pub fn group_by_keys<'json, J, P>(root: &'json J, paths: &[P]) -> IndexMap<GroupKey, Vec<&'json J>>
+where
+    J: JsonLike<'json>,
+    P: AsRef<[PathSegment]>,
+{
+    let mut groups = IndexMap::new();
+    add_items(root, paths, &mut groups);
+    groups
+}
+
+fn add_items<'json, J, P>(
+    value: &'json J,
+    paths: &[P],
+    groups: &mut IndexMap<GroupKey, Vec<&'json J>>,
+) where
+    J: JsonLike<'json>,
+    P: AsRef<[PathSegment]>,
+{
+    if let Some(arr) = value.as_array() {
+        for item in arr {
+            add_items(item, paths, groups);
+        }
+        return;
+    }
+
+    let mut keys = vec![vec![]];
+    for path in paths {
+        let found: Vec<_> = gather_segment_matches(value, path.as_ref(), vec![])
+            .into_iter()
+            .filter_map(|(key, _)| GroupKey::from_value(key))
+            .collect();
+        keys = keys
+            .into_iter()
+            .flat_map(|prefix| {
+                found.iter().map(move |key| {
+                    let mut keys = prefix.clone();
+                    keys.push(key.clone());
+                    keys
+                })
+            })
+            .collect();
+    }
+
+    for mut key in keys {
+        let key = match key.len() {
+            0 => continue,
+            1 if paths.len() == 1 => key.remove(0),
+            _ => GroupKey::Composite(key),
+        };
+        let group: &mut Vec<_> = groups.entry(key).or_default();
+        if !group.last().is_some_and(|last| std::ptr::eq(*last, value)) {
+            group.push(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{group_by_keys, GroupKey};
+    use crate::jq::JqTemplate;
+    use crate::jsonlike::{convert, JsonLike, JsonLikeOwned, PathSegment};
+
+    fn ids<'json, J: JsonLike<'json>>(items: &[&'json J]) -> Vec<i64> {
+        items
+            .iter()
+            .filter_map(|item| item.get_key("n")?.as_i64())
+            .collect()
+    }
+
+    fn check_group_by<A: JsonLikeOwned>(
+        input: &Value,
+        paths: &[Vec<PathSegment>],
+    ) -> Vec<(GroupKey, Vec<i64>)> {
+        let input: A = convert(input);
+        group_by_keys(&input, paths)
+            .iter()
+            .map(|(key, items)| (key.clone(), ids(items)))
+            .collect()
+    }
+
+    #[test]
+    fn test_group_by_typed_keys() {
+        let input = json!([
+            {"n": 1, "id": "1"},
+            {"n": 2, "id": 1},
+            {"n": 3, "id": true},
+            {"n": 4, "id": 1},
+            {"n": 5, "id": 1.5},
+            {"n": 6},
+            [{"n": 7, "id": "1"}],
+            {"n": 8, "id": u64::MAX},
+        ]);
+        let paths = [vec!["id".into()]];
+        let expected = vec![
+            (GroupKey::String("1".into()), vec![1, 7]),
+            (GroupKey::Int(1), vec![2, 4]),
+            (GroupKey::Bool(true), vec![3]),
+            (GroupKey::UInt(u64::MAX), vec![8]),
+        ];
+        assert_eq!(check_group_by::<Value>(&input, &paths), expected);
+        assert_eq!(check_group_by::<ConstValue>(&input, &paths), expected);
+        assert_eq!(check_group_by::<Val>(&input, &paths), expected);
+    }
+
+    #[test]
+    fn test_group_by_integral_floats() {
+        let input = json!([{"n": 1, "id": 1}, {"n": 2, "id": 2}, {"n": 3, "id": 2.5}]);
+        let output = JqTemplate::parse("[.[] | {n, id: (.id + 1)}]")
+            .unwrap()
+            .run(input)
+            .remove(0)
+            .unwrap();
+        let paths = [vec!["id".into()]];
+        assert_eq!(
+            check_group_by::<Value>(&output, &paths),
+            vec![(GroupKey::Int(2), vec![1]), (GroupKey::Int(3), vec![2])]
+        );
+        assert_eq!(
+            check_group_by::<Value>(&json!([{"n": 1, "id": -0.0}, {"n": 2, "id": 1e19}]), &paths),
+            vec![
+                (GroupKey::Int(0), vec![1]),
+                (GroupKey::UInt(10_000_000_000_000_000_000), vec![2])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_group_by_composite_keys() {
+        let input = json!([
+            {"n": 1, "user": {"id": 2}, "kind": "a", "tags": ["x", "y"]},
+            {"n": 2, "user": {"id": 1}, "kind": "b", "tags": ["x"]},
+            {"n": 3, "user": {"id": 2}, "kind": "a", "tags": ["x", "x"]},
+            {"n": 4, "kind": "a", "tags": ["x"]},
+        ]);
+        let composite = |id, kind: &str| {
+            GroupKey::Composite(vec![GroupKey::Int(id), GroupKey::String(kind.into())])
+        };
+
+        let paths = [vec!["user".into(), "id".into()], vec!["kind".into()]];
+        assert_eq!(
+            check_group_by::<Value>(&input, &paths),
+            vec![
+                (composite(2, "a"), vec![1, 3]),
+                (composite(1, "b"), vec![2])
+            ]
+        );
+
+        let paths = [vec!["tags".into(), PathSegment::Wildcard]];
+        assert_eq!(
+            check_group_by::<Value>(&input, &paths),
+            vec![
+                (GroupKey::String("x".into()), vec![1, 2, 3, 4]),
+                (GroupKey::String("y".into()), vec![1]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,232 @@
+//! Equality and hashing of [`JsonLike`] values that don't depend on the
+//! backend or on the order of object keys.
+//!
+//! Numbers are compared by value: integers and integral floats are the same
+//! number, so `1`, `1u64` and `1.0` are equal, and so are `0.0` and `-0.0`.
+//! Other floats are compared bit for bit, so `NaN` equals itself. GraphQL
+//! enums are compared as strings. JSON Patch tests, JSONPath filters, merges
+//! and diffs all compare values with [`json_eq`].
+use std::hash::{Hash, Hasher};
+
+use super::{JsonLike, JsonObjectLike};
+
+/// Whether `left` and `right` are the same JSON value.
+pub fn json_eq<'a, 'b, A, B>(left: &'a A, right: &'b B) -> bool
+where
+    A: JsonLike<'a>,
+    B: JsonLike<'b>,
+{
+    match (Scalar::of(left), Scalar::of(right)) {
+        (Some(l), Some(r)) => return l == r,
+        (Some(_), None) | (None, Some(_)) => return false,
+        (None, None) => {}
+    }
+    if let (Some(l), Some(r)) = (left.as_array(), right.as_array()) {
+        return l.len() == r.len() && l.iter().zip(r).all(|(l, r)| json_eq(l, r));
+    }
+    if let (Some(l), Some(r)) = (left.as_object(), right.as_object()) {
+        return l.iter().count() == r.iter().count()
+            && l.iter()
+                .all(|(key, l)| r.get_key(key).is_some_and(|r| json_eq(l, r)));
+    }
+    false
+}
+
+/// Hash of `value`, the same for values that are [`json_eq`] on every run,
+/// platform and backend. It is the 64-bit FNV-1a hash of a canonical encoding
+/// of the value, where object entries are sorted by key.
+pub fn json_hash<'json, J: JsonLike<'json>>(value: &'json J) -> u64 {
+    let mut hasher = Fnv::default();
+    write_value(value, &mut hasher);
+    hasher.0
+}
+
+/// Wrapper of a value to use it as a key in maps and sets, with
+/// [`json_eq`] as equality and [`json_hash`] as hash.
+#[derive(Debug, Clone, Copy)]
+pub struct JsonKey<'json, J>(pub &'json J);
+
+impl<'json, J: JsonLike<'json>> PartialEq for JsonKey<'json, J> {
+    fn eq(&self, other: &Self) -> bool {
+        json_eq(self.0, other.0)
+    }
+}
+
+impl<'json, J: JsonLike<'json>> Eq for JsonKey<'json, J> {}
+
+impl<'json, J: JsonLike<'json>> Hash for JsonKey<'json, J> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(json_hash(self.0));
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Scalar<'a> {
+    Null,
+    Bool(bool),
+    /// Integers, including integral floats.
+    Int(i128),
+    /// Bits of the other floats, with a single `NaN`.
+    Float(u64),
+    String(&'a str),
+    Binary(&'a [u8]),
+}
+
+impl<'a> Scalar<'a> {
+    /// `None` for arrays and objects.
+    fn of<'json: 'a, J: JsonLike<'json>>(value: &'a J) -> Option<Self> {
+        if value.is_null() {
+            Some(Scalar::Null)
+        } else if let Some(b) = value.as_bool() {
+            Some(Scalar::Bool(b))
+        } else if let Some(n) = value.as_i64() {
+            Some(Scalar::Int(n.into()))
+        } else if let Some(n) = value.as_u64() {
+            Some(Scalar::Int(n.into()))
+        } else if let Some(n) = value.as_f64() {
+            Some(if n.is_nan() {
+                Scalar::Float(f64::NAN.to_bits())
+            } else if n.fract() == 0.0 && n.abs() < 2f64.powi(127) {
+                Scalar::Int(n as i128)
+            } else {
+                Scalar::Float(n.to_bits())
+            })
+        } else if let Some(s) = value.as_str().or_else(|| value.as_enum()) {
+            Some(Scalar::String(s))
+        } else {
+            value.as_binary().map(Scalar::Binary)
+        }
+    }
+}
+
+fn write_value<'json, J: JsonLike<'json>>(value: &'json J, out: &mut Fnv) {
+    if let Some(scalar) = Scalar::of(value) {
+        match scalar {
+            Scalar::Null => out.write(&[0]),
+            Scalar::Bool(b) => out.write(&[1, b as u8]),
+            Scalar::Int(n) => {
+                out.write(&[2]);
+                out.write(&n.to_le_bytes());
+            }
+            Scalar::Float(bits) => {
+                out.write(&[3]);
+                out.write(&bits.to_le_bytes());
+            }
+            Scalar::String(s) => write_bytes(4, s.as_bytes(), out),
+            Scalar::Binary(bytes) => write_bytes(5, bytes, out),
+        }
+    } else if let Some(arr) = value.as_array() {
+        out.write(&[6]);
+        out.write(&(arr.len() as u64).to_le_bytes());
+        for item in arr {
+            write_value(item, out);
+        }
+    } else if let Some(obj) = value.as_object() {
+        let mut entries: Vec<_> = obj.iter().collect();
+        entries.sort_unstable_by_key(|(key, _)| *key);
+        out.write(&[7]);
+        out.write(&(entries.len() as u64).to_le_bytes());
+        for (key, value) in entries {
+            write_bytes(4, key.as_bytes(), out);
+            write_value(value, out);
+        }
+    }
+}
+
+fn write_bytes(tag: u8, bytes: &[u8], out: &mut Fnv) {
+    out.write(&[tag]);
+    out.write(&(bytes.len() as u64).to_le_bytes());
+    out.write(bytes);
+}
+
+/// 64-bit FNV-1a, which unlike the standard hasher is fixed.
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Fnv(0xcbf29ce484222325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use async_graphql_value::{ConstValue, Name};
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{json_eq, json_hash, JsonKey};
+    use crate::jsonlike::convert;
+
+    #[test]
+    fn test_json_eq() {
+        let cases = [
+            (json!(1), json!(1.0), true),
+            (json!(0.0), json!(-0.0), true),
+            (json!(u64::MAX), json!(u64::MAX as f64), false),
+            (json!(1), json!("1"), false),
+            (json!(null), json!(false), false),
+            (
+                json!({"a": 1, "b": [1, 2]}),
+                json!({"b": [1.0, 2], "a": 1}),
+                true,
+            ),
+            (json!({"a": 1}), json!({"a": 1, "b": null}), false),
+            (json!([1, 2]), json!([2, 1]), false),
+            (json!([]), json!({}), false),
+        ];
+        for (left, right, expected) in cases {
+            assert_eq!(json_eq(&left, &right), expected, "{} {}", left, right);
+            let graphql: ConstValue = convert(&right);
+            assert_eq!(json_eq(&left, &graphql), expected, "{} {}", left, right);
+            let val: Val = convert(&right);
+            assert_eq!(json_eq(&left, &val), expected, "{} {}", left, right);
+            if expected {
+                assert_eq!(json_hash(&left), json_hash(&right));
+                assert_eq!(json_hash(&left), json_hash(&graphql));
+                assert_eq!(json_hash(&left), json_hash(&val));
+            }
+        }
+
+        let color = ConstValue::Enum(Name::new("RED"));
+        assert!(json_eq(&color, &json!("RED")));
+        assert_eq!(json_hash(&color), json_hash(&json!("RED")));
+    }
+
+    #[test]
+    fn test_json_hash_is_stable() {
+        assert_eq!(json_hash(&json!(null)), 0xaf63bd4c8601b7df);
+        assert_eq!(
+            json_hash(&json!({"b": [1, "x"], "a": {"c": true}})),
+            json_hash(&json!({"a": {"c": true}, "b": [1.0, "x"]}))
+        );
+        assert_ne!(json_hash(&json!([1, 2])), json_hash(&json!([2, 1])));
+        assert_ne!(json_hash(&json!(["ab"])), json_hash(&json!(["a", "b"])));
+    }
+
+    #[test]
+    fn test_json_key() {
+        let values = [
+            json!({"id": 1, "x": 2}),
+            json!({"x": 2, "id": 1.0}),
+            json!({"id": 2}),
+        ];
+        let mut counts: HashMap<JsonKey<'_, Value>, usize> = HashMap::new();
+        for value in &values {
+            *counts.entry(JsonKey(value)).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts[&JsonKey(&values[1])], 2);
+    }
+}
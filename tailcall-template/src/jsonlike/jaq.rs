@@ -0,0 +1,361 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::rc::Rc;
+
+use indexmap::{Equivalent, IndexMap};
+use jaq_json::Val;
+
+use super::{gather_path_matches, group_by_key, JsonLike, JsonObjectLike};
+use crate::mustache::path::PathString;
+
+type Map = IndexMap<Rc<String>, Val, foldhash::fast::RandomState>;
+
+/// Looks up `Rc<String>` keys by `&str` without allocating a `String`.
+/// Hashes like `String`, which hashes like `str`.
+struct Key<'a>(&'a str);
+
+impl Hash for Key<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl Equivalent<Rc<String>> for Key<'_> {
+    fn equivalent(&self, key: &Rc<String>) -> bool {
+        self.0 == key.as_str()
+    }
+}
+
+impl<'obj> JsonObjectLike<'obj> for Map {
+    type Value = Val;
+
+    fn new() -> Self {
+        Map::default()
+    }
+
+    fn with_capacity(n: usize) -> Self {
+        Map::with_capacity_and_hasher(n, Default::default())
+    }
+
+    fn get_key(&self, key: &str) -> Option<&Self::Value> {
+        self.get(&Key(key))
+    }
+
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self::Value> {
+        self.get_mut(&Key(key))
+    }
+
+    fn insert_key(&mut self, key: &'obj str, value: Self::Value) {
+        match self.get_mut(&Key(key)) {
+            Some(slot) => *slot = value,
+            None => {
+                self.insert(Rc::new(key.to_string()), value);
+            }
+        }
+    }
+
+    fn insert_key_cow(&mut self, key: Cow<'obj, str>, value: Self::Value) {
+        match self.get_mut(&Key(&key)) {
+            Some(slot) => *slot = value,
+            None => {
+                self.insert(Rc::new(key.into_owned()), value);
+            }
+        }
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self::Value> {
+        self.shift_remove(&Key(key))
+    }
+
+    fn iter(&'obj self) -> impl Iterator<Item = (&'obj str, &'obj Self::Value)> {
+        self.iter().map(|(k, v)| (k.as_str(), v))
+    }
+}
+
+// Arrays and objects are shared through `Rc`, and mutable access clones them
+// only if they are shared.
+impl<'json> JsonLike<'json> for Val {
+    type JsonObject = Map;
+
+    fn null() -> Self {
+        Val::Null
+    }
+
+    fn object(obj: Self::JsonObject) -> Self {
+        Val::Obj(Rc::new(obj))
+    }
+
+    fn obj(pairs: Vec<(&'json str, Self)>) -> Self {
+        let mut map = Map::with_capacity_and_hasher(pairs.len(), Default::default());
+        for (k, v) in pairs {
+            map.insert_key(k, v);
+        }
+        Val::Obj(Rc::new(map))
+    }
+
+    fn obj_cow(pairs: Vec<(Cow<'json, str>, Self)>) -> Self {
+        let mut map = Map::with_capacity_and_hasher(pairs.len(), Default::default());
+        for (k, v) in pairs {
+            map.insert(Rc::new(k.into_owned()), v);
+        }
+        Val::Obj(Rc::new(map))
+    }
+
+    fn array(arr: Vec<Self>) -> Self {
+        Val::Arr(Rc::new(arr))
+    }
+
+    fn string(s: Cow<'json, str>) -> Self {
+        Val::Str(Rc::new(s.into_owned()))
+    }
+
+    fn boolean(b: bool) -> Self {
+        Val::Bool(b)
+    }
+
+    fn number_i64(n: i64) -> Self {
+        match isize::try_from(n) {
+            Ok(n) => Val::Int(n),
+            Err(_) => Val::Num(Rc::new(n.to_string())),
+        }
+    }
+
+    fn number_u64(n: u64) -> Self {
+        match isize::try_from(n) {
+            Ok(n) => Val::Int(n),
+            Err(_) => Val::Num(Rc::new(n.to_string())),
+        }
+    }
+
+    /// Integral numbers become `Val::Int`, like numbers parsed by jaq.
+    fn number_f64(n: f64) -> Self {
+        if n.fract() == 0.0 && n >= isize::MIN as f64 && n < isize::MAX as f64 {
+            Val::Int(n as isize)
+        } else {
+            Val::Float(n)
+        }
+    }
+
+    fn as_array(&self) -> Option<&Vec<Self>> {
+        match self {
+            Val::Arr(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    fn as_array_mut(&mut self) -> Option<&mut Vec<Self>> {
+        match self {
+            Val::Arr(arr) => Some(Rc::make_mut(arr)),
+            _ => None,
+        }
+    }
+
+    fn into_array(self) -> Option<Vec<Self>> {
+        match self {
+            Val::Arr(arr) => Some(Rc::unwrap_or_clone(arr)),
+            _ => None,
+        }
+    }
+
+    fn as_object(&self) -> Option<&Self::JsonObject> {
+        match self {
+            Val::Obj(obj) => Some(obj),
+            _ => None,
+        }
+    }
+
+    fn as_object_mut(&mut self) -> Option<&mut Self::JsonObject> {
+        match self {
+            Val::Obj(obj) => Some(Rc::make_mut(obj)),
+            _ => None,
+        }
+    }
+
+    fn into_object(self) -> Option<Self::JsonObject> {
+        match self {
+            Val::Obj(obj) => Some(Rc::unwrap_or_clone(obj)),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Val::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Val::Int(n) => Some(*n as i64),
+            Val::Num(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Val::Int(n) => u64::try_from(*n).ok(),
+            Val::Num(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_f64(&self) -> Option<f64> {
+        match self {
+            Val::Int(n) => Some(*n as f64),
+            Val::Float(n) => Some(*n),
+            Val::Num(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_bool(&self) -> Option<bool> {
+        match self {
+            Val::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    fn is_null(&self) -> bool {
+        matches!(self, Val::Null)
+    }
+
+    fn get_path<T: AsRef<str>>(&'json self, path: &[T]) -> Option<&'json Self> {
+        let mut val = self;
+        for token in path {
+            val = match val {
+                Val::Arr(arr) => {
+                    let index = token.as_ref().parse::<usize>().ok()?;
+                    arr.get(index)?
+                }
+                Val::Obj(map) => map.get_key(token.as_ref())?,
+                _ => return None,
+            };
+        }
+        Some(val)
+    }
+
+    fn get_key(&'json self, path: &str) -> Option<&'json Self> {
+        match self {
+            Val::Obj(map) => map.get_key(path),
+            _ => None,
+        }
+    }
+
+    fn group_by(&'json self, path: &[String]) -> HashMap<String, Vec<&'json Self>> {
+        let src = gather_path_matches(self, path, vec![]);
+        group_by_key(src)
+    }
+}
+
+impl PathString for Val {
+    fn path_string<'a, T: AsRef<str>>(&'a self, path: &'a [T]) -> Option<Cow<'a, str>> {
+        self.get_path(path).map(move |a| match a {
+            Val::Str(s) => Cow::Borrowed(s.as_str()),
+            _ => Cow::Owned(a.to_string()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use crate::jq::JqTemplate;
+    use crate::jsonlike::{gather_path_matches, JsonLike, JsonObjectLike};
+    use crate::mustache::Mustache;
+
+    fn val(value: serde_json::Value) -> Val {
+        Val::from(value)
+    }
+
+    #[test]
+    fn test_accessors() {
+        let value = val(json!({"a": [1, 2.5, "x", true, null], "b": {"c": 3}}));
+        let arr = value.get_key("a").and_then(|a| a.as_array()).unwrap();
+        assert_eq!(arr[0].as_i64(), Some(1));
+        assert_eq!(arr[1].as_f64(), Some(2.5));
+        assert_eq!(arr[2].as_str(), Some("x"));
+        assert_eq!(arr[3].as_bool(), Some(true));
+        assert!(arr[4].is_null());
+        assert_eq!(value.get_path(&["b", "c"]), Some(&Val::Int(3)));
+        assert_eq!(value.get_path(&["a", "2"]), Some(&val(json!("x"))));
+        assert_eq!(value.get_path(&["a", "x"]), None);
+    }
+
+    #[test]
+    fn test_mutation_does_not_affect_clones() {
+        let original = val(json!({"a": {"b": 1}, "c": [1]}));
+        let mut value = original.clone();
+        value.as_object_mut().unwrap().insert_key("d", Val::Int(2));
+        value
+            .as_object_mut()
+            .unwrap()
+            .get_key_mut("c")
+            .and_then(|c| c.as_array_mut())
+            .unwrap()
+            .push(Val::Int(2));
+        assert_eq!(
+            value.as_object_mut().unwrap().remove_key("a"),
+            Some(val(json!({"b": 1})))
+        );
+
+        assert_eq!(serde_json::Value::from(value), json!({"c": [1, 2], "d": 2}));
+        assert_eq!(
+            serde_json::Value::from(original),
+            json!({"a": {"b": 1}, "c": [1]})
+        );
+    }
+
+    #[test]
+    fn test_constructors() {
+        let value = <Val as JsonLike>::obj(vec![
+            ("n", Val::number_f64(1.0)),
+            ("f", Val::number_f64(1.5)),
+            ("s", Val::string("x".into())),
+            ("a", Val::array(vec![Val::boolean(false), Val::null()])),
+        ]);
+        assert_eq!(
+            value.to_string(),
+            r#"{"n":1,"f":1.5,"s":"x","a":[false,null]}"#
+        );
+        let keys: Vec<_> = JsonObjectLike::iter(value.as_object().unwrap())
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(keys, vec!["n", "f", "s", "a"]);
+        assert_eq!(Val::Str(Rc::new("x".to_string())).into_array(), None);
+    }
+
+    #[test]
+    fn test_render_and_group_by() {
+        let value = val(json!({"data": [{"id": 1, "name": "a"}, {"id": 2}, {"id": 1}]}));
+        let mustache = Mustache::parse("/users/{{data}}");
+        assert_eq!(
+            mustache.render(&value),
+            r#"/users/[{"id":1,"name":"a"},{"id":2},{"id":1}]"#
+        );
+
+        let path = ["data".to_string(), "id".to_string()];
+        assert_eq!(gather_path_matches(&value, &path, vec![]).len(), 3);
+        let groups = value.group_by(&path);
+        assert_eq!(groups["1"].len(), 2);
+        assert_eq!(groups["2"], vec![&val(json!({"id": 2}))]);
+    }
+
+    #[test]
+    fn test_jq_outputs_render() {
+        let template = JqTemplate::<Val>::parse("{user: {id: (.id + 1), tags: .tags}}").unwrap();
+        let output = template
+            .run(val(json!({"id": 1, "tags": ["a"]})))
+            .remove(0)
+            .unwrap();
+        let mustache = Mustache::parse("/users/{{user.id}}?tags={{user.tags}}");
+        assert_eq!(mustache.render(&output), r#"/users/2?tags=["a"]"#);
+    }
+}
@@ -0,0 +1,203 @@
+//! Stitching of batched children back onto their parents, the last step of
+//! resolving N+1 queries in one call.
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+
+use super::{
+    gather_segment_matches, group_by_keys, GroupKey, JsonLikeOwned, JsonObjectLike, PathSegment,
+};
+
+/// How many children [`Join`] sets on each parent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Cardinality {
+    /// The first matching child, or `null`.
+    One,
+    /// An array of all matching children, possibly empty.
+    #[default]
+    Many,
+}
+
+/// Sets on each parent object the children whose key, at `child_key`, is the
+/// same as one of the parent's keys at `parent_key`, in the order of the
+/// parent's keys. Keys are compared as [`GroupKey`]s, so `1` and `"1"` don't
+/// match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    parent_key: Vec<PathSegment>,
+    child_key: Vec<PathSegment>,
+    field: String,
+    cardinality: Cardinality,
+}
+
+impl Join {
+    pub fn new(
+        parent_key: Vec<PathSegment>,
+        child_key: Vec<PathSegment>,
+        field: impl Into<String>,
+    ) -> Self {
+        Self {
+            parent_key,
+            child_key,
+            field: field.into(),
+            cardinality: Cardinality::default(),
+        }
+    }
+
+    pub fn cardinality(self, cardinality: Cardinality) -> Self {
+        Self {
+            cardinality,
+            ..self
+        }
+    }
+
+    /// Returns `parents` with `field` set on each of them. Parents are
+    /// `parents` itself or the items of arrays in it, as in
+    /// [`group_by_keys`]. Children are found the same way in `children`.
+    pub fn join<A: JsonLikeOwned + Clone>(&self, mut parents: A, children: &A) -> A {
+        let groups = group_by_keys(children, &[&self.child_key]);
+        self.stitch(&mut parents, &groups);
+        parents
+    }
+
+    fn stitch<A: JsonLikeOwned + Clone>(
+        &self,
+        parent: &mut A,
+        children: &IndexMap<GroupKey, Vec<&A>>,
+    ) {
+        if let Some(arr) = parent.as_array_mut() {
+            for item in arr {
+                self.stitch(item, children);
+            }
+            return;
+        }
+        if parent.as_object().is_none() {
+            return;
+        }
+        // the parent key may match several values, like the ids at `ids.*`,
+        // and a child that matches more than one of them is set once
+        let (mut matched, mut seen): (Vec<&A>, HashSet<*const A>) = Default::default();
+        for (key, _) in gather_segment_matches(&*parent, &self.parent_key, vec![]) {
+            let group = GroupKey::from_value(key).and_then(|key| children.get(&key));
+            for &child in group.into_iter().flatten() {
+                if seen.insert(child) {
+                    matched.push(child);
+                }
+            }
+        }
+        let value = match self.cardinality {
+            Cardinality::One => matched.first().map_or_else(A::null, |&child| child.clone()),
+            Cardinality::Many => A::array(matched.into_iter().cloned().collect()),
+        };
+        if let Some(obj) = parent.as_object_mut() {
+            obj.insert_key_cow(Cow::Owned(self.field.clone()), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{Cardinality, Join};
+    use crate::jsonlike::{convert, JsonLikeOwned, PathSegment};
+
+    fn check_join<A: JsonLikeOwned + Clone>(
+        join: &Join,
+        parents: &Value,
+        children: &Value,
+    ) -> Value {
+        let parents: A = convert(parents);
+        let children: A = convert(children);
+        convert(&join.join(parents, &children))
+    }
+
+    #[test]
+    fn test_join_many() {
+        let join = Join::new(vec!["id".into()], vec!["user".into(), "id".into()], "posts");
+        let parents = json!([{"id": 1}, {"id": 2}, {"id": "1"}, {"name": "anon"}]);
+        let children = json!([
+            {"title": "a", "user": {"id": 1}},
+            {"title": "b", "user": {"id": 3}},
+            [{"title": "c", "user": {"id": 1}}],
+        ]);
+        let expected = json!([
+            {"id": 1, "posts": [{"title": "a", "user": {"id": 1}}, {"title": "c", "user": {"id": 1}}]},
+            {"id": 2, "posts": []},
+            {"id": "1", "posts": []},
+            {"name": "anon", "posts": []},
+        ]);
+        assert_eq!(check_join::<Value>(&join, &parents, &children), expected);
+        assert_eq!(
+            check_join::<ConstValue>(&join, &parents, &children),
+            expected
+        );
+        assert_eq!(check_join::<Val>(&join, &parents, &children), expected);
+    }
+
+    #[test]
+    fn test_join_many_keys() {
+        let join = Join::new(
+            vec!["authorIds".into(), PathSegment::Wildcard],
+            vec!["id".into()],
+            "authors",
+        );
+        let parents = json!([{"authorIds": [2, 1, 2]}, {"authorIds": [3]}, {"authorIds": []}]);
+        let children = json!([{"id": 1, "name": "ann"}, {"id": 2, "name": "bob"}]);
+        let expected = json!([
+            {"authorIds": [2, 1, 2], "authors": [{"id": 2, "name": "bob"}, {"id": 1, "name": "ann"}]},
+            {"authorIds": [3], "authors": []},
+            {"authorIds": [], "authors": []},
+        ]);
+        assert_eq!(check_join::<Value>(&join, &parents, &children), expected);
+        assert_eq!(
+            check_join::<ConstValue>(&join, &parents, &children),
+            expected
+        );
+        assert_eq!(check_join::<Val>(&join, &parents, &children), expected);
+
+        let join = join.cardinality(Cardinality::One);
+        let expected = json!([
+            {"authorIds": [2, 1, 2], "authors": {"id": 2, "name": "bob"}},
+            {"authorIds": [3], "authors": null},
+            {"authorIds": [], "authors": null},
+        ]);
+        assert_eq!(check_join::<Value>(&join, &parents, &children), expected);
+    }
+
+    #[test]
+    fn test_join_one() {
+        let join = Join::new(vec!["userId".into()], vec!["id".into()], "user")
+            .cardinality(Cardinality::One);
+        let parents = json!({"posts": [{"userId": 2}, {"userId": 1}, {"userId": 3}]});
+        let children =
+            json!([{"id": 1, "name": "ann"}, {"id": 2, "name": "bob"}, {"id": 2, "name": "eve"}]);
+
+        // only the root object is a parent
+        let expected =
+            json!({"posts": [{"userId": 2}, {"userId": 1}, {"userId": 3}], "user": null});
+        assert_eq!(check_join::<Value>(&join, &parents, &children), expected);
+
+        let expected = json!([
+            {"userId": 2, "user": {"id": 2, "name": "bob"}},
+            {"userId": 1, "user": {"id": 1, "name": "ann"}},
+            {"userId": 3, "user": null},
+        ]);
+        assert_eq!(
+            check_join::<Value>(&join, &parents["posts"], &children),
+            expected
+        );
+        assert_eq!(
+            check_join::<ConstValue>(&join, &parents["posts"], &children),
+            expected
+        );
+        assert_eq!(
+            check_join::<Val>(&join, &parents["posts"], &children),
+            expected
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 
+use super::pointer::{array_index, child_mut};
+use super::PathSegment;
+
 pub trait JsonLikeOwned: for<'json> JsonLike<'json> {}
 impl<T> JsonLikeOwned for T where T: for<'json> JsonLike<'json> {}
 
@@ -12,12 +15,26 @@ pub trait JsonLike<'json>: Sized {
     fn null() -> Self;
     fn object(obj: Self::JsonObject) -> Self;
     fn obj(pairs: Vec<(&'json str, Self)>) -> Self;
+    /// Like [`obj`](Self::obj), but keys may also be owned.
+    fn obj_cow(pairs: Vec<(Cow<'json, str>, Self)>) -> Self;
 
     fn array(arr: Vec<Self>) -> Self;
     fn string(s: Cow<'json, str>) -> Self;
-    // TODO: try from number
+    fn boolean(b: bool) -> Self;
+    fn number_i64(n: i64) -> Self;
+    fn number_u64(n: u64) -> Self;
+    /// Backends that can't represent NaN and infinities return null for them.
     fn number_f64(n: f64) -> Self;
 
+    /// GraphQL enum value, for backends that have them.
+    fn enum_value(_name: &str) -> Option<Self> {
+        None
+    }
+    /// Binary value, for backends that have them.
+    fn binary(_bytes: &[u8]) -> Option<Self> {
+        None
+    }
+
     // Operators
     fn as_array(&self) -> Option<&Vec<Self>>;
     fn as_array_mut(&mut self) -> Option<&mut Vec<Self>>;
@@ -31,9 +48,117 @@ pub trait JsonLike<'json>: Sized {
     fn as_f64(&self) -> Option<f64>;
     fn as_bool(&self) -> Option<bool>;
     fn is_null(&self) -> bool;
+    fn as_enum(&self) -> Option<&str> {
+        None
+    }
+    fn as_binary(&self) -> Option<&[u8]> {
+        None
+    }
     fn get_path<T: AsRef<str>>(&'json self, path: &[T]) -> Option<&'json Self>;
     fn get_key(&'json self, path: &str) -> Option<&'json Self>;
     fn group_by(&'json self, path: &[String]) -> HashMap<String, Vec<&'json Self>>;
+
+    /// Like [`group_by`](Self::group_by), with index, wildcard and optional
+    /// segments in the path.
+    fn group_by_segments(&'json self, path: &[PathSegment]) -> HashMap<String, Vec<&'json Self>> {
+        super::group_by_key(super::gather_segment_matches(self, path, vec![]))
+    }
+
+    fn get_path_mut<'a, T: AsRef<str>>(&'a mut self, path: &[T]) -> Option<&'a mut Self>
+    where
+        'json: 'a,
+    {
+        path.iter()
+            .try_fold(self, |value, token| child_mut(value, token.as_ref()))
+    }
+
+    /// Sets the value at `path`. Missing and null values on the way become
+    /// arrays if the next token is an index, and objects otherwise, and arrays
+    /// are padded with nulls up to the index. If a value on the way can't
+    /// hold the next token, nothing changes and `value` is returned back.
+    fn set_path<T: AsRef<str>>(&mut self, path: &[T], value: Self) -> Result<(), Self> {
+        // checked up front, as `slot` creates the values on the way
+        if !settable(self, path) {
+            return Err(value);
+        }
+        match path
+            .iter()
+            .try_fold(self, |current, token| slot(current, token.as_ref()))
+        {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Removes the value at `path` and returns it. Later array items move
+    /// down by one.
+    fn remove_path<T: AsRef<str>>(&mut self, path: &[T]) -> Option<Self> {
+        let (last, parents) = path.split_last()?;
+        let parent = self.get_path_mut(parents)?;
+        if let Some(arr) = parent.as_array_mut() {
+            let index = array_index(last.as_ref()).filter(|index| *index < arr.len())?;
+            return Some(arr.remove(index));
+        }
+        parent.as_object_mut()?.remove_key(last.as_ref())
+    }
+}
+
+/// Largest index [`JsonLike::set_path`] pads an array up to, the same as jq's.
+const MAX_INDEX: usize = 536_870_911;
+
+/// Whether [`JsonLike::set_path`] can set `path` in `value`: every array on
+/// the way, existing or not, is indexed by a token up to [`MAX_INDEX`], and
+/// every other value on the way is an object or null.
+fn settable<'json, J: JsonLike<'json>, T: AsRef<str>>(value: &J, path: &[T]) -> bool {
+    let mut current = Some(value);
+    for token in path {
+        let token = token.as_ref();
+        let index = array_index(token);
+        let (arr, obj) = match current {
+            Some(value) => (value.as_array(), value.as_object()),
+            None => (None, None),
+        };
+        current = match (arr, obj) {
+            (Some(arr), _) => match index {
+                Some(index) if index <= MAX_INDEX => arr.get(index),
+                _ => return false,
+            },
+            (_, Some(obj)) => obj.get_key(token),
+            _ if current.is_some_and(|value| !value.is_null()) => return false,
+            // missing and null values become arrays or objects
+            _ if index.is_some_and(|index| index > MAX_INDEX) => return false,
+            _ => None,
+        };
+    }
+    true
+}
+
+/// Like [`child_mut`], but creates the child and its container as needed.
+fn slot<'a, 'json: 'a, J: JsonLike<'json>>(value: &'a mut J, token: &str) -> Option<&'a mut J> {
+    let index = array_index(token);
+    if value.is_null() {
+        *value = match index {
+            Some(index) if index > MAX_INDEX => return None,
+            Some(_) => J::array(Vec::new()),
+            None => J::object(J::JsonObject::new()),
+        };
+    }
+    if value.as_array().is_some() {
+        let arr = value.as_array_mut()?;
+        let index = index.filter(|index| *index <= MAX_INDEX)?;
+        if index >= arr.len() {
+            arr.resize_with(index + 1, J::null);
+        }
+        return arr.get_mut(index);
+    }
+    let obj = value.as_object_mut()?;
+    if obj.get_key(token).is_none() {
+        obj.insert_key_cow(Cow::Owned(token.to_string()), J::null());
+    }
+    obj.get_key_mut(token)
 }
 
 /// A trait for objects that can be used as JSON objects
@@ -42,9 +167,71 @@ pub trait JsonObjectLike<'obj>: Sized {
     fn new() -> Self;
     fn with_capacity(n: usize) -> Self;
     fn get_key(&self, key: &str) -> Option<&Self::Value>;
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut Self::Value>;
     fn insert_key(&mut self, key: &'obj str, value: Self::Value);
-    fn remove_key(&mut self, key: &'obj str) -> Option<Self::Value>;
+    /// Like [`insert_key`](Self::insert_key), but the key may also be owned.
+    fn insert_key_cow(&mut self, key: Cow<'obj, str>, value: Self::Value);
+    fn remove_key(&mut self, key: &str) -> Option<Self::Value>;
     fn iter(&'obj self) -> impl Iterator<Item = (&'obj str, &'obj Self::Value)>;
+
+    /// Entry for `key`, to insert or update its value in place.
+    fn entry(&mut self, key: Cow<'obj, str>) -> Entry<'_, 'obj, Self> {
+        if self.get_key(&key).is_none() {
+            return Entry::Vacant(VacantEntry { obj: self, key });
+        }
+        match self.get_key_mut(&key) {
+            Some(value) => Entry::Occupied(value),
+            None => unreachable!(),
+        }
+    }
+}
+
+/// Returned by [`JsonObjectLike::entry`].
+pub enum Entry<'a, 'obj, O: JsonObjectLike<'obj>> {
+    Occupied(&'a mut O::Value),
+    Vacant(VacantEntry<'a, 'obj, O>),
+}
+
+pub struct VacantEntry<'a, 'obj, O> {
+    obj: &'a mut O,
+    key: Cow<'obj, str>,
+}
+
+impl<'a, 'obj, O: JsonObjectLike<'obj>> Entry<'a, 'obj, O> {
+    pub fn or_insert(self, value: O::Value) -> &'a mut O::Value {
+        self.or_insert_with(|| value)
+    }
+
+    pub fn or_insert_with(self, f: impl FnOnce() -> O::Value) -> &'a mut O::Value {
+        match self {
+            Entry::Occupied(value) => value,
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    pub fn and_modify(self, f: impl FnOnce(&mut O::Value)) -> Self {
+        match self {
+            Entry::Occupied(value) => {
+                f(value);
+                Entry::Occupied(value)
+            }
+            entry => entry,
+        }
+    }
+}
+
+impl<'a, 'obj, O: JsonObjectLike<'obj>> VacantEntry<'a, 'obj, O> {
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn insert(self, value: O::Value) -> &'a mut O::Value {
+        self.obj.insert_key_cow(self.key.clone(), value);
+        match self.obj.get_key_mut(&self.key) {
+            Some(value) => value,
+            None => unreachable!(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -53,7 +240,7 @@ mod tests {
     use pretty_assertions::assert_eq;
     use serde_json::json;
 
-    use super::super::gather_path_matches;
+    use super::super::{gather_path_matches, gather_segment_matches, PathSegment};
     use super::{JsonLike, JsonObjectLike};
 
     // for lifetime testing purposes
@@ -154,7 +341,7 @@ mod tests {
 
     #[test]
     fn test_group_by_key() {
-        let arr = vec![
+        let arr = [
             (json!("1"), json!({"id": "1"})),
             (json!("2"), json!({"id": "2"})),
             (json!("2"), json!({"id": "2"})),
@@ -178,7 +365,7 @@ mod tests {
 
     #[test]
     fn test_group_by_numeric_key() {
-        let arr = vec![
+        let arr = [
             (json!(1), json!({"id": 1})),
             (json!(2), json!({"id": 2})),
             (json!(2), json!({"id": 2})),
@@ -199,4 +386,225 @@ mod tests {
 
         assert_eq!(actual, expected)
     }
+
+    #[test]
+    fn test_gather_segment_matches() {
+        let input = json!({
+            "items": [{"id": 1, "tags": ["a", "b"]}, {"id": 2, "tags": [["c"], ["d", "e"]]}],
+            "wrapped": [{"node": {"id": 3}}, {"id": 4}],
+        });
+        let values = |path: &[PathSegment]| {
+            gather_segment_matches(&input, path, vec![])
+                .into_iter()
+                .map(|(value, _)| value.clone())
+                .collect::<Vec<_>>()
+        };
+        let index = |index| PathSegment::Index(index);
+        let optional = |key: &str| PathSegment::Optional(Box::new(key.into()));
+
+        assert_eq!(values(&["items".into(), "id".into()]), [json!(1), json!(2)]);
+        assert_eq!(values(&["items".into(), index(0), "id".into()]), [json!(1)]);
+        assert_eq!(
+            values(&["items".into(), index(-1), "id".into()]),
+            [json!(2)]
+        );
+        assert_eq!(
+            values(&["items".into(), index(2), "id".into()]),
+            Vec::<serde_json::Value>::new()
+        );
+        assert_eq!(
+            values(&[
+                "items".into(),
+                index(0),
+                "tags".into(),
+                PathSegment::Wildcard
+            ]),
+            [json!("a"), json!("b")]
+        );
+        assert_eq!(
+            values(&[
+                "items".into(),
+                "tags".into(),
+                PathSegment::Wildcard,
+                PathSegment::Wildcard
+            ]),
+            [json!("c"), json!("d"), json!("e")]
+        );
+        assert_eq!(
+            values(&["items".into(), index(1), PathSegment::Wildcard]),
+            [json!(2), json!([["c"], ["d", "e"]])]
+        );
+        assert_eq!(
+            values(&["wrapped".into(), optional("node"), "id".into()]),
+            [json!(3), json!(4)]
+        );
+
+        let plain = ["items".to_string(), "id".to_string()];
+        let segments = plain.clone().map(PathSegment::from);
+        assert_eq!(
+            gather_segment_matches(&input, &segments, vec![]),
+            gather_path_matches(&input, &plain, vec![])
+        );
+    }
+
+    #[test]
+    fn test_group_by_segments() {
+        let input = json!([
+            {"id": 1, "tags": ["a", "b"]},
+            {"id": 2, "tags": ["b"]},
+        ]);
+        let actual = input.group_by_segments(&["tags".into(), PathSegment::Wildcard]);
+        let actual = serde_json::to_value(actual).unwrap();
+        assert_eq!(actual, json!({"a": [["a", "b"]], "b": [["a", "b"], ["b"]]}));
+    }
+
+    #[test]
+    fn test_remove_key() {
+        let input = r#"{"a": 1, "b\u0021": [2], "c": {"d": 3}}"#;
+
+        let mut value: serde_json_borrow::Value = serde_json::from_str(input).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        assert_eq!(
+            obj.remove_key("b!"),
+            Some(serde_json_borrow::Value::from(vec![2u64]))
+        );
+        assert_eq!(obj.remove_key("x"), None);
+        obj.insert_key("e", serde_json_borrow::Value::Null);
+        let actual = serde_json::Value::from(value);
+        assert_eq!(actual, json!({"a": 1, "c": {"d": 3}, "e": null}));
+
+        let mut value: serde_json::Value = serde_json::from_str(input).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        assert_eq!(JsonObjectLike::remove_key(obj, "b!"), Some(json!([2])));
+        assert_eq!(value, json!({"a": 1, "c": {"d": 3}}));
+
+        let mut value =
+            async_graphql_value::ConstValue::from_json(json!({"a": 1, "b": 2})).unwrap();
+        let obj = JsonLike::as_object_mut(&mut value).unwrap();
+        assert!(obj.remove_key("a").is_some());
+        assert_eq!(value.into_json().unwrap(), json!({"b": 2}));
+    }
+
+    fn check_paths<A>(mut value: A)
+    where
+        A: for<'a> JsonLike<'a> + PartialEq + std::fmt::Debug,
+    {
+        let json = |v: serde_json::Value| crate::jsonlike::convert::<_, A>(&v);
+        *value.get_path_mut(&["user", "tags", "1"]).unwrap() = json(json!("z"));
+        assert_eq!(value.get_path_mut(&["user", "tags", "2"]), None);
+        assert_eq!(value.get_path_mut(&["user", "name", "x"]), None);
+
+        assert_eq!(
+            value.set_path(&["user", "name"], json(json!("bob"))),
+            Ok(())
+        );
+        assert_eq!(
+            value.set_path(&["meta", "page", "size"], json(json!(10))),
+            Ok(())
+        );
+        assert_eq!(value.set_path(&["list", "2", "id"], json(json!(1))), Ok(()));
+        assert_eq!(value.set_path(&["nothing", "0"], json(json!(true))), Ok(()));
+        assert_eq!(
+            value.set_path(&["user", "name", "first"], json(json!(1))),
+            Err(json(json!(1)))
+        );
+        assert_eq!(
+            value.set_path(&["user", "tags", "x"], json(json!(1))),
+            Err(json(json!(1)))
+        );
+        for index in ["+1", "01", "99999999999999999"] {
+            assert_eq!(value.get_path_mut(&["user", "tags", index]), None);
+            assert_eq!(
+                value.set_path(&["user", "tags", index], json(json!(1))),
+                Err(json(json!(1)))
+            );
+            assert_eq!(value.remove_path(&["user", "tags", index]), None);
+        }
+        // failed paths leave no created or padded values behind, see `expected`
+        for path in [
+            ["list", "0", "99999999999999999"],
+            ["list", "5", "99999999999"],
+            ["missing", "0", "99999999999"],
+            ["missing", "a", "99999999999"],
+        ] {
+            assert_eq!(value.set_path(&path, json(json!(1))), Err(json(json!(1))));
+        }
+
+        assert_eq!(
+            value.remove_path(&["user", "tags", "0"]),
+            Some(json(json!("x")))
+        );
+        assert_eq!(value.remove_path(&["user", "tags", "5"]), None);
+        assert_eq!(value.remove_path(&["user", "age"]), None);
+        assert_eq!(value.remove_path::<&str>(&[]), None);
+
+        let expected = json!({
+            "user": {"name": "bob", "tags": ["z"]},
+            "nothing": [true],
+            "meta": {"page": {"size": 10}},
+            "list": [null, null, {"id": 1}]
+        });
+        assert_eq!(
+            crate::jsonlike::convert::<_, serde_json::Value>(&value),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_paths() {
+        let value = json!({"user": {"name": "ann", "tags": ["x", "y"]}, "nothing": null});
+        check_paths(value.clone());
+        check_paths(crate::jsonlike::convert::<_, async_graphql_value::ConstValue>(&value));
+        check_paths(crate::jsonlike::convert::<_, jaq_json::Val>(&value));
+
+        let text = value.to_string();
+        let mut value: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        let name = serde_json_borrow::Value::Str("bob".into());
+        assert_eq!(value.set_path(&["user", "name"], name), Ok(()));
+        assert_eq!(value.set_path(&["user", "id"], 1u64.into()), Ok(()));
+        assert!(value.remove_path(&["user", "tags"]).is_some());
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!({"user": {"name": "bob", "id": 1}, "nothing": null})
+        );
+    }
+
+    #[test]
+    fn test_entry() {
+        use std::borrow::Cow;
+
+        let mut value = json!({"count": 1});
+        let obj = value.as_object_mut().unwrap();
+        JsonObjectLike::entry(obj, Cow::Borrowed("count"))
+            .and_modify(|n| *n = json!(n.as_i64().unwrap() + 1))
+            .or_insert(json!(0));
+        JsonObjectLike::entry(obj, Cow::Borrowed("total"))
+            .and_modify(|n| *n = json!(n.as_i64().unwrap() + 1))
+            .or_insert(json!(0));
+        let items = JsonObjectLike::entry(obj, Cow::Owned("items".to_string()))
+            .or_insert_with(|| json!([]));
+        items.as_array_mut().unwrap().push(json!(1));
+        assert_eq!(value, json!({"count": 2, "total": 0, "items": [1]}));
+
+        let text = r#"{"a": {"b": 1}}"#;
+        let mut value: serde_json_borrow::Value = serde_json::from_str(text).unwrap();
+        let obj = value.as_object_mut().unwrap();
+        match obj.entry(Cow::Borrowed("c")) {
+            super::Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), "c");
+                *entry.insert(serde_json_borrow::Value::Null) = true.into();
+            }
+            super::Entry::Occupied(_) => panic!("`c` doesn't exist"),
+        }
+        let a = obj
+            .entry(Cow::Owned("a".to_string()))
+            .or_insert(true.into());
+        a.as_object_mut()
+            .unwrap()
+            .insert_key("d", serde_json_borrow::Value::Null);
+        assert_eq!(
+            serde_json::Value::from(value),
+            json!({"a": {"b": 1, "d": null}, "c": true})
+        );
+    }
 }
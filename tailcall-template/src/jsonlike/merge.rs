@@ -0,0 +1,300 @@
+//! Merging of [`JsonLike`] values: JSON Merge Patch
+//! ([RFC 7396](https://www.rfc-editor.org/rfc/rfc7396)) with [`merge_patch`],
+//! and deep merges with configurable strategies with [`DeepMerge`].
+use std::borrow::Cow;
+
+use super::{json_eq, JsonLikeOwned, JsonObjectLike};
+
+/// Applies `patch` to `target` as a merge patch: objects are merged key by
+/// key, `null` removes a key and any other value replaces the target.
+pub fn merge_patch<A: JsonLikeOwned + Clone>(target: &mut A, patch: &A) {
+    let Some(patch) = patch.as_object() else {
+        *target = patch.clone();
+        return;
+    };
+    if target.as_object().is_none() {
+        *target = A::object(A::JsonObject::new());
+    }
+    let Some(target) = target.as_object_mut() else {
+        return;
+    };
+    for (key, value) in patch.iter() {
+        if value.is_null() {
+            target.remove_key(key);
+        } else if let Some(slot) = target.get_key_mut(key) {
+            merge_patch(slot, value);
+        } else {
+            let mut slot = A::null();
+            merge_patch(&mut slot, value);
+            target.insert_key_cow(Cow::Owned(key.to_string()), slot);
+        }
+    }
+}
+
+/// How [`DeepMerge`] combines two arrays.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ArrayMerge {
+    /// The source array replaces the target array.
+    #[default]
+    Replace,
+    /// Source items are appended to the target array.
+    Concat,
+    /// Items at the same index are merged, extra source items are appended.
+    ByIndex,
+    /// Objects having the same value at this key are merged, other source
+    /// items are appended.
+    ByKey(String),
+}
+
+/// How [`DeepMerge`] handles `null` in the source.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NullMerge {
+    /// `null` replaces the target value.
+    #[default]
+    Replace,
+    /// `null` never replaces a target value.
+    Keep,
+    /// `null` removes the key from the target object, as in a merge patch.
+    Remove,
+}
+
+/// Recursively merges a source value into a target value. Objects are merged
+/// key by key, arrays according to [`ArrayMerge`], and other source values
+/// replace the target.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DeepMerge {
+    arrays: ArrayMerge,
+    nulls: NullMerge,
+}
+
+impl DeepMerge {
+    pub fn arrays(self, arrays: ArrayMerge) -> Self {
+        Self { arrays, ..self }
+    }
+
+    pub fn nulls(self, nulls: NullMerge) -> Self {
+        Self { nulls, ..self }
+    }
+
+    pub fn merge<A: JsonLikeOwned + Clone>(&self, target: &mut A, source: &A) {
+        if source.is_null() {
+            if self.nulls != NullMerge::Keep {
+                *target = A::null();
+            }
+            return;
+        }
+        if let (Some(target), Some(source)) = (target.as_object_mut(), source.as_object()) {
+            for (key, value) in source.iter() {
+                if value.is_null() && self.nulls == NullMerge::Remove {
+                    target.remove_key(key);
+                } else if let Some(slot) = target.get_key_mut(key) {
+                    self.merge(slot, value);
+                } else {
+                    target.insert_key_cow(Cow::Owned(key.to_string()), value.clone());
+                }
+            }
+            return;
+        }
+        if let (Some(target), Some(source)) = (target.as_array_mut(), source.as_array()) {
+            match &self.arrays {
+                ArrayMerge::Replace => *target = source.clone(),
+                ArrayMerge::Concat => target.extend(source.iter().cloned()),
+                ArrayMerge::ByIndex => {
+                    for (slot, value) in target.iter_mut().zip(source) {
+                        self.merge(slot, value);
+                    }
+                    target.extend(source.iter().skip(target.len()).cloned());
+                }
+                ArrayMerge::ByKey(key) => {
+                    for value in source {
+                        let id = value.as_object().and_then(|obj| obj.get_key(key));
+                        let slot = id.and_then(|id| {
+                            target.iter_mut().find(|item| {
+                                let item_id = item.as_object().and_then(|obj| obj.get_key(key));
+                                item_id.is_some_and(|item_id| json_eq(item_id, id))
+                            })
+                        });
+                        match slot {
+                            Some(slot) => self.merge(slot, value),
+                            None => target.push(value.clone()),
+                        }
+                    }
+                }
+            }
+            return;
+        }
+        *target = source.clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{merge_patch, ArrayMerge, DeepMerge, NullMerge};
+    use crate::jsonlike::{convert, JsonLikeOwned};
+
+    fn check_merge_patch<A: JsonLikeOwned + Clone>(target: &Value, patch: &Value) -> Value {
+        let mut target: A = convert(target);
+        merge_patch(&mut target, &convert(patch));
+        convert(&target)
+    }
+
+    #[test]
+    fn test_merge_patch() {
+        // Appendix A of RFC 7396
+        let cases = [
+            (json!({"a": "b"}), json!({"a": "c"}), json!({"a": "c"})),
+            (
+                json!({"a": "b"}),
+                json!({"b": "c"}),
+                json!({"a": "b", "b": "c"}),
+            ),
+            (json!({"a": "b"}), json!({"a": null}), json!({})),
+            (
+                json!({"a": "b", "b": "c"}),
+                json!({"a": null}),
+                json!({"b": "c"}),
+            ),
+            (json!({"a": ["b"]}), json!({"a": "c"}), json!({"a": "c"})),
+            (json!({"a": "c"}), json!({"a": ["b"]}), json!({"a": ["b"]})),
+            (
+                json!({"a": {"b": "c"}}),
+                json!({"a": {"b": "d", "c": null}}),
+                json!({"a": {"b": "d"}}),
+            ),
+            (
+                json!({"a": [{"b": "c"}]}),
+                json!({"a": [1]}),
+                json!({"a": [1]}),
+            ),
+            (json!(["a", "b"]), json!(["c", "d"]), json!(["c", "d"])),
+            (json!({"a": "b"}), json!(["c"]), json!(["c"])),
+            (json!({"a": "foo"}), json!(null), json!(null)),
+            (json!({"a": "foo"}), json!("bar"), json!("bar")),
+            (
+                json!({"e": null}),
+                json!({"a": 1}),
+                json!({"e": null, "a": 1}),
+            ),
+            (
+                json!([1, 2]),
+                json!({"a": "b", "c": null}),
+                json!({"a": "b"}),
+            ),
+            (
+                json!({}),
+                json!({"a": {"bb": {"ccc": null}}}),
+                json!({"a": {"bb": {}}}),
+            ),
+        ];
+        for (target, patch, expected) in cases {
+            assert_eq!(
+                check_merge_patch::<Value>(&target, &patch),
+                expected,
+                "{}",
+                patch
+            );
+            assert_eq!(
+                check_merge_patch::<ConstValue>(&target, &patch),
+                expected,
+                "{}",
+                patch
+            );
+            assert_eq!(
+                check_merge_patch::<Val>(&target, &patch),
+                expected,
+                "{}",
+                patch
+            );
+        }
+    }
+
+    fn merge(strategy: &DeepMerge, target: Value, source: Value) -> Value {
+        let mut target = target;
+        strategy.merge(&mut target, &source);
+        target
+    }
+
+    #[test]
+    fn test_deep_merge_objects() {
+        let target = json!({"user": {"id": 1, "name": "ann", "email": "a@x"}, "ok": true});
+        let source = json!({"user": {"name": "bob", "email": null, "age": 3}, "ok": null});
+
+        let expected =
+            json!({"user": {"id": 1, "name": "bob", "email": null, "age": 3}, "ok": null});
+        assert_eq!(
+            merge(&DeepMerge::default(), target.clone(), source.clone()),
+            expected
+        );
+
+        let keep = DeepMerge::default().nulls(NullMerge::Keep);
+        let expected =
+            json!({"user": {"id": 1, "name": "bob", "email": "a@x", "age": 3}, "ok": true});
+        assert_eq!(merge(&keep, target.clone(), source.clone()), expected);
+
+        let remove = DeepMerge::default().nulls(NullMerge::Remove);
+        let expected = json!({"user": {"id": 1, "name": "bob", "age": 3}});
+        assert_eq!(merge(&remove, target, source), expected);
+
+        assert_eq!(
+            merge(&DeepMerge::default(), json!({"a": 1}), json!([1])),
+            json!([1])
+        );
+        assert_eq!(merge(&keep, json!(1), json!(null)), json!(1));
+    }
+
+    #[test]
+    fn test_deep_merge_arrays() {
+        let target = json!({"items": [{"id": 1, "a": 1}, {"id": 2, "a": 2}]});
+        let source = json!({"items": [{"id": 2, "b": 2}, {"id": 3, "b": 3}, 4]});
+        let cases = [
+            (
+                ArrayMerge::Replace,
+                json!([{"id": 2, "b": 2}, {"id": 3, "b": 3}, 4]),
+            ),
+            (
+                ArrayMerge::Concat,
+                json!([{"id": 1, "a": 1}, {"id": 2, "a": 2}, {"id": 2, "b": 2}, {"id": 3, "b": 3}, 4]),
+            ),
+            (
+                ArrayMerge::ByIndex,
+                json!([{"id": 2, "a": 1, "b": 2}, {"id": 3, "a": 2, "b": 3}, 4]),
+            ),
+            (
+                ArrayMerge::ByKey("id".to_string()),
+                json!([{"id": 1, "a": 1}, {"id": 2, "a": 2, "b": 2}, {"id": 3, "b": 3}, 4]),
+            ),
+        ];
+        for (arrays, expected) in cases {
+            let strategy = DeepMerge::default().arrays(arrays.clone());
+            let merged = merge(&strategy, target.clone(), source.clone());
+            assert_eq!(merged["items"], expected, "{:?}", arrays);
+        }
+    }
+
+    #[test]
+    fn test_deep_merge_backends() {
+        let strategy = DeepMerge::default()
+            .arrays(ArrayMerge::ByKey("id".to_string()))
+            .nulls(NullMerge::Keep);
+        let target = json!({"data": {"users": [{"id": 1, "name": "ann"}]}, "errors": null});
+        let source = json!({"data": {"users": [{"id": 1, "age": 3}]}, "errors": null});
+        let expected = merge(&strategy, target.clone(), source.clone());
+        assert_eq!(
+            expected,
+            json!({"data": {"users": [{"id": 1, "name": "ann", "age": 3}]}, "errors": null})
+        );
+
+        let mut graphql: ConstValue = convert(&target);
+        strategy.merge(&mut graphql, &convert(&source));
+        assert_eq!(convert::<_, Value>(&graphql), expected);
+
+        let mut val: Val = convert(&target);
+        strategy.merge(&mut val, &convert(&source));
+        assert_eq!(convert::<_, Value>(&val), expected);
+    }
+}
@@ -1,13 +1,39 @@
 mod borrow;
+mod canonical;
+mod convert;
+mod diff;
 mod graphql;
+mod group;
+mod hash;
+mod jaq;
+mod join;
 mod json_like;
 mod json_like_list;
+mod merge;
+mod parse;
+mod patch;
+mod pointer;
 mod serde;
+mod serialize;
+mod visit;
 
 use std::collections::HashMap;
 
+pub use borrow::into_owned;
+pub use canonical::*;
+pub use convert::*;
+pub use diff::*;
+pub use group::*;
+pub use hash::*;
+pub use join::*;
 pub use json_like::*;
 pub use json_like_list::*;
+pub use merge::*;
+pub use parse::*;
+pub use patch::*;
+pub use pointer::*;
+pub use serialize::*;
+pub use visit::*;
 
 // Highly micro-optimized and benchmarked version of get_path_all
 // Any further changes should be verified with benchmarks
@@ -33,6 +59,104 @@ pub fn gather_path_matches<'json, J: JsonLike<'json>>(
     vector
 }
 
+/// Segment of a path for [`gather_segment_matches`] and
+/// [`JsonLike::group_by_segments`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Value at this key. Arrays are walked through, applying the key to
+    /// each of their items, as in [`gather_path_matches`].
+    Key(String),
+    /// Array item at this index, counting from the end if negative.
+    Index(isize),
+    /// Every item of an array or value of an object.
+    Wildcard,
+    /// Like the inner segment, but if it selects nothing, the rest of the
+    /// path applies to the current value.
+    Optional(Box<PathSegment>),
+}
+
+impl From<&str> for PathSegment {
+    fn from(key: &str) -> Self {
+        PathSegment::Key(key.to_string())
+    }
+}
+
+impl From<String> for PathSegment {
+    fn from(key: String) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+/// Like [`gather_path_matches`], with any [`PathSegment`]. Paths of keys only
+/// are faster with [`gather_path_matches`].
+pub fn gather_segment_matches<'json, J: JsonLike<'json>>(
+    root: &'json J,
+    path: &[PathSegment],
+    mut vector: Vec<(&'json J, &'json J)>,
+) -> Vec<(&'json J, &'json J)> {
+    collect_segment_matches(root, root, path, &mut vector);
+    vector
+}
+
+fn collect_segment_matches<'json, J: JsonLike<'json>>(
+    value: &'json J,
+    parent: &'json J,
+    path: &[PathSegment],
+    out: &mut Vec<(&'json J, &'json J)>,
+) {
+    let Some((segment, tail)) = path.split_first() else {
+        out.push((value, parent));
+        return;
+    };
+    let (mut segment, mut optional) = (segment, false);
+    while let PathSegment::Optional(inner) = segment {
+        (segment, optional) = (inner, true);
+    }
+    let mut matched = false;
+    let mut visit = |child: &'json J, out: &mut Vec<(&'json J, &'json J)>| {
+        matched = true;
+        collect_segment_matches(child, value, tail, out);
+    };
+    match (segment, value.as_array()) {
+        (PathSegment::Key(_), Some(arr)) => {
+            for item in arr {
+                collect_segment_matches(item, value, path, out);
+            }
+            return;
+        }
+        (PathSegment::Key(key), None) => {
+            if let Some(child) = value.get_key(key) {
+                visit(child, out);
+            }
+        }
+        (PathSegment::Index(index), Some(arr)) => {
+            let index = match *index {
+                index if index < 0 => arr.len().checked_sub(index.unsigned_abs()),
+                index => Some(index as usize),
+            };
+            if let Some(item) = index.and_then(|index| arr.get(index)) {
+                visit(item, out);
+            }
+        }
+        (PathSegment::Wildcard, Some(arr)) => {
+            for item in arr {
+                visit(item, out);
+            }
+        }
+        (PathSegment::Wildcard, None) => {
+            if let Some(obj) = value.as_object() {
+                for (_, child) in obj.iter() {
+                    visit(child, out);
+                }
+            }
+        }
+        (PathSegment::Index(_), None) | (PathSegment::Optional(_), _) => {}
+    }
+    if optional && !matched {
+        collect_segment_matches(value, parent, tail, out);
+    }
+}
+
 fn group_by_key<'json, J: JsonLike<'json>>(
     src: Vec<(&'json J, &'json J)>,
 ) -> HashMap<String, Vec<&'json J>> {
@@ -0,0 +1,359 @@
+//! JSON parser that builds any [`JsonLike`] value.
+//!
+//! Values are built with the trait constructors, so no intermediate
+//! representation is allocated. Strings and keys without escapes are passed to
+//! the backend as [`Cow::Borrowed`] slices of the input, which backends such as
+//! `serde_json_borrow` keep without copying. Integers are kept exactly when
+//! they fit in `i64` or `u64`.
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take};
+use nom::character::complete::{anychar, char, digit0, digit1, multispace0, one_of};
+use nom::combinator::{cut, map, map_opt, map_res, opt, recognize, verify};
+use nom::error::{Error, ErrorKind};
+use nom::sequence::{delimited, preceded, separated_pair};
+use nom::{IResult, Parser};
+
+use super::JsonLike;
+
+/// Maximum nesting of arrays and objects, like `serde_json`.
+const MAX_DEPTH: usize = 128;
+
+/// Parses `input` as a single JSON value, surrounded by optional whitespace.
+pub fn from_str<'a, A: JsonLike<'a>>(input: &'a str) -> Result<A, JsonParseError> {
+    let result = delimited(multispace0, |i| json_value(i, 0), multispace0).parse(input);
+    match result {
+        Ok(("", value)) => Ok(value),
+        Ok((rest, _)) => Err(JsonParseError::new(input, rest, "trailing characters")),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => {
+            let message = match (err.code, err.input.chars().next()) {
+                (ErrorKind::TooLarge, _) => "nesting is too deep".to_string(),
+                (ErrorKind::Float, _) => "number out of range".to_string(),
+                (_, Some(c)) => format!("unexpected character `{}`", c.escape_debug()),
+                (_, None) => "unexpected end of input".to_string(),
+            };
+            Err(JsonParseError::new(input, err.input, message))
+        }
+        Err(nom::Err::Incomplete(_)) => {
+            Err(JsonParseError::new(input, "", "unexpected end of input"))
+        }
+    }
+}
+
+/// Error produced by [`from_str`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonParseError {
+    pub message: String,
+    /// Byte offset of the error in the input.
+    pub offset: usize,
+}
+
+impl JsonParseError {
+    fn new(input: &str, rest: &str, message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            offset: input.len() - rest.len(),
+        }
+    }
+}
+
+impl Display for JsonParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonParseError {}
+
+fn json_value<'a, A: JsonLike<'a>>(input: &'a str, depth: usize) -> IResult<&'a str, A> {
+    match input.chars().next() {
+        Some('n') => map(tag("null"), |_| A::null()).parse(input),
+        Some('t') => map(tag("true"), |_| A::boolean(true)).parse(input),
+        Some('f') => map(tag("false"), |_| A::boolean(false)).parse(input),
+        Some('"') => map(string, A::string).parse(input),
+        Some('[') => map(|i| array(i, depth), A::array).parse(input),
+        Some('{') => map(|i| object(i, depth), A::obj_cow).parse(input),
+        Some('-' | '0'..='9') => number(input),
+        _ => Err(nom::Err::Error(Error::new(input, ErrorKind::Char))),
+    }
+}
+
+fn number<'a, A: JsonLike<'a>>(input: &'a str) -> IResult<&'a str, A> {
+    let (rest, text) = recognize((
+        opt(char('-')),
+        alt((tag("0"), recognize((one_of("123456789"), digit0)))),
+        opt((char('.'), cut(digit1))),
+        opt((one_of("eE"), opt(one_of("+-")), cut(digit1))),
+    ))
+    .parse(input)?;
+
+    // `-0` stays a float so the sign survives, as in serde_json.
+    if !text.contains(['.', 'e', 'E']) && text != "-0" {
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok((rest, A::number_i64(n)));
+        }
+        if let Ok(n) = text.parse::<u64>() {
+            return Ok((rest, A::number_u64(n)));
+        }
+    }
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok((rest, A::number_f64(n))),
+        _ => Err(nom::Err::Failure(Error::new(input, ErrorKind::Float))),
+    }
+}
+
+fn array<'a, A: JsonLike<'a>>(input: &'a str, depth: usize) -> IResult<&'a str, Vec<A>> {
+    sequence(input, depth, '[', ']', |i| json_value(i, depth + 1))
+}
+
+fn object<'a, A: JsonLike<'a>>(
+    input: &'a str,
+    depth: usize,
+) -> IResult<&'a str, Vec<(Cow<'a, str>, A)>> {
+    let member = |i| {
+        separated_pair(
+            string,
+            cut(delimited(multispace0, char(':'), multispace0)),
+            cut(|i| json_value(i, depth + 1)),
+        )
+        .parse(i)
+    };
+    sequence(input, depth, '{', '}', member)
+}
+
+/// Comma separated items between `open` and `close`.
+fn sequence<'a, T>(
+    input: &'a str,
+    depth: usize,
+    open: char,
+    close: char,
+    mut item: impl FnMut(&'a str) -> IResult<&'a str, T>,
+) -> IResult<&'a str, Vec<T>> {
+    if depth >= MAX_DEPTH {
+        return Err(nom::Err::Failure(Error::new(input, ErrorKind::TooLarge)));
+    }
+    let (mut input, _) = (char(open), multispace0).parse(input)?;
+    let mut items = Vec::new();
+    if let Some(rest) = input.strip_prefix(close) {
+        return Ok((rest, items));
+    }
+    loop {
+        let (rest, value) = cut(&mut item).parse(input)?;
+        items.push(value);
+        let (rest, _) = multispace0(rest)?;
+        if let Some(rest) = rest.strip_prefix(close) {
+            return Ok((rest, items));
+        }
+        (input, _) = cut((char(','), multispace0)).parse(rest)?;
+    }
+}
+
+/// A quoted string, borrowed from the input if it has no escapes.
+fn string(input: &str) -> IResult<&str, Cow<'_, str>> {
+    let (input, _) = char('"').parse(input)?;
+    let end = input
+        .find(|c: char| c == '"' || c == '\\' || c < ' ')
+        .unwrap_or(input.len());
+    if let Some(rest) = input[end..].strip_prefix('"') {
+        return Ok((rest, Cow::Borrowed(&input[..end])));
+    }
+
+    let mut out = input[..end].to_string();
+    let mut rest = &input[end..];
+    loop {
+        match rest.chars().next() {
+            Some('"') => return Ok((&rest[1..], Cow::Owned(out))),
+            Some('\\') => {
+                // Points at the invalid escape rather than inside it
+                let (next, c) = escape(&rest[1..])
+                    .map_err(|_| nom::Err::Failure(Error::new(&rest[1..], ErrorKind::Char)))?;
+                out.push(c);
+                rest = next;
+            }
+            Some(c) if c >= ' ' => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            _ => return Err(nom::Err::Failure(Error::new(rest, ErrorKind::Char))),
+        }
+    }
+}
+
+/// The character escaped after a backslash.
+fn escape(input: &str) -> IResult<&str, char> {
+    alt((
+        map_opt(anychar, |c| match c {
+            '"' | '\\' | '/' => Some(c),
+            'b' => Some('\x08'),
+            'f' => Some('\x0C'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            _ => None,
+        }),
+        preceded(char('u'), unicode_escape),
+    ))
+    .parse(input)
+}
+
+fn u16_hex(input: &str) -> IResult<&str, u16> {
+    map_res(
+        verify(take(4usize), |s: &str| {
+            s.chars().all(|c| c.is_ascii_hexdigit())
+        }),
+        |s| u16::from_str_radix(s, 16),
+    )
+    .parse(input)
+}
+
+fn unicode_escape(input: &str) -> IResult<&str, char> {
+    map_opt(
+        alt((
+            // Not a surrogate
+            map(verify(u16_hex, |cp| !(0xD800..0xE000).contains(cp)), |cp| {
+                cp as u32
+            }),
+            // See https://en.wikipedia.org/wiki/UTF-16#Code_points_from_U+010000_to_U+10FFFF for details
+            map(
+                verify(
+                    separated_pair(u16_hex, tag("\\u"), u16_hex),
+                    |(high, low)| (0xD800..0xDC00).contains(high) && (0xDC00..0xE000).contains(low),
+                ),
+                |(high, low)| {
+                    let high_ten = (high as u32) - 0xD800;
+                    let low_ten = (low as u32) - 0xDC00;
+                    (high_ten << 10) + low_ten + 0x10000
+                },
+            ),
+        )),
+        std::char::from_u32,
+    )
+    .parse(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{from_str, JsonParseError};
+    use crate::jsonlike::{convert, JsonSerializer};
+
+    const INPUT: &str = r#" {
+        "name": "John \"J\" é😀",
+        "age": 30,
+        "is_student": false,
+        "scores": [85.5, 90, -7.5e-1, 1E2],
+        "address": null,
+        "big": 18446744073709551615,
+        "small": -9223372036854775808,
+        "a\/b": {"": []}
+    } "#;
+
+    #[test]
+    fn test_matches_serde_json() {
+        let expected: serde_json::Value = serde_json::from_str(INPUT).unwrap();
+        assert_eq!(from_str::<serde_json::Value>(INPUT).unwrap(), expected);
+        assert_eq!(
+            from_str::<ConstValue>(INPUT).unwrap(),
+            convert::<_, ConstValue>(&expected)
+        );
+        assert_eq!(
+            from_str::<Val>(INPUT).unwrap(),
+            convert::<_, Val>(&expected)
+        );
+        let borrowed = from_str::<serde_json_borrow::Value>(INPUT).unwrap();
+        assert_eq!(convert::<_, serde_json::Value>(&borrowed), expected);
+    }
+
+    #[test]
+    fn test_borrows_unescaped_strings() {
+        let input = r#"{"plain": "text", "escaped\n": "a\tb"}"#;
+        let value = from_str::<serde_json_borrow::Value>(input).unwrap();
+        assert!(matches!(
+            value.get("plain"),
+            serde_json_borrow::Value::Str(Cow::Borrowed("text"))
+        ));
+        assert!(matches!(
+            value.get("escaped\n"),
+            serde_json_borrow::Value::Str(Cow::Owned(s)) if s == "a\tb"
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let input = r#"{"a\n":1,"a\n":2,"b":3}"#;
+        let expected: serde_json::Value = serde_json::from_str(input).unwrap();
+        let borrowed = from_str::<serde_json_borrow::Value>(input).unwrap();
+        assert_eq!(borrowed.as_object().map(|obj| obj.len()), Some(3));
+        assert_eq!(convert::<_, serde_json::Value>(&borrowed), expected);
+        assert_eq!(from_str::<serde_json::Value>(input).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_numbers() {
+        let value = from_str::<serde_json::Value>("[0, -0, 1.0, 1e400000000000000000000]");
+        assert_eq!(
+            value,
+            Err(JsonParseError {
+                message: "number out of range".to_string(),
+                offset: 13
+            })
+        );
+        let input = "[0, -0, 1.0, 12345678901234567890123]";
+        let value = from_str::<serde_json::Value>(input).unwrap();
+        assert_eq!(value, json!([0, -0.0, 1.0, 1.2345678901234568e22]));
+        assert_eq!(
+            value,
+            serde_json::from_str::<serde_json::Value>(input).unwrap()
+        );
+        assert_eq!(value[0].as_i64(), Some(0));
+        assert!(value[1].as_f64().is_some_and(f64::is_sign_negative));
+        assert_eq!(
+            JsonSerializer::default().to_string(&value),
+            "[0,-0,1,1.2345678901234568e+22]"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("", "unexpected end of input at byte 0"),
+            ("[1, 2", "unexpected end of input at byte 5"),
+            ("[1, x]", "unexpected character `x` at byte 4"),
+            ("[1 2]", "unexpected character `2` at byte 3"),
+            (r#"{"a" 1}"#, "unexpected character `1` at byte 5"),
+            (r#"{"a": 1,}"#, "unexpected character `}` at byte 8"),
+            (r#"{1: 2}"#, "unexpected character `1` at byte 1"),
+            ("01", "trailing characters at byte 1"),
+            ("1.", "unexpected end of input at byte 2"),
+            ("nul", "unexpected character `n` at byte 0"),
+            (r#""a\x""#, "unexpected character `x` at byte 3"),
+            (r#""\ud800""#, "unexpected character `u` at byte 2"),
+            ("\"a\nb\"", "unexpected character `\\n` at byte 2"),
+            ("\"abc", "unexpected end of input at byte 4"),
+            ("{} {}", "trailing characters at byte 3"),
+        ];
+        for (input, expected) in cases {
+            let err = from_str::<serde_json::Value>(input).unwrap_err();
+            assert_eq!(err.to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        let ok = format!("{}{}", "[".repeat(128), "]".repeat(128));
+        assert!(from_str::<serde_json::Value>(&ok).is_ok());
+        let deep = format!("{}{}", "[".repeat(129), "]".repeat(129));
+        assert_eq!(
+            from_str::<ConstValue>(&deep).unwrap_err().to_string(),
+            "nesting is too deep at byte 128"
+        );
+    }
+}
@@ -0,0 +1,635 @@
+//! JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902)) documents
+//! over [`JsonLike`] values.
+//!
+//! A patch is a list of operations addressing values with JSON Pointers, see
+//! [`JsonPointer`]. [`JsonPatch::apply`] applies all of them or none of them,
+//! and [`JsonPatch::diff`] produces the patch turning one value into another.
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use super::pointer::{array_index, child_mut, escape_token, tokens};
+use super::{json_eq, JsonLike, JsonLikeOwned, JsonObjectLike, JsonPointer, PointerError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchOperation<A> {
+    Add {
+        path: String,
+        value: A,
+    },
+    Remove {
+        path: String,
+    },
+    Replace {
+        path: String,
+        value: A,
+    },
+    Move {
+        from: String,
+        path: String,
+    },
+    Copy {
+        from: String,
+        path: String,
+    },
+    /// Fails the patch unless the value at `path` equals `value`.
+    Test {
+        path: String,
+        value: A,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPatch<A>(pub Vec<PatchOperation<A>>);
+
+impl<A> JsonPatch<A> {
+    /// Reads a patch document, an array of operation objects.
+    pub fn from_value<'json>(value: &'json A) -> Result<Self, PatchError>
+    where
+        A: JsonLike<'json> + Clone,
+    {
+        let operations = value.as_array().ok_or_else(|| PatchError::Invalid {
+            index: None,
+            message: "a patch must be an array".to_string(),
+        })?;
+        operations
+            .iter()
+            .enumerate()
+            .map(|(index, operation)| {
+                read_operation(operation).map_err(|message| PatchError::Invalid {
+                    index: Some(index),
+                    message,
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(JsonPatch)
+    }
+
+    /// The patch document, as read by [`from_value`](Self::from_value).
+    pub fn to_value<'json>(&self) -> A
+    where
+        A: JsonLike<'json> + Clone,
+    {
+        A::array(self.0.iter().map(write_operation).collect())
+    }
+
+    /// Applies the operations in order. If one of them fails, `target` is
+    /// left untouched.
+    pub fn apply(&self, target: &mut A) -> Result<(), PatchError>
+    where
+        A: JsonLikeOwned + Clone,
+    {
+        let mut patched = target.clone();
+        for (index, operation) in self.0.iter().enumerate() {
+            apply_operation(&mut patched, index, operation)?;
+        }
+        *target = patched;
+        Ok(())
+    }
+
+    /// A patch turning `from` into `to`. Objects are patched key by key and
+    /// arrays item by item, after skipping the items they start and end with.
+    pub fn diff<'json>(from: &'json A, to: &'json A) -> Self
+    where
+        A: JsonLike<'json> + Clone,
+    {
+        let mut operations = Vec::new();
+        diff(from, to, &mut String::new(), &mut operations);
+        JsonPatch(operations)
+    }
+}
+
+/// Error produced by [`JsonPatch::from_value`] and [`JsonPatch::apply`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatchError {
+    /// The patch isn't an array, or the operation at `index` is malformed.
+    Invalid {
+        index: Option<usize>,
+        message: String,
+    },
+    /// The operation at `index` addresses a value that doesn't exist.
+    Pointer { index: usize, error: PointerError },
+    /// The `test` operation at `index` failed.
+    TestFailed { index: usize, path: String },
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::Invalid {
+                index: None,
+                message,
+            } => write!(f, "invalid patch: {}", message),
+            PatchError::Invalid {
+                index: Some(index),
+                message,
+            } => {
+                write!(f, "invalid operation {}: {}", index, message)
+            }
+            PatchError::Pointer { index, error } => {
+                write!(f, "operation {} failed: {}", index, error)
+            }
+            PatchError::TestFailed { index, path } => {
+                write!(f, "test of operation {} failed at `{}`", index, path)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+fn read_operation<'json, A: JsonLike<'json> + Clone>(
+    operation: &'json A,
+) -> Result<PatchOperation<A>, String> {
+    let obj = operation
+        .as_object()
+        .ok_or_else(|| "an operation must be an object".to_string())?;
+    let string = |name: &str| match obj.get_key(name) {
+        Some(value) => value
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| format!("`{}` must be a string", name)),
+        None => Err(format!("missing `{}`", name)),
+    };
+    let value = || {
+        obj.get_key("value")
+            .cloned()
+            .ok_or_else(|| "missing `value`".to_string())
+    };
+
+    let op = string("op")?;
+    let path = string("path")?;
+    match op.as_str() {
+        "add" => Ok(PatchOperation::Add {
+            path,
+            value: value()?,
+        }),
+        "remove" => Ok(PatchOperation::Remove { path }),
+        "replace" => Ok(PatchOperation::Replace {
+            path,
+            value: value()?,
+        }),
+        "move" => Ok(PatchOperation::Move {
+            from: string("from")?,
+            path,
+        }),
+        "copy" => Ok(PatchOperation::Copy {
+            from: string("from")?,
+            path,
+        }),
+        "test" => Ok(PatchOperation::Test {
+            path,
+            value: value()?,
+        }),
+        op => Err(format!("unknown operation `{}`", op)),
+    }
+}
+
+fn write_operation<'json, A: JsonLike<'json> + Clone>(operation: &PatchOperation<A>) -> A {
+    let string = |s: &str| A::string(Cow::Owned(s.to_string()));
+    let (op, path, rest) = match operation {
+        PatchOperation::Add { path, value } => ("add", path, ("value", value.clone())),
+        PatchOperation::Remove { path } => {
+            return A::obj(vec![("op", string("remove")), ("path", string(path))])
+        }
+        PatchOperation::Replace { path, value } => ("replace", path, ("value", value.clone())),
+        PatchOperation::Move { from, path } => ("move", path, ("from", string(from))),
+        PatchOperation::Copy { from, path } => ("copy", path, ("from", string(from))),
+        PatchOperation::Test { path, value } => ("test", path, ("value", value.clone())),
+    };
+    A::obj(vec![("op", string(op)), ("path", string(path)), rest])
+}
+
+fn apply_operation<A: JsonLikeOwned + Clone>(
+    target: &mut A,
+    index: usize,
+    operation: &PatchOperation<A>,
+) -> Result<(), PatchError> {
+    let failed = |error| PatchError::Pointer { index, error };
+    let not_found = |path: &str| failed(PointerError::NotFound(path.to_string()));
+    let (path, from) = match operation {
+        PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+            (path, Some(from))
+        }
+        PatchOperation::Add { path, .. }
+        | PatchOperation::Remove { path }
+        | PatchOperation::Replace { path, .. }
+        | PatchOperation::Test { path, .. } => (path, None),
+    };
+    for pointer in std::iter::once(path).chain(from) {
+        tokens(pointer).map_err(failed)?;
+    }
+    match operation {
+        PatchOperation::Add { path, value } => add(target, path, value.clone()).map_err(failed),
+        PatchOperation::Remove { path } => remove(target, path).map(drop).map_err(failed),
+        PatchOperation::Replace { path, value } => {
+            *target.pointer_mut(path).ok_or_else(|| not_found(path))? = value.clone();
+            Ok(())
+        }
+        PatchOperation::Move { from, path } => {
+            if from == path {
+                return target
+                    .pointer(from)
+                    .map(drop)
+                    .ok_or_else(|| not_found(from));
+            }
+            if path.starts_with(from.as_str()) && path[from.len()..].starts_with('/') {
+                return Err(PatchError::Invalid {
+                    index: Some(index),
+                    message: format!("can't move `{}` into itself", from),
+                });
+            }
+            let value = remove(target, from).map_err(failed)?;
+            add(target, path, value).map_err(failed)
+        }
+        PatchOperation::Copy { from, path } => {
+            let value = target
+                .pointer(from)
+                .cloned()
+                .ok_or_else(|| not_found(from))?;
+            add(target, path, value).map_err(failed)
+        }
+        PatchOperation::Test { path, value } => {
+            let actual = target.pointer(path).ok_or_else(|| not_found(path))?;
+            match json_eq(actual, value) {
+                true => Ok(()),
+                false => Err(PatchError::TestFailed {
+                    index,
+                    path: path.clone(),
+                }),
+            }
+        }
+    }
+}
+
+/// Like [`JsonPointer::set_pointer`], but inserts into arrays rather than
+/// replacing their items.
+fn add<A: JsonLikeOwned>(target: &mut A, path: &str, value: A) -> Result<(), PointerError> {
+    let tokens = tokens(path)?;
+    if let Some((last, parents)) = tokens.split_last() {
+        let parent = parents
+            .iter()
+            .try_fold(&mut *target, |value, token| child_mut(value, token));
+        if let Some(arr) = parent.and_then(|parent| parent.as_array_mut()) {
+            let index = match last.as_ref() {
+                "-" => arr.len(),
+                token => array_index(token)
+                    .filter(|index| *index <= arr.len())
+                    .ok_or_else(|| PointerError::NotFound(path.to_string()))?,
+            };
+            arr.insert(index, value);
+            return Ok(());
+        }
+    }
+    target.set_pointer(path, value).map(drop)
+}
+
+fn remove<A: JsonLikeOwned>(target: &mut A, path: &str) -> Result<A, PointerError> {
+    target
+        .remove_pointer(path)
+        .ok_or_else(|| PointerError::NotFound(path.to_string()))
+}
+
+fn diff<'json, A: JsonLike<'json> + Clone>(
+    from: &'json A,
+    to: &'json A,
+    path: &mut String,
+    operations: &mut Vec<PatchOperation<A>>,
+) {
+    if json_eq(from, to) {
+        return;
+    }
+    if let (Some(from), Some(to)) = (from.as_array(), to.as_array()) {
+        let same = |(from, to): &(&'json A, &'json A)| json_eq(*from, *to);
+        let prefix = from.iter().zip(to).take_while(same).count();
+        let (from, to) = (&from[prefix..], &to[prefix..]);
+        let suffix = from
+            .iter()
+            .rev()
+            .zip(to.iter().rev())
+            .take_while(same)
+            .count();
+        let (from, to) = (&from[..from.len() - suffix], &to[..to.len() - suffix]);
+
+        let common = from.len().min(to.len());
+        for (index, (from, to)) in from.iter().zip(to).enumerate() {
+            with_token(path, &(prefix + index).to_string(), |path| {
+                diff(from, to, path, operations)
+            });
+        }
+        for _ in common..from.len() {
+            let path = format!("{}/{}", path, prefix + common);
+            operations.push(PatchOperation::Remove { path });
+        }
+        for (index, value) in to.iter().enumerate().skip(common) {
+            let path = format!("{}/{}", path, prefix + index);
+            operations.push(PatchOperation::Add {
+                path,
+                value: value.clone(),
+            });
+        }
+        return;
+    }
+    if let (Some(from), Some(to)) = (from.as_object(), to.as_object()) {
+        for (key, _) in from.iter() {
+            if to.get_key(key).is_none() {
+                let path = format!("{}/{}", path, escape_token(key));
+                operations.push(PatchOperation::Remove { path });
+            }
+        }
+        for (key, value) in to.iter() {
+            match from.get_key(key) {
+                Some(old) => with_token(path, &escape_token(key), |path| {
+                    diff(old, value, path, operations)
+                }),
+                None => {
+                    let path = format!("{}/{}", path, escape_token(key));
+                    operations.push(PatchOperation::Add {
+                        path,
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        return;
+    }
+    operations.push(PatchOperation::Replace {
+        path: path.clone(),
+        value: to.clone(),
+    });
+}
+
+/// Calls `f` with `token` appended to `path`.
+fn with_token(path: &mut String, token: &str, f: impl FnOnce(&mut String)) {
+    let len = path.len();
+    path.push('/');
+    path.push_str(token);
+    f(path);
+    path.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{JsonPatch, PatchError};
+    use crate::jsonlike::{convert, JsonLikeOwned, PointerError};
+
+    fn apply(doc: Value, patch: Value) -> Result<Value, PatchError> {
+        let mut doc = doc;
+        JsonPatch::from_value(&patch)?.apply(&mut doc)?;
+        Ok(doc)
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        // Appendix A of RFC 6902
+        let cases = [
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux"}]),
+                json!({"baz": "qux", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "baz"]}),
+                json!([{"op": "add", "path": "/foo/1", "value": "qux"}]),
+                json!({"foo": ["bar", "qux", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "remove", "path": "/baz"}]),
+                json!({"foo": "bar"}),
+            ),
+            (
+                json!({"foo": ["bar", "qux", "baz"]}),
+                json!([{"op": "remove", "path": "/foo/1"}]),
+                json!({"foo": ["bar", "baz"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": "bar"}),
+                json!([{"op": "replace", "path": "/baz", "value": "boo"}]),
+                json!({"baz": "boo", "foo": "bar"}),
+            ),
+            (
+                json!({"foo": {"bar": "baz", "waldo": "fred"}, "qux": {"corge": "grault"}}),
+                json!([{"op": "move", "from": "/foo/waldo", "path": "/qux/thud"}]),
+                json!({"foo": {"bar": "baz"}, "qux": {"corge": "grault", "thud": "fred"}}),
+            ),
+            (
+                json!({"foo": ["all", "grass", "cows", "eat"]}),
+                json!([{"op": "move", "from": "/foo/1", "path": "/foo/3"}]),
+                json!({"foo": ["all", "cows", "eat", "grass"]}),
+            ),
+            (
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+                json!([
+                    {"op": "test", "path": "/baz", "value": "qux"},
+                    {"op": "test", "path": "/foo/1", "value": 2}
+                ]),
+                json!({"baz": "qux", "foo": ["a", 2, "c"]}),
+            ),
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/child", "value": {"grandchild": {}}}]),
+                json!({"foo": "bar", "child": {"grandchild": {}}}),
+            ),
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz", "value": "qux", "xyz": 123}]),
+                json!({"foo": "bar", "baz": "qux"}),
+            ),
+            (
+                json!({"/": 9, "~1": 10}),
+                json!([{"op": "test", "path": "/~01", "value": 10}]),
+                json!({"/": 9, "~1": 10}),
+            ),
+            (
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/-", "value": ["abc", "def"]}]),
+                json!({"foo": ["bar", ["abc", "def"]]}),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "copy", "from": "/foo", "path": "/bar"}]),
+                json!({"foo": 1, "bar": 1}),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "test", "path": "/foo", "value": 1.0}]),
+                json!({"foo": 1}),
+            ),
+        ];
+        for (doc, patch, expected) in cases {
+            assert_eq!(apply(doc, patch.clone()), Ok(expected), "{}", patch);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let not_found = |index, path: &str| PatchError::Pointer {
+            index,
+            error: PointerError::NotFound(path.to_string()),
+        };
+        let cases = [
+            (
+                json!({"foo": "bar"}),
+                json!([{"op": "add", "path": "/baz/bat", "value": "qux"}]),
+                not_found(0, "/baz"),
+            ),
+            (
+                json!({"/": 9, "~1": 10}),
+                json!([{"op": "test", "path": "/~01", "value": "10"}]),
+                PatchError::TestFailed {
+                    index: 0,
+                    path: "/~01".to_string(),
+                },
+            ),
+            (
+                json!({"id": 9007199254740993u64}),
+                json!([{"op": "test", "path": "/id", "value": 9007199254740992u64}]),
+                PatchError::TestFailed {
+                    index: 0,
+                    path: "/id".to_string(),
+                },
+            ),
+            (
+                json!({"foo": ["bar"]}),
+                json!([{"op": "add", "path": "/foo/2", "value": 1}]),
+                not_found(0, "/foo/2"),
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "remove", "path": "/bar"}]),
+                not_found(0, "/bar"),
+            ),
+            (
+                json!({"foo": {}}),
+                json!([{"op": "move", "from": "/foo", "path": "/foo/bar"}]),
+                PatchError::Invalid {
+                    index: Some(0),
+                    message: "can't move `/foo` into itself".to_string(),
+                },
+            ),
+            (
+                json!({"foo": 1}),
+                json!([{"op": "replace", "path": "foo", "value": 2}]),
+                PatchError::Pointer {
+                    index: 0,
+                    error: PointerError::Invalid("foo".to_string()),
+                },
+            ),
+        ];
+        for (doc, patch, expected) in cases {
+            assert_eq!(apply(doc, patch.clone()), Err(expected), "{}", patch);
+        }
+
+        let invalid = |patch: Value| JsonPatch::from_value(&patch).unwrap_err().to_string();
+        assert_eq!(
+            invalid(json!({})),
+            "invalid patch: a patch must be an array"
+        );
+        assert_eq!(
+            invalid(json!([{"op": "add", "path": "/a"}])),
+            "invalid operation 0: missing `value`"
+        );
+        assert_eq!(
+            invalid(json!([{"op": "remove", "path": "/a"}, {"op": "x", "path": "/a"}])),
+            "invalid operation 1: unknown operation `x`"
+        );
+        assert_eq!(
+            invalid(json!([{"op": "copy", "path": 1}])),
+            "invalid operation 0: `path` must be a string"
+        );
+    }
+
+    #[test]
+    fn test_atomic() {
+        let mut doc = json!({"a": [1, 2], "b": true});
+        let patch = JsonPatch::from_value(&json!([
+            {"op": "remove", "path": "/a/0"},
+            {"op": "add", "path": "/c", "value": null},
+            {"op": "test", "path": "/b", "value": false}
+        ]))
+        .unwrap();
+        assert_eq!(
+            patch.apply(&mut doc),
+            Err(PatchError::TestFailed {
+                index: 2,
+                path: "/b".to_string()
+            })
+        );
+        assert_eq!(doc, json!({"a": [1, 2], "b": true}));
+    }
+
+    fn check_diff<A>(from: &Value, to: &Value, expected: &Value)
+    where
+        A: JsonLikeOwned + Clone + PartialEq + std::fmt::Debug,
+    {
+        let (from, to): (A, A) = (convert(from), convert(to));
+        let patch = JsonPatch::diff(&from, &to);
+        assert_eq!(convert::<_, Value>(&patch.to_value()), *expected);
+        let mut patched = from.clone();
+        patch.apply(&mut patched).unwrap();
+        assert_eq!(patched, to);
+    }
+
+    #[test]
+    fn test_diff() {
+        // keys are sorted, so the order of the operations doesn't depend on
+        // whether `serde_json` preserves the order of keys
+        let from = json!({
+            "gone": null,
+            "id": 1,
+            "kind": [1],
+            "tags": ["a", "b", "c", "d"],
+            "user": {"a/b": 1, "name": "ann"}
+        });
+        let to = json!({
+            "id": 1,
+            "kind": {"n": 1},
+            "tags": ["a", "x", "d"],
+            "user": {"a/b": 1, "name": "bob", "rank": 30}
+        });
+        let expected = json!([
+            {"op": "remove", "path": "/gone"},
+            {"op": "replace", "path": "/kind", "value": {"n": 1}},
+            {"op": "replace", "path": "/tags/1", "value": "x"},
+            {"op": "remove", "path": "/tags/2"},
+            {"op": "replace", "path": "/user/name", "value": "bob"},
+            {"op": "add", "path": "/user/rank", "value": 30}
+        ]);
+        check_diff::<Value>(&from, &to, &expected);
+        check_diff::<ConstValue>(&from, &to, &expected);
+        check_diff::<Val>(&from, &to, &expected);
+
+        let inserted = json!([
+            {"op": "add", "path": "/1", "value": 0},
+            {"op": "add", "path": "/2", "value": 0}
+        ]);
+        check_diff::<Value>(&json!([1, 2, 3]), &json!([1, 0, 0, 2, 3]), &inserted);
+        check_diff::<Value>(
+            &json!(1),
+            &json!("1"),
+            &json!([{"op": "replace", "path": "", "value": "1"}]),
+        );
+        let (one, float) = (json!({"a": 1}), json!({"a": 1.0}));
+        assert_eq!(JsonPatch::diff(&one, &float), JsonPatch(vec![]));
+    }
+
+    #[test]
+    fn test_diff_borrowed() {
+        let (from, to) = (r#"{"a": [1, 2], "b": "x"}"#, r#"{"a": [2], "b": "y"}"#);
+        let from: serde_json_borrow::Value = serde_json::from_str(from).unwrap();
+        let to: serde_json_borrow::Value = serde_json::from_str(to).unwrap();
+        let patch = JsonPatch::diff(&from, &to);
+        assert_eq!(
+            convert::<_, Value>(&patch.to_value()),
+            json!([
+                {"op": "remove", "path": "/a/0"},
+                {"op": "replace", "path": "/b", "value": "y"}
+            ])
+        );
+    }
+}
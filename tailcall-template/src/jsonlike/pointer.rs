@@ -0,0 +1,349 @@
+//! JSON Pointer ([RFC 6901](https://www.rfc-editor.org/rfc/rfc6901)) access to
+//! [`JsonLike`] values.
+//!
+//! `""` is the whole value and every `/`-prefixed token selects an object key
+//! or an array index, with `~1` standing for `/` and `~0` for `~` in tokens.
+//! Array indexes have no leading zeros. `-` is the position after the last
+//! item of an array: [`JsonPointer::set_pointer`] appends there, and nothing
+//! can be read or removed there.
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use super::{JsonLike, JsonObjectLike};
+
+pub trait JsonPointer<'json>: JsonLike<'json> {
+    fn pointer<'a>(&'a self, pointer: &str) -> Option<&'a Self>
+    where
+        'json: 'a,
+    {
+        tokens(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| child(value, token))
+    }
+
+    fn pointer_mut<'a>(&'a mut self, pointer: &str) -> Option<&'a mut Self>
+    where
+        'json: 'a,
+    {
+        tokens(pointer)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))
+    }
+
+    /// Sets the value at `pointer` and returns the value it replaced. Array
+    /// items are replaced, except at `-` or the length of the array, where
+    /// `value` is appended. Everything but the last token must exist.
+    fn set_pointer(&mut self, pointer: &str, value: Self) -> Result<Option<Self>, PointerError> {
+        let tokens = tokens(pointer)?;
+        let Some((last, parents)) = tokens.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        let parent_pointer = &pointer[..pointer.rfind('/').unwrap_or(0)];
+        let parent = parents
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))
+            .ok_or_else(|| PointerError::NotFound(parent_pointer.to_string()))?;
+
+        if let Some(arr) = parent.as_array_mut() {
+            let index = match last.as_ref() {
+                "-" => arr.len(),
+                token => array_index(token)
+                    .filter(|index| *index <= arr.len())
+                    .ok_or_else(|| PointerError::NotFound(pointer.to_string()))?,
+            };
+            if index == arr.len() {
+                arr.push(value);
+                return Ok(None);
+            }
+            return Ok(Some(std::mem::replace(&mut arr[index], value)));
+        }
+        let obj = parent
+            .as_object_mut()
+            .ok_or_else(|| PointerError::NotContainer(parent_pointer.to_string()))?;
+        match obj.get_key_mut(last) {
+            Some(slot) => Ok(Some(std::mem::replace(slot, value))),
+            None => {
+                obj.insert_key_cow(Cow::Owned(last.to_string()), value);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Removes the value at `pointer` and returns it. Later array items move
+    /// down by one. The whole value can't be removed.
+    fn remove_pointer(&mut self, pointer: &str) -> Option<Self> {
+        let tokens = tokens(pointer).ok()?;
+        let (last, parents) = tokens.split_last()?;
+        let parent = parents
+            .iter()
+            .try_fold(self, |value, token| child_mut(value, token))?;
+        if let Some(arr) = parent.as_array_mut() {
+            let index = array_index(last).filter(|index| *index < arr.len())?;
+            return Some(arr.remove(index));
+        }
+        parent.as_object_mut()?.remove_key(last)
+    }
+}
+
+impl<'json, T: JsonLike<'json>> JsonPointer<'json> for T {}
+
+/// Error produced by [`JsonPointer::set_pointer`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum PointerError {
+    /// The pointer is neither empty nor starts with `/`, or has a `~` that
+    /// isn't followed by `0` or `1`.
+    Invalid(String),
+    /// Nothing exists at this pointer.
+    NotFound(String),
+    /// The value at this pointer is neither an array nor an object.
+    NotContainer(String),
+}
+
+impl Display for PointerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PointerError::Invalid(pointer) => write!(f, "invalid JSON pointer `{}`", pointer),
+            PointerError::NotFound(pointer) => write!(f, "nothing exists at `{}`", pointer),
+            PointerError::NotContainer(pointer) => {
+                write!(
+                    f,
+                    "value at `{}` is neither an array nor an object",
+                    pointer
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PointerError {}
+
+/// Escapes `key` as a JSON Pointer token.
+pub(crate) fn escape_token(key: &str) -> Cow<'_, str> {
+    if key.contains(['~', '/']) {
+        Cow::Owned(key.replace('~', "~0").replace('/', "~1"))
+    } else {
+        Cow::Borrowed(key)
+    }
+}
+
+/// Unescaped tokens of `pointer`.
+pub(super) fn tokens(pointer: &str) -> Result<Vec<Cow<'_, str>>, PointerError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let invalid = || PointerError::Invalid(pointer.to_string());
+    let rest = pointer.strip_prefix('/').ok_or_else(invalid)?;
+    rest.split('/')
+        .map(|token| unescape(token).ok_or_else(invalid))
+        .collect()
+}
+
+fn unescape(token: &str) -> Option<Cow<'_, str>> {
+    if !token.contains('~') {
+        return Some(Cow::Borrowed(token));
+    }
+    let mut out = String::with_capacity(token.len());
+    let mut chars = token.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '~' => match chars.next()? {
+                '0' => out.push('~'),
+                '1' => out.push('/'),
+                _ => return None,
+            },
+            c => out.push(c),
+        }
+    }
+    Some(Cow::Owned(out))
+}
+
+pub(super) fn array_index(token: &str) -> Option<usize> {
+    let digits = !token.is_empty() && token.bytes().all(|b| b.is_ascii_digit());
+    if !digits || (token.len() > 1 && token.starts_with('0')) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn child<'a, 'json: 'a, A: JsonLike<'json>>(value: &'a A, token: &str) -> Option<&'a A> {
+    match value.as_array() {
+        Some(arr) => arr.get(array_index(token)?),
+        None => value.as_object()?.get_key(token),
+    }
+}
+
+pub(super) fn child_mut<'a, 'json: 'a, A: JsonLike<'json>>(
+    value: &'a mut A,
+    token: &str,
+) -> Option<&'a mut A> {
+    if value.as_array().is_some() {
+        return value.as_array_mut()?.get_mut(array_index(token)?);
+    }
+    value.as_object_mut()?.get_key_mut(token)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::{JsonPointer, PointerError};
+    use crate::jsonlike::{convert, JsonLike};
+
+    /// The example document of RFC 6901.
+    fn document() -> serde_json::Value {
+        json!({
+            "foo": ["bar", "baz"],
+            "": 0,
+            "a/b": 1,
+            "c%d": 2,
+            "e^f": 3,
+            "g|h": 4,
+            "i\\j": 5,
+            "k\"l": 6,
+            " ": 7,
+            "m~n": 8
+        })
+    }
+
+    fn check_rfc_examples<A>(doc: &A)
+    where
+        A: for<'a> JsonLike<'a> + PartialEq + std::fmt::Debug,
+    {
+        let at = |pointer| doc.pointer(pointer).map(convert::<_, serde_json::Value>);
+        assert_eq!(at(""), Some(document()));
+        assert_eq!(at("/foo"), Some(json!(["bar", "baz"])));
+        assert_eq!(at("/foo/0"), Some(json!("bar")));
+        let examples = [
+            ("/", 0),
+            ("/a~1b", 1),
+            ("/c%d", 2),
+            ("/e^f", 3),
+            ("/g|h", 4),
+            ("/i\\j", 5),
+            ("/k\"l", 6),
+            ("/ ", 7),
+            ("/m~0n", 8),
+        ];
+        for (pointer, expected) in examples {
+            assert_eq!(at(pointer), Some(json!(expected)), "{}", pointer);
+        }
+        for pointer in [
+            "/foo/2", "/foo/-", "/foo/01", "/foo/+1", "/bar", "/m~2n", "foo",
+        ] {
+            assert_eq!(at(pointer), None, "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn test_rfc_examples() {
+        let doc = document();
+        check_rfc_examples(&doc);
+        check_rfc_examples(&convert::<_, ConstValue>(&doc));
+        check_rfc_examples(&convert::<_, Val>(&doc));
+
+        let text = doc.to_string();
+        let borrowed: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(borrowed.pointer("/a~1b").and_then(|v| v.as_u64()), Some(1));
+        assert_eq!(
+            borrowed.pointer("/foo/1").and_then(|v| v.as_str()),
+            Some("baz")
+        );
+    }
+
+    #[test]
+    fn test_pointer_mut() {
+        let mut doc = document();
+        *doc.pointer_mut("/foo/1").unwrap() = json!("qux");
+        *doc.pointer_mut("/m~0n").unwrap() = json!(9);
+        assert_eq!(doc["foo"], json!(["bar", "qux"]));
+        assert_eq!(doc["m~n"], json!(9));
+        assert_eq!(doc.pointer_mut("/foo/-"), None);
+    }
+
+    fn check_set_and_remove<A>(mut doc: A)
+    where
+        A: for<'a> JsonLike<'a> + PartialEq + std::fmt::Debug,
+    {
+        let value = |v: serde_json::Value| convert::<_, A>(&v);
+        assert_eq!(doc.set_pointer("/foo/-", value(json!("x"))), Ok(None));
+        assert_eq!(doc.set_pointer("/foo/3", value(json!("y"))), Ok(None));
+        assert_eq!(
+            doc.set_pointer("/foo/0", value(json!("z"))),
+            Ok(Some(value(json!("bar"))))
+        );
+        assert_eq!(doc.set_pointer("/new~1key", value(json!({}))), Ok(None));
+        assert_eq!(doc.set_pointer("/new~1key/a", value(json!(1))), Ok(None));
+        assert_eq!(
+            doc.set_pointer("/foo/5", value(json!(1))),
+            Err(PointerError::NotFound("/foo/5".to_string()))
+        );
+        assert_eq!(
+            doc.set_pointer("/missing/a", value(json!(1))),
+            Err(PointerError::NotFound("/missing".to_string()))
+        );
+        assert_eq!(
+            doc.set_pointer("/ /a", value(json!(1))),
+            Err(PointerError::NotContainer("/ ".to_string()))
+        );
+        assert_eq!(
+            doc.set_pointer("a", value(json!(1))),
+            Err(PointerError::Invalid("a".to_string()))
+        );
+
+        assert_eq!(doc.remove_pointer("/foo/1"), Some(value(json!("baz"))));
+        assert_eq!(doc.remove_pointer("/foo/-"), None);
+        assert_eq!(doc.remove_pointer("/a~1b"), Some(value(json!(1))));
+        assert_eq!(doc.remove_pointer("/a~1b"), None);
+        assert_eq!(doc.remove_pointer(""), None);
+
+        let expected = json!({
+            "foo": ["z", "x", "y"],
+            "": 0,
+            "c%d": 2,
+            "e^f": 3,
+            "g|h": 4,
+            "i\\j": 5,
+            "k\"l": 6,
+            " ": 7,
+            "m~n": 8,
+            "new/key": {"a": 1}
+        });
+        assert_eq!(convert::<_, serde_json::Value>(&doc), expected);
+        assert_eq!(
+            doc.set_pointer("", value(json!(null))),
+            Ok(Some(value(expected)))
+        );
+    }
+
+    #[test]
+    fn test_set_and_remove() {
+        check_set_and_remove(document());
+        check_set_and_remove(convert::<_, ConstValue>(&document()));
+        check_set_and_remove(convert::<_, Val>(&document()));
+    }
+
+    #[test]
+    fn test_set_and_remove_borrowed() {
+        let text = document().to_string();
+        let mut doc: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        assert!(doc
+            .set_pointer("/a~1b/-", serde_json_borrow::Value::Null)
+            .is_err());
+        doc.set_pointer("/m~0n~1", serde_json_borrow::Value::Bool(true))
+            .unwrap();
+        doc.set_pointer("/foo/-", serde_json_borrow::Value::Null)
+            .unwrap();
+        assert_eq!(doc.remove_pointer("/c%d").and_then(|v| v.as_u64()), Some(2));
+
+        let mut expected = document();
+        expected["m~n/"] = json!(true);
+        expected["foo"] = json!(["bar", "baz", null]);
+        expected.as_object_mut().unwrap().remove("c%d");
+        assert_eq!(convert::<_, serde_json::Value>(&doc), expected);
+    }
+}
@@ -20,11 +20,23 @@ impl<'obj> JsonObjectLike<'obj> for serde_json::Map<String, serde_json::Value> {
         self.get(key)
     }
 
+    fn get_key_mut(&mut self, key: &str) -> Option<&mut serde_json::Value> {
+        self.get_mut(key)
+    }
+
     fn insert_key(&mut self, key: &'obj str, value: Self::Value) {
         self.insert(key.to_owned(), value);
     }
 
-    fn remove_key(&mut self, key: &'obj str) -> Option<Self::Value> {
+    fn insert_key_cow(&mut self, key: Cow<'obj, str>, value: Self::Value) {
+        self.insert(key.into_owned(), value);
+    }
+
+    fn remove_key(&mut self, key: &str) -> Option<Self::Value> {
+        // keeps the order of the remaining keys, see `jq::order`
+        #[cfg(feature = "preserve_order")]
+        return self.shift_remove(key);
+        #[cfg(not(feature = "preserve_order"))]
         self.remove(key)
     }
 
@@ -137,6 +149,11 @@ impl<'json> JsonLike<'json> for serde_json::Value {
         serde_json::Value::Object(map)
     }
 
+    fn obj_cow(pairs: Vec<(Cow<'json, str>, Self)>) -> Self {
+        let map = pairs.into_iter().map(|(k, v)| (k.into_owned(), v));
+        serde_json::Value::Object(map.collect())
+    }
+
     fn array(arr: Vec<Self>) -> Self {
         serde_json::Value::Array(arr)
     }
@@ -145,7 +162,19 @@ impl<'json> JsonLike<'json> for serde_json::Value {
         serde_json::Value::String(s.to_string())
     }
 
+    fn boolean(b: bool) -> Self {
+        Self::Bool(b)
+    }
+
+    fn number_i64(n: i64) -> Self {
+        Self::Number(Number::from(n))
+    }
+
+    fn number_u64(n: u64) -> Self {
+        Self::Number(Number::from(n))
+    }
+
     fn number_f64(n: f64) -> Self {
-        Self::Number(Number::from_f64(n).unwrap())
+        Number::from_f64(n).map_or(Self::Null, Self::Number)
     }
 }
@@ -0,0 +1,385 @@
+//! JSON text for any [`JsonLike`] value.
+//!
+//! The output only depends on the data, not on the backend: integers are
+//! written as integers, floats in their shortest round-trip form (without a
+//! fractional part if they have none, with an exponent from 1e21 on, like
+//! jq does), NaN as `null` and infinities as the largest finite numbers. GraphQL
+//! enums are written as strings and binary values as arrays of bytes, like
+//! [`convert`](super::convert) does.
+use std::fmt::{self, Write};
+use std::io;
+
+use super::canonical::write_number;
+use super::{JsonLike, JsonObjectLike};
+
+/// Writes [`JsonLike`] values as JSON text. The default writes compact JSON.
+///
+/// ```
+/// use serde_json::json;
+/// use tailcall_template::jsonlike::JsonSerializer;
+///
+/// let value = json!({"a": [1, "é"]});
+/// assert_eq!(JsonSerializer::default().to_string(&value), r#"{"a":[1,"é"]}"#);
+/// assert_eq!(
+///     JsonSerializer::default().pretty(2).ascii(true).to_string(&value),
+///     "{\n  \"a\": [\n    1,\n    \"\\u00e9\"\n  ]\n}"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct JsonSerializer {
+    indent: Option<usize>,
+    color: bool,
+    ascii: bool,
+}
+
+// Colors used by jq.
+const NULL: &str = "1;30";
+const SCALAR: &str = "0;39";
+const STRING: &str = "0;32";
+const CONTAINER: &str = "1;39";
+const KEY: &str = "34;1";
+
+impl JsonSerializer {
+    /// Puts every array item and object entry on its own line, indented by
+    /// `indent` spaces per level.
+    pub fn pretty(self, indent: usize) -> Self {
+        Self {
+            indent: Some(indent),
+            ..self
+        }
+    }
+
+    /// Highlights values with ANSI escape codes, using jq's colors.
+    pub fn color(self, color: bool) -> Self {
+        Self { color, ..self }
+    }
+
+    /// Escapes every non-ASCII character in strings as `\uXXXX`.
+    pub fn ascii(self, ascii: bool) -> Self {
+        Self { ascii, ..self }
+    }
+
+    pub fn to_string<'a, A: JsonLike<'a>>(&self, value: &'a A) -> String {
+        let mut out = String::new();
+        // Writing to a `String` never fails.
+        let _ = self.write(&mut out, value);
+        out
+    }
+
+    pub fn write<'a, A, W>(&self, out: &mut W, value: &'a A) -> fmt::Result
+    where
+        A: JsonLike<'a>,
+        W: Write + ?Sized,
+    {
+        self.write_value(out, value, 0)
+    }
+
+    pub fn write_io<'a, A, W>(&self, out: &mut W, value: &'a A) -> io::Result<()>
+    where
+        A: JsonLike<'a>,
+        W: io::Write + ?Sized,
+    {
+        let mut adapter = IoAdapter {
+            inner: out,
+            error: None,
+        };
+        match self.write(&mut adapter, value) {
+            Ok(()) => Ok(()),
+            Err(_) => Err(adapter
+                .error
+                .unwrap_or_else(|| io::Error::other("formatter error"))),
+        }
+    }
+
+    /// Writes `s` as a quoted JSON string.
+    pub fn write_str<W: Write + ?Sized>(&self, out: &mut W, s: &str) -> fmt::Result {
+        self.colored(out, STRING, |out| self.write_quoted(out, s))
+    }
+
+    fn write_value<'a, A, W>(&self, out: &mut W, value: &'a A, depth: usize) -> fmt::Result
+    where
+        A: JsonLike<'a>,
+        W: Write + ?Sized,
+    {
+        if value.is_null() {
+            self.colored(out, NULL, |out| out.write_str("null"))
+        } else if let Some(b) = value.as_bool() {
+            self.colored(out, SCALAR, |out| {
+                out.write_str(if b { "true" } else { "false" })
+            })
+        } else if let Some(s) = value.as_str() {
+            self.write_str(out, s)
+        } else if let Some(arr) = value.as_array() {
+            let items = arr.iter().map(|item| (None, item));
+            self.write_seq(out, '[', ']', items, depth, Self::write_value)
+        } else if let Some(obj) = value.as_object() {
+            let entries = obj.iter().map(|(key, item)| (Some(key), item));
+            self.write_seq(out, '{', '}', entries, depth, Self::write_value)
+        } else if let Some(n) = value.as_i64() {
+            self.colored(out, SCALAR, |out| write!(out, "{}", n))
+        } else if let Some(n) = value.as_u64() {
+            self.colored(out, SCALAR, |out| write!(out, "{}", n))
+        } else if let Some(n) = value.as_f64() {
+            self.colored(out, SCALAR, |out| write_f64(out, n))
+        } else if let Some(name) = value.as_enum() {
+            self.write_str(out, name)
+        } else if let Some(bytes) = value.as_binary() {
+            let bytes = bytes.iter().map(|b| (None, b));
+            self.write_seq(out, '[', ']', bytes, depth, |this, out, b, _| {
+                this.colored(out, SCALAR, |out| write!(out, "{}", b))
+            })
+        } else {
+            self.colored(out, NULL, |out| out.write_str("null"))
+        }
+    }
+
+    /// Writes an array, or an object if the items have keys.
+    fn write_seq<'k, T, W>(
+        &self,
+        out: &mut W,
+        open: char,
+        close: char,
+        items: impl Iterator<Item = (Option<&'k str>, T)>,
+        depth: usize,
+        write_item: impl Fn(&Self, &mut W, T, usize) -> fmt::Result,
+    ) -> fmt::Result
+    where
+        W: Write + ?Sized,
+    {
+        self.punct(out, open)?;
+        let mut empty = true;
+        for (key, item) in items {
+            if !empty {
+                self.punct(out, ',')?;
+            }
+            empty = false;
+            self.newline(out, depth + 1)?;
+            if let Some(key) = key {
+                self.colored(out, KEY, |out| self.write_quoted(out, key))?;
+                self.punct(out, ':')?;
+                if self.indent.is_some() {
+                    out.write_char(' ')?;
+                }
+            }
+            write_item(self, out, item, depth + 1)?;
+        }
+        if !empty {
+            self.newline(out, depth)?;
+        }
+        self.punct(out, close)
+    }
+
+    fn newline<W: Write + ?Sized>(&self, out: &mut W, depth: usize) -> fmt::Result {
+        match self.indent {
+            Some(indent) => write!(out, "\n{:1$}", "", indent * depth),
+            None => Ok(()),
+        }
+    }
+
+    fn punct<W: Write + ?Sized>(&self, out: &mut W, c: char) -> fmt::Result {
+        self.colored(out, CONTAINER, |out| out.write_char(c))
+    }
+
+    fn colored<W, F>(&self, out: &mut W, color: &str, f: F) -> fmt::Result
+    where
+        W: Write + ?Sized,
+        F: FnOnce(&mut W) -> fmt::Result,
+    {
+        if !self.color {
+            return f(out);
+        }
+        write!(out, "\x1b[{}m", color)?;
+        f(out)?;
+        out.write_str("\x1b[0m")
+    }
+
+    fn write_quoted<W: Write + ?Sized>(&self, out: &mut W, s: &str) -> fmt::Result {
+        out.write_char('"')?;
+        for c in s.chars() {
+            match c {
+                '"' => out.write_str("\\\"")?,
+                '\\' => out.write_str("\\\\")?,
+                '\n' => out.write_str("\\n")?,
+                '\r' => out.write_str("\\r")?,
+                '\t' => out.write_str("\\t")?,
+                '\x08' => out.write_str("\\b")?,
+                '\x0C' => out.write_str("\\f")?,
+                c if c < ' ' || c == '\x7f' => write!(out, "\\u{:04x}", c as u32)?,
+                c if self.ascii && !c.is_ascii() => {
+                    for unit in c.encode_utf16(&mut [0; 2]) {
+                        write!(out, "\\u{:04x}", unit)?;
+                    }
+                }
+                c => out.write_char(c)?,
+            }
+        }
+        out.write_char('"')
+    }
+}
+
+fn write_f64<W: Write + ?Sized>(out: &mut W, n: f64) -> fmt::Result {
+    if n.is_nan() {
+        return out.write_str("null");
+    }
+    // jq clamps infinities to the largest finite numbers
+    let n = if n.is_infinite() {
+        f64::MAX.copysign(n)
+    } else {
+        n
+    };
+    if n == 0.0 && n.is_sign_negative() {
+        return out.write_str("-0");
+    }
+    let mut number = String::new();
+    write_number(&mut number, n);
+    out.write_str(&number)
+}
+
+/// Forwards to an `io::Write`, keeping the error that `fmt::Error` can't hold.
+struct IoAdapter<'w, W: ?Sized> {
+    inner: &'w mut W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write + ?Sized> Write for IoAdapter<'_, W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|err| {
+            self.error = Some(err);
+            fmt::Error
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::JsonSerializer;
+    use crate::jsonlike::convert;
+
+    fn sample() -> serde_json::Value {
+        json!({"a": [1, 2.5, 3.0, "x\"\n", true, null], "b": {}, "c": [], "é": u64::MAX})
+    }
+
+    /// Output of `serializer` for the sample on every backend, checking that
+    /// they are identical.
+    fn all_backends(serializer: JsonSerializer) -> String {
+        let value = sample();
+        let expected = serializer.to_string(&value);
+        let text = value.to_string();
+        let borrowed: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(serializer.to_string(&borrowed), expected);
+        assert_eq!(
+            serializer.to_string(&convert::<_, ConstValue>(&value)),
+            expected
+        );
+        assert_eq!(serializer.to_string(&convert::<_, Val>(&value)), expected);
+        expected
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(
+            all_backends(JsonSerializer::default()),
+            r#"{"a":[1,2.5,3,"x\"\n",true,null],"b":{},"c":[],"é":18446744073709551615}"#
+        );
+    }
+
+    #[test]
+    fn test_pretty() {
+        let expected = r#"{
+    "a": [
+        1,
+        2.5,
+        3,
+        "x\"\n",
+        true,
+        null
+    ],
+    "b": {},
+    "c": [],
+    "é": 18446744073709551615
+}"#;
+        assert_eq!(all_backends(JsonSerializer::default().pretty(4)), expected);
+    }
+
+    #[test]
+    fn test_ascii() {
+        assert_eq!(
+            all_backends(JsonSerializer::default().ascii(true)),
+            r#"{"a":[1,2.5,3,"x\"\n",true,null],"b":{},"c":[],"\u00e9":18446744073709551615}"#
+        );
+        let serializer = JsonSerializer::default().ascii(true);
+        assert_eq!(
+            serializer.to_string(&json!("😀\u{7f}")),
+            r#""\ud83d\ude00\u007f""#
+        );
+    }
+
+    #[test]
+    fn test_color() {
+        let serializer = JsonSerializer::default().color(true);
+        all_backends(serializer);
+        assert_eq!(
+            serializer.to_string(&json!({"a": [null, "s", 1]})),
+            "\x1b[1;39m{\x1b[0m\x1b[34;1m\"a\"\x1b[0m\x1b[1;39m:\x1b[0m\
+             \x1b[1;39m[\x1b[0m\x1b[1;30mnull\x1b[0m\x1b[1;39m,\x1b[0m\
+             \x1b[0;32m\"s\"\x1b[0m\x1b[1;39m,\x1b[0m\x1b[0;39m1\x1b[0m\
+             \x1b[1;39m]\x1b[0m\x1b[1;39m}\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_floats() {
+        let serializer = JsonSerializer::default();
+        let value = json!([1e300, -0.0, 0.0, 1e20, 1e21, 0.1, 1.5e-7, -2.0, 123456.789]);
+        assert_eq!(
+            serializer.to_string(&value),
+            "[1e+300,-0,0,100000000000000000000,1e+21,0.1,1.5e-7,-2,123456.789]"
+        );
+        assert_eq!(
+            serializer.to_string(&convert::<_, ConstValue>(&value)),
+            serializer.to_string(&value)
+        );
+    }
+
+    #[test]
+    fn test_non_json_values() {
+        let serializer = JsonSerializer::default();
+        let value = Val::Arr(Rc::new(vec![
+            Val::Float(f64::NAN),
+            Val::Float(f64::NEG_INFINITY),
+        ]));
+        assert_eq!(
+            serializer.to_string(&value),
+            "[null,-1.7976931348623157e+308]"
+        );
+
+        let value = ConstValue::List(vec![
+            ConstValue::Enum(async_graphql::Name::new("ADMIN")),
+            ConstValue::Binary(vec![0, 255].into()),
+        ]);
+        assert_eq!(serializer.to_string(&value), r#"["ADMIN",[0,255]]"#);
+    }
+
+    #[test]
+    fn test_write_io() {
+        let mut out = Vec::new();
+        JsonSerializer::default()
+            .pretty(1)
+            .write_io(&mut out, &json!([1]))
+            .unwrap();
+        assert_eq!(out, b"[\n 1\n]");
+
+        let mut full = [0u8; 3];
+        let err = JsonSerializer::default()
+            .write_io(&mut full.as_mut_slice(), &json!([1, 2]))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+    }
+}
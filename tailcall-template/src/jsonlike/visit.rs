@@ -0,0 +1,410 @@
+//! Depth-first walks over [`JsonLike`] values with the path of each visited
+//! value, with [`walk`] for borrowed values and [`walk_mut`] for mutable ones.
+use std::borrow::Cow;
+
+use super::pointer::escape_token;
+use super::{JsonLike, JsonLikeOwned, JsonObjectLike};
+
+/// Step from a value to one of its children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathStep<'a> {
+    Key(Cow<'a, str>),
+    Index(usize),
+}
+
+/// The JSON Pointer to the value at `path`.
+pub fn to_pointer(path: &[PathStep<'_>]) -> String {
+    let mut out = String::new();
+    for step in path {
+        out.push('/');
+        match step {
+            PathStep::Key(key) => out.push_str(&escape_token(key)),
+            PathStep::Index(index) => out.push_str(&index.to_string()),
+        }
+    }
+    out
+}
+
+/// What a walk does after a hook returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Control {
+    /// Go on with the walk.
+    #[default]
+    Continue,
+    /// Don't visit the children of the object or array being entered. Same as
+    /// [`Continue`](Control::Continue) in other hooks.
+    Skip,
+    /// End the walk, without calling any other hook.
+    Stop,
+}
+
+/// Hooks called by [`walk`]. Objects and arrays are entered before their
+/// children and left after them, even if they are skipped.
+pub trait Visitor<'json, J: JsonLike<'json>> {
+    fn enter_object(&mut self, _path: &[PathStep<'json>], _value: &'json J) -> Control {
+        Control::Continue
+    }
+    fn leave_object(&mut self, _path: &[PathStep<'json>], _value: &'json J) -> Control {
+        Control::Continue
+    }
+    fn enter_array(&mut self, _path: &[PathStep<'json>], _value: &'json J) -> Control {
+        Control::Continue
+    }
+    fn leave_array(&mut self, _path: &[PathStep<'json>], _value: &'json J) -> Control {
+        Control::Continue
+    }
+    /// Called for every value that is neither an object nor an array.
+    fn visit_scalar(&mut self, _path: &[PathStep<'json>], _value: &'json J) -> Control {
+        Control::Continue
+    }
+}
+
+/// Hooks called by [`walk_mut`]. Values may be changed or replaced in any
+/// hook; the children walked after `enter_object` and `enter_array` are the
+/// ones of the value as left by the hook. If one of these hooks replaces the
+/// value with one of another kind, the walk goes on after it, without walking
+/// its children or calling `leave_object` or `leave_array` on it.
+pub trait VisitorMut<J: JsonLikeOwned> {
+    fn enter_object(&mut self, _path: &[PathStep<'_>], _value: &mut J) -> Control {
+        Control::Continue
+    }
+    fn leave_object(&mut self, _path: &[PathStep<'_>], _value: &mut J) -> Control {
+        Control::Continue
+    }
+    fn enter_array(&mut self, _path: &[PathStep<'_>], _value: &mut J) -> Control {
+        Control::Continue
+    }
+    fn leave_array(&mut self, _path: &[PathStep<'_>], _value: &mut J) -> Control {
+        Control::Continue
+    }
+    /// Called for every value that is neither an object nor an array.
+    fn visit_scalar(&mut self, _path: &[PathStep<'_>], _value: &mut J) -> Control {
+        Control::Continue
+    }
+}
+
+/// Walks `value` depth-first, calling the hooks of `visitor`. Returns
+/// [`Control::Stop`] if a hook stopped the walk and
+/// [`Control::Continue`] otherwise.
+pub fn walk<'json, J, V>(value: &'json J, visitor: &mut V) -> Control
+where
+    J: JsonLike<'json>,
+    V: Visitor<'json, J> + ?Sized,
+{
+    walk_value(value, &mut vec![], visitor)
+}
+
+fn walk_value<'json, J, V>(
+    value: &'json J,
+    path: &mut Vec<PathStep<'json>>,
+    visitor: &mut V,
+) -> Control
+where
+    J: JsonLike<'json>,
+    V: Visitor<'json, J> + ?Sized,
+{
+    if let Some(obj) = value.as_object() {
+        match visitor.enter_object(path, value) {
+            Control::Stop => return Control::Stop,
+            Control::Skip => {}
+            Control::Continue => {
+                for (key, child) in obj.iter() {
+                    path.push(PathStep::Key(Cow::Borrowed(key)));
+                    let control = walk_value(child, path, visitor);
+                    path.pop();
+                    if control == Control::Stop {
+                        return Control::Stop;
+                    }
+                }
+            }
+        }
+        stop_or_continue(visitor.leave_object(path, value))
+    } else if let Some(arr) = value.as_array() {
+        match visitor.enter_array(path, value) {
+            Control::Stop => return Control::Stop,
+            Control::Skip => {}
+            Control::Continue => {
+                for (index, child) in arr.iter().enumerate() {
+                    path.push(PathStep::Index(index));
+                    let control = walk_value(child, path, visitor);
+                    path.pop();
+                    if control == Control::Stop {
+                        return Control::Stop;
+                    }
+                }
+            }
+        }
+        stop_or_continue(visitor.leave_array(path, value))
+    } else {
+        stop_or_continue(visitor.visit_scalar(path, value))
+    }
+}
+
+/// Like [`walk`], for a mutable value.
+pub fn walk_mut<J, V>(value: &mut J, visitor: &mut V) -> Control
+where
+    J: JsonLikeOwned,
+    V: VisitorMut<J> + ?Sized,
+{
+    walk_value_mut(value, &mut vec![], visitor)
+}
+
+fn walk_value_mut<J, V>(
+    value: &mut J,
+    path: &mut Vec<PathStep<'static>>,
+    visitor: &mut V,
+) -> Control
+where
+    J: JsonLikeOwned,
+    V: VisitorMut<J> + ?Sized,
+{
+    if value.as_object().is_some() {
+        match visitor.enter_object(path, value) {
+            Control::Stop => return Control::Stop,
+            _ if value.as_object().is_none() => return Control::Continue,
+            Control::Skip => {}
+            Control::Continue => {
+                let keys: Vec<String> = value
+                    .as_object()
+                    .map(|obj| obj.iter().map(|(key, _)| key.to_string()).collect())
+                    .unwrap_or_default();
+                for key in keys {
+                    let Some(child) = value.as_object_mut().and_then(|obj| obj.get_key_mut(&key))
+                    else {
+                        continue;
+                    };
+                    path.push(PathStep::Key(Cow::Owned(key)));
+                    let control = walk_value_mut(child, path, visitor);
+                    path.pop();
+                    if control == Control::Stop {
+                        return Control::Stop;
+                    }
+                }
+            }
+        }
+        stop_or_continue(visitor.leave_object(path, value))
+    } else if value.as_array().is_some() {
+        match visitor.enter_array(path, value) {
+            Control::Stop => return Control::Stop,
+            _ if value.as_array().is_none() => return Control::Continue,
+            Control::Skip => {}
+            Control::Continue => {
+                let mut index = 0;
+                while let Some(child) = value.as_array_mut().and_then(|arr| arr.get_mut(index)) {
+                    path.push(PathStep::Index(index));
+                    let control = walk_value_mut(child, path, visitor);
+                    path.pop();
+                    if control == Control::Stop {
+                        return Control::Stop;
+                    }
+                    index += 1;
+                }
+            }
+        }
+        stop_or_continue(visitor.leave_array(path, value))
+    } else {
+        stop_or_continue(visitor.visit_scalar(path, value))
+    }
+}
+
+fn stop_or_continue(control: Control) -> Control {
+    match control {
+        Control::Stop => Control::Stop,
+        Control::Continue | Control::Skip => Control::Continue,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use jaq_json::Val;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use super::{to_pointer, walk, walk_mut, Control, PathStep, Visitor, VisitorMut};
+    use crate::jsonlike::{convert, JsonLike, JsonLikeOwned, JsonObjectLike};
+
+    #[derive(Default)]
+    struct Trace {
+        events: Vec<String>,
+        skip: Option<String>,
+        stop: Option<String>,
+    }
+
+    impl Trace {
+        fn control(&mut self, event: &str, path: &[PathStep<'_>]) -> Control {
+            let pointer = to_pointer(path);
+            self.events.push(format!("{} {}", event, pointer));
+            if self.stop.as_deref() == Some(pointer.as_str()) {
+                Control::Stop
+            } else if self.skip.as_deref() == Some(pointer.as_str()) {
+                Control::Skip
+            } else {
+                Control::Continue
+            }
+        }
+    }
+
+    impl<'json, J: JsonLike<'json>> Visitor<'json, J> for Trace {
+        fn enter_object(&mut self, path: &[PathStep<'json>], _: &'json J) -> Control {
+            self.control("enter_object", path)
+        }
+        fn leave_object(&mut self, path: &[PathStep<'json>], _: &'json J) -> Control {
+            self.control("leave_object", path)
+        }
+        fn enter_array(&mut self, path: &[PathStep<'json>], _: &'json J) -> Control {
+            self.control("enter_array", path)
+        }
+        fn leave_array(&mut self, path: &[PathStep<'json>], _: &'json J) -> Control {
+            self.control("leave_array", path)
+        }
+        fn visit_scalar(&mut self, path: &[PathStep<'json>], _: &'json J) -> Control {
+            self.control("visit_scalar", path)
+        }
+    }
+
+    fn check_walk<A: JsonLikeOwned>(input: &Value, trace: &mut Trace) -> Control {
+        let value: A = convert(input);
+        walk(&value, trace)
+    }
+
+    #[test]
+    fn test_walk() {
+        let input = json!({"a": [1, {"b/c": null}], "d": "x"});
+        let expected = [
+            "enter_object ",
+            "enter_array /a",
+            "visit_scalar /a/0",
+            "enter_object /a/1",
+            "visit_scalar /a/1/b~1c",
+            "leave_object /a/1",
+            "leave_array /a",
+            "visit_scalar /d",
+            "leave_object ",
+        ];
+        let mut trace = Trace::default();
+        assert_eq!(check_walk::<Value>(&input, &mut trace), Control::Continue);
+        assert_eq!(trace.events, expected);
+        let mut trace = Trace::default();
+        check_walk::<ConstValue>(&input, &mut trace);
+        assert_eq!(trace.events, expected);
+        let mut trace = Trace::default();
+        check_walk::<Val>(&input, &mut trace);
+        assert_eq!(trace.events, expected);
+
+        let mut trace = Trace {
+            skip: Some("/a".into()),
+            ..Default::default()
+        };
+        check_walk::<Value>(&input, &mut trace);
+        assert_eq!(
+            trace.events,
+            [
+                "enter_object ",
+                "enter_array /a",
+                "leave_array /a",
+                "visit_scalar /d",
+                "leave_object "
+            ]
+        );
+
+        let mut trace = Trace {
+            stop: Some("/a/0".into()),
+            ..Default::default()
+        };
+        assert_eq!(check_walk::<Value>(&input, &mut trace), Control::Stop);
+        assert_eq!(
+            trace.events,
+            ["enter_object ", "enter_array /a", "visit_scalar /a/0"]
+        );
+    }
+
+    struct Redact;
+
+    impl<J: JsonLikeOwned> VisitorMut<J> for Redact {
+        fn enter_object(&mut self, path: &[PathStep<'_>], value: &mut J) -> Control {
+            if let Some(PathStep::Key(key)) = path.last() {
+                if key == "secret" {
+                    *value = J::string("***".into());
+                }
+            }
+            Control::Continue
+        }
+        fn visit_scalar(&mut self, path: &[PathStep<'_>], value: &mut J) -> Control {
+            if let Some(PathStep::Key(key)) = path.last() {
+                if key == "secret" {
+                    *value = J::string("***".into());
+                }
+            }
+            Control::Continue
+        }
+    }
+
+    /// Swaps objects and arrays at `/a`, and traces the other hooks.
+    #[derive(Default)]
+    struct Swap(Trace);
+
+    impl<J: JsonLikeOwned> VisitorMut<J> for Swap {
+        fn enter_object(&mut self, path: &[PathStep<'_>], value: &mut J) -> Control {
+            if to_pointer(path) == "/a" {
+                *value = J::array(vec![J::null()]);
+                return Control::Continue;
+            }
+            self.0.control("enter_object", path)
+        }
+        fn leave_object(&mut self, path: &[PathStep<'_>], _: &mut J) -> Control {
+            self.0.control("leave_object", path)
+        }
+        fn enter_array(&mut self, path: &[PathStep<'_>], value: &mut J) -> Control {
+            if to_pointer(path) == "/a" {
+                *value = J::object(J::JsonObject::new());
+                return Control::Continue;
+            }
+            self.0.control("enter_array", path)
+        }
+        fn leave_array(&mut self, path: &[PathStep<'_>], _: &mut J) -> Control {
+            self.0.control("leave_array", path)
+        }
+        fn visit_scalar(&mut self, path: &[PathStep<'_>], _: &mut J) -> Control {
+            self.0.control("visit_scalar", path)
+        }
+    }
+
+    #[test]
+    fn test_walk_mut_kind_change() {
+        for (input, expected) in [
+            (json!({"a": {"b": 1}, "c": 2}), json!({"a": [null], "c": 2})),
+            (json!({"a": [1], "c": 2}), json!({"a": {}, "c": 2})),
+        ] {
+            let mut value = input;
+            let mut swap = Swap::default();
+            assert_eq!(walk_mut(&mut value, &mut swap), Control::Continue);
+            assert_eq!(value, expected);
+            assert_eq!(
+                swap.0.events,
+                ["enter_object ", "visit_scalar /c", "leave_object "]
+            );
+        }
+    }
+
+    fn check_walk_mut<A: JsonLikeOwned>(input: &Value) -> Value {
+        let mut value: A = convert(input);
+        walk_mut(&mut value, &mut Redact);
+        convert(&value)
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        let input = json!({
+            "users": [{"name": "ann", "secret": "a"}, {"secret": {"pin": 1}}],
+            "secrets": [1],
+        });
+        let expected = json!({
+            "users": [{"name": "ann", "secret": "***"}, {"secret": "***"}],
+            "secrets": [1],
+        });
+        assert_eq!(check_walk_mut::<Value>(&input), expected);
+        assert_eq!(check_walk_mut::<ConstValue>(&input), expected);
+        assert_eq!(check_walk_mut::<Val>(&input), expected);
+    }
+}
@@ -0,0 +1,605 @@
+use std::rc::Rc;
+
+use regex::Regex;
+
+use super::parse::to_regex;
+use super::query::*;
+use crate::jsonlike::{json_eq, JsonLike, JsonObjectLike};
+
+/// Path of a node while evaluating, sharing its prefix with its parent.
+#[derive(Clone, Default)]
+pub(super) struct Trail<'a>(Option<Rc<(Trail<'a>, Step<'a>)>>);
+
+#[derive(Clone)]
+enum Step<'a> {
+    Name(&'a str),
+    Index(usize),
+}
+
+impl Trail<'_> {
+    pub fn to_path(&self) -> NormalizedPath {
+        let mut elements = Vec::new();
+        let mut trail = self;
+        while let Some(node) = &trail.0 {
+            elements.push(match node.1 {
+                Step::Name(name) => PathElement::Name(name.to_string()),
+                Step::Index(index) => PathElement::Index(index),
+            });
+            trail = &node.0;
+        }
+        elements.reverse();
+        NormalizedPath(elements)
+    }
+}
+
+type Nodes<'a, 'json, A> = Vec<(Trail<'a>, &'json A)>;
+
+/// Nodes selected by `query`, with their paths if `track` is set.
+pub(super) fn query<'a, 'json: 'a, A: JsonLike<'json>>(
+    query: &'a Query,
+    root: &'json A,
+    track: bool,
+) -> Nodes<'a, 'json, A> {
+    Eval { root, track }.run(query, root)
+}
+
+/// A value compared in a filter.
+enum Value<'a, 'json, A> {
+    /// The result of a query that selected nothing.
+    Nothing,
+    Node(&'json A),
+    Literal(&'a Literal),
+    Number(f64),
+}
+
+/// [`Value`]s that have an order: numbers and strings.
+enum Ordered<'a> {
+    Number(f64),
+    String(&'a str),
+}
+
+struct Eval<'json, A> {
+    root: &'json A,
+    track: bool,
+}
+
+impl<'json, A: JsonLike<'json>> Eval<'json, A> {
+    fn run<'a>(&self, query: &'a Query, current: &'json A) -> Nodes<'a, 'json, A>
+    where
+        'json: 'a,
+    {
+        let start = if query.relative { current } else { self.root };
+        let mut nodes = vec![(Trail::default(), start)];
+        for segment in &query.segments {
+            let mut next = Vec::new();
+            for (trail, node) in &nodes {
+                match segment {
+                    Segment::Child(selectors) => {
+                        for selector in selectors {
+                            self.select(selector, trail, node, &mut next);
+                        }
+                    }
+                    Segment::Descendant(selectors) => {
+                        self.descend(selectors, trail, node, &mut next)
+                    }
+                }
+            }
+            nodes = next;
+        }
+        nodes
+    }
+
+    /// Applies `selectors` to `node` and all its descendants, parents first.
+    fn descend<'a>(
+        &self,
+        selectors: &'a [Selector],
+        trail: &Trail<'a>,
+        node: &'json A,
+        out: &mut Nodes<'a, 'json, A>,
+    ) where
+        'json: 'a,
+    {
+        for selector in selectors {
+            self.select(selector, trail, node, out);
+        }
+        self.for_each_child(trail, node, |trail, child| {
+            self.descend(selectors, &trail, child, out)
+        });
+    }
+
+    fn select<'a>(
+        &self,
+        selector: &'a Selector,
+        trail: &Trail<'a>,
+        node: &'json A,
+        out: &mut Nodes<'a, 'json, A>,
+    ) where
+        'json: 'a,
+    {
+        match selector {
+            Selector::Name(name) => {
+                if let Some(value) = node.as_object().and_then(|obj| obj.get_key(name)) {
+                    out.push((self.child(trail, Step::Name(name)), value));
+                }
+            }
+            Selector::Wildcard => self.for_each_child(trail, node, |trail, child| {
+                out.push((trail, child));
+            }),
+            Selector::Index(index) => {
+                let Some(arr) = node.as_array() else {
+                    return;
+                };
+                let index = match *index {
+                    index if index < 0 => arr.len() as i64 + index,
+                    index => index,
+                };
+                if let Some(value) = usize::try_from(index).ok().and_then(|i| arr.get(i)) {
+                    out.push((self.child(trail, Step::Index(index as usize)), value));
+                }
+            }
+            Selector::Slice { start, end, step } => {
+                let Some(arr) = node.as_array() else {
+                    return;
+                };
+                for index in slice(arr.len(), *start, *end, *step) {
+                    out.push((self.child(trail, Step::Index(index)), &arr[index]));
+                }
+            }
+            Selector::Filter(expr) => self.for_each_child(trail, node, |trail, child| {
+                if self.test(expr, child) {
+                    out.push((trail, child));
+                }
+            }),
+        }
+    }
+
+    fn for_each_child<'a>(
+        &self,
+        trail: &Trail<'a>,
+        node: &'json A,
+        mut f: impl FnMut(Trail<'a>, &'json A),
+    ) where
+        'json: 'a,
+    {
+        if let Some(arr) = node.as_array() {
+            for (index, item) in arr.iter().enumerate() {
+                f(self.child(trail, Step::Index(index)), item);
+            }
+        } else if let Some(obj) = node.as_object() {
+            for (key, value) in obj.iter() {
+                f(self.child(trail, Step::Name(key)), value);
+            }
+        }
+    }
+
+    fn child<'a>(&self, trail: &Trail<'a>, step: Step<'a>) -> Trail<'a> {
+        match self.track {
+            true => Trail(Some(Rc::new((trail.clone(), step)))),
+            false => Trail(None),
+        }
+    }
+
+    /// Result of a logical expression, with `current` as `@`.
+    fn test(&self, expr: &Expr, current: &'json A) -> bool {
+        match expr {
+            Expr::Or(exprs) => exprs.iter().any(|expr| self.test(expr, current)),
+            Expr::And(exprs) => exprs.iter().all(|expr| self.test(expr, current)),
+            Expr::Not(expr) => !self.test(expr, current),
+            Expr::Exists(query) => !self.untracked().run(query, current).is_empty(),
+            Expr::Test(function) => self.matches(function, current),
+            Expr::Compare(left, op, right) => {
+                let left = self.value(left, current);
+                let right = self.value(right, current);
+                match op {
+                    CompareOp::Eq => equal(&left, &right),
+                    CompareOp::Ne => !equal(&left, &right),
+                    CompareOp::Lt => less(&left, &right),
+                    CompareOp::Le => less(&left, &right) || equal(&left, &right),
+                    CompareOp::Gt => less(&right, &left),
+                    CompareOp::Ge => less(&right, &left) || equal(&left, &right),
+                }
+            }
+        }
+    }
+
+    fn untracked(&self) -> Eval<'json, A> {
+        Eval {
+            root: self.root,
+            track: false,
+        }
+    }
+
+    fn value<'a>(&self, comparable: &'a Comparable, current: &'json A) -> Value<'a, 'json, A> {
+        match comparable {
+            Comparable::Literal(literal) => Value::Literal(literal),
+            Comparable::Query(query) => match self.untracked().run(query, current).first() {
+                Some((_, node)) => Value::Node(node),
+                None => Value::Nothing,
+            },
+            Comparable::Function(function) => match function.as_ref() {
+                Function::Length(arg) => {
+                    let value = self.value(arg, current);
+                    let length = match value {
+                        Value::Node(node) => node
+                            .as_str()
+                            .map(|s| s.chars().count())
+                            .or_else(|| node.as_array().map(|arr| arr.len()))
+                            .or_else(|| node.as_object().map(|obj| obj.iter().count())),
+                        Value::Literal(Literal::String(s)) => Some(s.chars().count()),
+                        _ => None,
+                    };
+                    length.map_or(Value::Nothing, |n| Value::Number(n as f64))
+                }
+                Function::Count(query) => {
+                    Value::Number(self.untracked().run(query, current).len() as f64)
+                }
+                Function::Value(query) => match self.untracked().run(query, current).as_slice() {
+                    [(_, node)] => Value::Node(node),
+                    _ => Value::Nothing,
+                },
+                Function::Match { .. } => Value::Nothing,
+            },
+        }
+    }
+
+    /// Result of `match` or `search`.
+    fn matches(&self, function: &Function, current: &'json A) -> bool {
+        let Function::Match {
+            full,
+            value,
+            pattern,
+        } = function
+        else {
+            return false;
+        };
+        let value = self.value(value, current);
+        let Some(text) = as_str(&value) else {
+            return false;
+        };
+        match pattern {
+            Pattern::Compiled(regex) => regex.is_match(text),
+            Pattern::Invalid => false,
+            Pattern::Dynamic(pattern) => {
+                let pattern = self.value(pattern, current);
+                as_str(&pattern)
+                    .and_then(|pattern| Regex::new(&to_regex(pattern, *full)).ok())
+                    .is_some_and(|regex| regex.is_match(text))
+            }
+        }
+    }
+}
+
+fn as_str<'a, 'json, A: JsonLike<'json>>(value: &'a Value<'_, 'json, A>) -> Option<&'a str> {
+    match value {
+        Value::Node(node) => node.as_str(),
+        Value::Literal(Literal::String(s)) => Some(s),
+        _ => None,
+    }
+}
+
+fn ordered<'a, 'json, A: JsonLike<'json>>(value: &'a Value<'_, 'json, A>) -> Option<Ordered<'a>> {
+    match value {
+        Value::Number(n) | Value::Literal(Literal::Number(n)) => Some(Ordered::Number(*n)),
+        Value::Literal(Literal::String(s)) => Some(Ordered::String(s)),
+        Value::Node(node) if node.is_null() || node.as_bool().is_some() => None,
+        Value::Node(node) => match node.as_str().or_else(|| node.as_enum()) {
+            Some(s) => Some(Ordered::String(s)),
+            None => node.as_f64().map(Ordered::Number),
+        },
+        _ => None,
+    }
+}
+
+fn equal<'json, A: JsonLike<'json>>(
+    left: &Value<'_, 'json, A>,
+    right: &Value<'_, 'json, A>,
+) -> bool {
+    match (left, right) {
+        (Value::Nothing, Value::Nothing) => true,
+        (Value::Nothing, _) | (_, Value::Nothing) => false,
+        (Value::Node(left), Value::Node(right)) => json_eq(*left, *right),
+        (Value::Node(node), value) | (value, Value::Node(node)) => json_eq(*node, &to_json(value)),
+        (left, right) => json_eq(&to_json(left), &to_json(right)),
+    }
+}
+
+/// A value that isn't a node, as JSON.
+fn to_json<'json, A: JsonLike<'json>>(value: &Value<'_, 'json, A>) -> serde_json::Value {
+    match value {
+        Value::Literal(Literal::Bool(b)) => serde_json::Value::Bool(*b),
+        Value::Literal(Literal::Number(n)) | Value::Number(n) => JsonLike::number_f64(*n),
+        Value::Literal(Literal::String(s)) => serde_json::Value::String(s.clone()),
+        Value::Literal(Literal::Null) | Value::Nothing | Value::Node(_) => serde_json::Value::Null,
+    }
+}
+
+fn less<'json, A: JsonLike<'json>>(
+    left: &Value<'_, 'json, A>,
+    right: &Value<'_, 'json, A>,
+) -> bool {
+    match (ordered(left), ordered(right)) {
+        (Some(Ordered::Number(left)), Some(Ordered::Number(right))) => left < right,
+        (Some(Ordered::String(left)), Some(Ordered::String(right))) => left < right,
+        _ => false,
+    }
+}
+
+/// Indexes selected by a slice, in order.
+fn slice(len: usize, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Vec<usize> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    let normalize = |index: i64| if index >= 0 { index } else { len + index };
+    let mut indexes = Vec::new();
+    if step > 0 {
+        let lower = normalize(start.unwrap_or(0)).clamp(0, len);
+        let upper = normalize(end.unwrap_or(len)).clamp(0, len);
+        let mut index = lower;
+        while index < upper {
+            indexes.push(index as usize);
+            index += step;
+        }
+    } else if step < 0 {
+        let upper = normalize(start.unwrap_or(len - 1)).clamp(-1, len - 1);
+        let lower = normalize(end.unwrap_or(-len - 1)).clamp(-1, len - 1);
+        let mut index = upper;
+        while lower < index {
+            indexes.push(index as usize);
+            index += step;
+        }
+    }
+    indexes
+}
+
+#[cfg(test)]
+mod tests {
+    use async_graphql_value::ConstValue;
+    use pretty_assertions::assert_eq;
+    use serde_json::{json, Value};
+
+    use crate::jsonlike::convert;
+    use crate::jsonpath::JsonPath;
+
+    fn select(query: &str, value: &Value) -> Vec<Value> {
+        let path = JsonPath::parse(query).unwrap();
+        path.select(value).into_iter().cloned().collect()
+    }
+
+    fn paths(query: &str, value: &Value) -> Vec<String> {
+        let path = JsonPath::parse(query).unwrap();
+        let nodes = path.query(value);
+        nodes.iter().map(|node| node.path.to_string()).collect()
+    }
+
+    /// The example of section 1.5 of RFC 9535.
+    /// The example of RFC 9535, with `bicycle` first to keep members sorted.
+    fn store() -> Value {
+        json!({ "store": {
+            "bicycle": { "color": "red", "price": 399 },
+            "book": [
+                { "category": "reference", "author": "Nigel Rees",
+                  "title": "Sayings of the Century", "price": 8.95 },
+                { "category": "fiction", "author": "Evelyn Waugh",
+                  "title": "Sword of Honour", "price": 12.99 },
+                { "category": "fiction", "author": "Herman Melville",
+                  "title": "Moby Dick", "isbn": "0-553-21311-3", "price": 8.99 },
+                { "category": "fiction", "author": "J. R. R. Tolkien",
+                  "title": "The Lord of the Rings", "isbn": "0-395-19395-8",
+                  "price": 22.99 }
+            ]
+        }})
+    }
+
+    #[test]
+    fn test_store_examples() {
+        let store = store();
+        let authors = json!([
+            "Nigel Rees",
+            "Evelyn Waugh",
+            "Herman Melville",
+            "J. R. R. Tolkien"
+        ]);
+        assert_eq!(
+            select("$.store.book[*].author", &store),
+            authors.as_array().unwrap().clone()
+        );
+        assert_eq!(
+            select("$..author", &store),
+            authors.as_array().unwrap().clone()
+        );
+        assert_eq!(select("$.store.*", &store).len(), 2);
+        assert_eq!(
+            select("$.store..price", &store),
+            vec![
+                json!(399),
+                json!(8.95),
+                json!(12.99),
+                json!(8.99),
+                json!(22.99)
+            ]
+        );
+        assert_eq!(paths("$..book[2]", &store), vec!["$['store']['book'][2]"]);
+        assert_eq!(paths("$..book[-1]", &store), vec!["$['store']['book'][3]"]);
+        assert_eq!(paths("$..book[0,1]", &store), paths("$..book[:2]", &store));
+        assert_eq!(
+            select("$..book[?@.isbn].title", &store),
+            vec![json!("Moby Dick"), json!("The Lord of the Rings")]
+        );
+        assert_eq!(
+            select("$..book[?@.price<10].title", &store),
+            vec![json!("Sayings of the Century"), json!("Moby Dick")]
+        );
+        assert_eq!(select("$..*", &store).len(), 27);
+        assert_eq!(
+            paths("$.store.book[?@.author == 'Herman Melville'].price", &store),
+            vec!["$['store']['book'][2]['price']"]
+        );
+    }
+
+    #[test]
+    fn test_filter_examples() {
+        // Section 2.3.5.3 of RFC 9535
+        let value = json!({
+            "a": [3, 5, 1, 2, 4, 6, {"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}],
+            "o": {"p": 1, "q": 2, "r": 3, "s": 5, "t": {"u": 6}},
+            "e": "f"
+        });
+        let cases = [
+            ("$.a[?@.b == 'kilo']", json!([{"b": "kilo"}])),
+            ("$.a[?(@.b == 'kilo')]", json!([{"b": "kilo"}])),
+            ("$.a[?@>3.5]", json!([5, 4, 6])),
+            (
+                "$.a[?@.b]",
+                json!([{"b": "j"}, {"b": "k"}, {"b": {}}, {"b": "kilo"}]),
+            ),
+            ("$[?@.*]", json!([value["a"], value["o"]])),
+            ("$[?@[?@.b]]", json!([value["a"]])),
+            ("$.o[?@<3, ?@<3]", json!([1, 2, 1, 2])),
+            ("$.a[?@<2 || @.b == \"k\"]", json!([1, {"b": "k"}])),
+            (
+                "$.a[?match(@.b, \"[jk]\")]",
+                json!([{"b": "j"}, {"b": "k"}]),
+            ),
+            (
+                "$.a[?search(@.b, \"[jk]\")]",
+                json!([{"b": "j"}, {"b": "k"}, {"b": "kilo"}]),
+            ),
+            ("$.o[?@>1 && @<4]", json!([2, 3])),
+            ("$.o[?@.u || @.x]", json!([{"u": 6}])),
+            ("$.a[?@.b == $.x]", json!([3, 5, 1, 2, 4, 6])),
+            ("$.a[?@ == @]", value["a"].clone()),
+            ("$[?@ == $.o]", json!([value["o"]])),
+            ("$.a[?!@.b && @ != 3]", json!([5, 1, 2, 4, 6])),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(Value::Array(select(query, &value)), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_large_integers() {
+        let value = json!([
+            {"a": 9007199254740993u64, "b": 9007199254740992u64},
+            {"a": 1, "b": 1.0},
+        ]);
+        assert_eq!(select("$[?@.a == @.b]", &value), vec![value[1].clone()]);
+    }
+
+    #[test]
+    fn test_slices() {
+        let value = json!(["a", "b", "c", "d", "e", "f", "g"]);
+        let cases = [
+            ("$[1:3]", json!(["b", "c"])),
+            ("$[5:]", json!(["f", "g"])),
+            ("$[1:5:2]", json!(["b", "d"])),
+            ("$[5:1:-2]", json!(["f", "d"])),
+            ("$[::-1]", json!(["g", "f", "e", "d", "c", "b", "a"])),
+            ("$[-2:]", json!(["f", "g"])),
+            ("$[::0]", json!([])),
+            ("$[10:20]", json!([])),
+        ];
+        for (query, expected) in cases {
+            assert_eq!(Value::Array(select(query, &value)), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_descendants() {
+        // Section 2.5.2.3 of RFC 9535
+        // members are in sorted order, so the results don't depend on whether
+        // `serde_json` preserves the order of keys
+        let value = json!({"a": [5, 3, [{"j": 4}, {"k": 6}]], "o": {"j": 1, "k": 2}});
+        assert_eq!(select("$..j", &value), vec![json!(4), json!(1)]);
+        assert_eq!(select("$..[0]", &value), vec![json!(5), json!({"j": 4})]);
+        assert_eq!(
+            paths("$..[*]", &value),
+            vec![
+                "$['a']",
+                "$['o']",
+                "$['a'][0]",
+                "$['a'][1]",
+                "$['a'][2]",
+                "$['a'][2][0]",
+                "$['a'][2][1]",
+                "$['a'][2][0]['j']",
+                "$['a'][2][1]['k']",
+                "$['o']['j']",
+                "$['o']['k']"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_functions() {
+        let value = json!({"users": [
+            {"name": "ann", "tags": ["a", "b"], "active": true},
+            {"name": "bob", "tags": [], "active": false},
+            {"name": "cécile", "tags": ["c"]}
+        ]});
+        let names = |query| select(query, &value);
+        assert_eq!(
+            names("$.users[?length(@.name) == 6].name"),
+            vec![json!("cécile")]
+        );
+        assert_eq!(
+            names("$.users[?length(@.tags) >= 1].name"),
+            vec![json!("ann"), json!("cécile")]
+        );
+        assert_eq!(
+            names("$.users[?count(@.*) == 3].name"),
+            vec![json!("ann"), json!("bob")]
+        );
+        assert_eq!(
+            names("$.users[?value(@..active) == false].name"),
+            vec![json!("bob")]
+        );
+        assert_eq!(names("$.users[?@.active == true].name"), vec![json!("ann")]);
+        assert_eq!(names("$.users[?@.active].name").len(), 2);
+        assert_eq!(
+            names("$.users[?match(@.name, 'b.b')].name"),
+            vec![json!("bob")]
+        );
+        assert_eq!(
+            names("$.users[?match(@.name, 'b')].name"),
+            Vec::<Value>::new()
+        );
+        assert_eq!(
+            names("$.users[?search(@.name, $.users[0].name)].name"),
+            vec![json!("ann")]
+        );
+        assert_eq!(names("$.users[?match(@.name, '(')]"), Vec::<Value>::new());
+    }
+
+    #[test]
+    fn test_paths() {
+        let value = json!({"a'b": {"c\nd": [1, 2]}, "x/y~": 1});
+        let path = JsonPath::parse("$..[1]").unwrap();
+        let nodes = path.query(&value);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].path.to_string(), r"$['a\'b']['c\nd'][1]");
+        assert_eq!(nodes[0].path.to_pointer(), "/a'b/c\nd/1");
+        assert_eq!(nodes[0].value, &json!(2));
+
+        let nodes = JsonPath::parse("$['x/y~']").unwrap().query(&value);
+        assert_eq!(nodes[0].path.to_pointer(), "/x~1y~0");
+    }
+
+    #[test]
+    fn test_backends() {
+        let store = store();
+        let query = JsonPath::parse("$..book[?@.price < 10 && @.category == 'fiction']").unwrap();
+        let expected = query.query(&store);
+        assert_eq!(expected.len(), 1);
+
+        let graphql: ConstValue = convert(&store);
+        let nodes = query.query(&graphql);
+        assert_eq!(nodes.len(), 1);
+        assert_eq!(nodes[0].path, expected[0].path);
+        assert_eq!(convert::<_, Value>(nodes[0].value), *expected[0].value);
+
+        let text = store.to_string();
+        let borrowed: serde_json_borrow::Value = serde_json::from_str(&text).unwrap();
+        let nodes = query.query(&borrowed);
+        assert_eq!(nodes[0].path, expected[0].path);
+        assert_eq!(convert::<_, Value>(nodes[0].value), *expected[0].value);
+    }
+}
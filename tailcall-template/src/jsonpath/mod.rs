@@ -0,0 +1,24 @@
+//! JSONPath ([RFC 9535](https://www.rfc-editor.org/rfc/rfc9535)) queries over
+//! [`JsonLike`](crate::jsonlike::JsonLike) values.
+//!
+//! Queries are parsed once and can then select references into any number of
+//! documents, together with the normalized paths of the selected nodes. All
+//! of the RFC is supported: name, wildcard, index, slice and filter selectors,
+//! child and descendant segments, and the `length`, `count`, `match`,
+//! `search` and `value` functions.
+//!
+//! ```
+//! use serde_json::json;
+//! use tailcall_template::jsonpath::JsonPath;
+//!
+//! let path = JsonPath::parse("$.data.users[?(@.active)].id").unwrap();
+//! let value = json!({"data": {"users": [{"id": 1, "active": true}, {"id": 2}]}});
+//! let nodes = path.query(&value);
+//! assert_eq!(nodes[0].value, &json!(1));
+//! assert_eq!(nodes[0].path.to_string(), "$['data']['users'][0]['id']");
+//! ```
+mod eval;
+mod parse;
+mod query;
+
+pub use query::{JsonPath, JsonPathError, Node, NormalizedPath, PathElement};
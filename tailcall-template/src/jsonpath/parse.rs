@@ -0,0 +1,567 @@
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{char, digit0, multispace0, one_of, satisfy};
+use nom::combinator::{cut, map, opt, recognize};
+use nom::error::{ErrorKind, ParseError};
+use nom::multi::{many0, separated_list1};
+use nom::sequence::{delimited, preceded, terminated};
+use nom::{IResult, Parser};
+use regex::Regex;
+
+use super::query::*;
+
+/// Error while parsing, with a message if it is more specific than the
+/// character at `input` being unexpected.
+struct Error<'a> {
+    input: &'a str,
+    message: Option<String>,
+}
+
+impl<'a> ParseError<&'a str> for Error<'a> {
+    fn from_error_kind(input: &'a str, _: ErrorKind) -> Self {
+        Error {
+            input,
+            message: None,
+        }
+    }
+
+    fn append(_: &'a str, _: ErrorKind, other: Self) -> Self {
+        other
+    }
+
+    /// Keeps the error of the alternative that got further.
+    fn or(self, other: Self) -> Self {
+        if other.input.len() <= self.input.len() {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+type Res<'a, T> = IResult<&'a str, T, Error<'a>>;
+
+fn fail<T>(input: &str, message: impl Into<String>) -> Res<'_, T> {
+    Err(nom::Err::Failure(Error {
+        input,
+        message: Some(message.into()),
+    }))
+}
+
+/// Largest integer allowed in indexes and slices, as in I-JSON.
+const MAX_INT: i64 = (1 << 53) - 1;
+
+pub(super) fn parse(input: &str) -> Result<Query, JsonPathError> {
+    let error = |at: &str, message: Option<String>| JsonPathError {
+        message: message.unwrap_or_else(|| match at.chars().next() {
+            Some(c) => format!("unexpected character `{}`", c.escape_debug()),
+            None => "unexpected end of input".to_string(),
+        }),
+        offset: input.len() - at.len(),
+    };
+    match root_query(input) {
+        Ok(("", query)) => Ok(query),
+        Ok((rest, _)) => Err(error(rest, None)),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => Err(error(err.input, err.message)),
+        Err(nom::Err::Incomplete(_)) => Err(error("", None)),
+    }
+}
+
+fn root_query(input: &str) -> Res<'_, Query> {
+    map(preceded(char('$'), segments), |segments| Query {
+        relative: false,
+        segments,
+    })
+    .parse(input)
+}
+
+fn filter_query(input: &str) -> Res<'_, Query> {
+    alt((
+        map(preceded(char('@'), segments), |segments| Query {
+            relative: true,
+            segments,
+        }),
+        root_query,
+    ))
+    .parse(input)
+}
+
+fn segments(input: &str) -> Res<'_, Vec<Segment>> {
+    many0(preceded(multispace0, segment)).parse(input)
+}
+
+fn segment(input: &str) -> Res<'_, Segment> {
+    let shorthand = || {
+        alt((
+            map(char('*'), |_| vec![Selector::Wildcard]),
+            map(member_name, |name| vec![Selector::Name(name.to_string())]),
+        ))
+    };
+    alt((
+        map(
+            preceded(tag(".."), cut(alt((bracketed, shorthand())))),
+            Segment::Descendant,
+        ),
+        map(preceded(char('.'), cut(shorthand())), Segment::Child),
+        map(bracketed, Segment::Child),
+    ))
+    .parse(input)
+}
+
+fn member_name(input: &str) -> Res<'_, &str> {
+    let first = |c: char| c.is_ascii_alphabetic() || c == '_' || !c.is_ascii();
+    recognize((
+        satisfy(first),
+        many0(satisfy(move |c| first(c) || c.is_ascii_digit())),
+    ))
+    .parse(input)
+}
+
+fn bracketed(input: &str) -> Res<'_, Vec<Selector>> {
+    delimited(
+        (char('['), multispace0),
+        cut(separated_list1(
+            (multispace0, char(','), multispace0),
+            selector,
+        )),
+        cut((multispace0, char(']'))),
+    )
+    .parse(input)
+}
+
+fn selector(input: &str) -> Res<'_, Selector> {
+    alt((
+        map(string_literal, Selector::Name),
+        map(char('*'), |_| Selector::Wildcard),
+        slice,
+        map(int, Selector::Index),
+        map(
+            preceded((char('?'), multispace0), cut(logical_expr)),
+            Selector::Filter,
+        ),
+    ))
+    .parse(input)
+}
+
+fn slice(input: &str) -> Res<'_, Selector> {
+    map(
+        (
+            opt(terminated(int, multispace0)),
+            char(':'),
+            multispace0,
+            opt(terminated(int, multispace0)),
+            opt(preceded(char(':'), opt(preceded(multispace0, int)))),
+        ),
+        |(start, _, _, end, step)| Selector::Slice {
+            start,
+            end,
+            step: step.flatten(),
+        },
+    )
+    .parse(input)
+}
+
+/// An integer without leading zeros, in the I-JSON range.
+fn int(input: &str) -> Res<'_, i64> {
+    let (rest, text) = alt((
+        tag("0"),
+        recognize((opt(char('-')), one_of("123456789"), digit0)),
+    ))
+    .parse(input)?;
+    match text.parse::<i64>() {
+        Ok(n) if (-MAX_INT..=MAX_INT).contains(&n) => Ok((rest, n)),
+        _ => fail(input, "integer out of range"),
+    }
+}
+
+fn logical_expr(input: &str) -> Res<'_, Expr> {
+    let or = (multispace0, tag("||"), multispace0);
+    map(separated_list1(or, and_expr), |mut exprs| {
+        match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::Or(exprs),
+        }
+    })
+    .parse(input)
+}
+
+fn and_expr(input: &str) -> Res<'_, Expr> {
+    let and = (multispace0, tag("&&"), multispace0);
+    map(separated_list1(and, basic_expr), |mut exprs| {
+        match exprs.len() {
+            1 => exprs.remove(0),
+            _ => Expr::And(exprs),
+        }
+    })
+    .parse(input)
+}
+
+fn basic_expr(input: &str) -> Res<'_, Expr> {
+    alt((paren_expr, comparison, test_expr)).parse(input)
+}
+
+fn not(negate: Option<char>, expr: Expr) -> Expr {
+    match negate {
+        Some(_) => Expr::Not(Box::new(expr)),
+        None => expr,
+    }
+}
+
+fn paren_expr(input: &str) -> Res<'_, Expr> {
+    map(
+        (
+            opt(terminated(char('!'), multispace0)),
+            delimited(
+                (char('('), multispace0),
+                cut(logical_expr),
+                cut((multispace0, char(')'))),
+            ),
+        ),
+        |(negate, expr)| not(negate, expr),
+    )
+    .parse(input)
+}
+
+fn test_expr(input: &str) -> Res<'_, Expr> {
+    let (rest, negate) = opt(terminated(char('!'), multispace0)).parse(input)?;
+    let (rest, function) = match filter_query(rest) {
+        Ok((rest, query)) => return Ok((rest, not(negate, Expr::Exists(query)))),
+        Err(nom::Err::Error(_)) => function_expr(rest)?,
+        Err(err) => return Err(err),
+    };
+    if !function.is_logical() {
+        return fail(rest, "a function returning a value must be compared");
+    }
+    Ok((rest, not(negate, Expr::Test(function))))
+}
+
+fn comparison(input: &str) -> Res<'_, Expr> {
+    let op = alt((
+        map(tag("=="), |_| CompareOp::Eq),
+        map(tag("!="), |_| CompareOp::Ne),
+        map(tag("<="), |_| CompareOp::Le),
+        map(tag(">="), |_| CompareOp::Ge),
+        map(tag("<"), |_| CompareOp::Lt),
+        map(tag(">"), |_| CompareOp::Gt),
+    ));
+    let (rest, (left, _, op, _)) = (operand, multispace0, op, multispace0).parse(input)?;
+    let left = comparable(input, left, None)?;
+    let (end, right) = cut(operand).parse(rest)?;
+    let right = comparable(rest, right, None)?;
+    Ok((end, Expr::Compare(left, op, right)))
+}
+
+enum Operand {
+    Literal(Literal),
+    Query(Query),
+    Function(Function),
+}
+
+fn operand(input: &str) -> Res<'_, Operand> {
+    alt((
+        map(literal, Operand::Literal),
+        map(filter_query, Operand::Query),
+        map(function_expr, Operand::Function),
+    ))
+    .parse(input)
+}
+
+/// Checks that the operand at `input` is a literal, a singular query or a
+/// function returning a value. `function` is the function it's passed to.
+fn comparable<'a>(
+    input: &'a str,
+    operand: Operand,
+    function: Option<&str>,
+) -> Result<Comparable, nom::Err<Error<'a>>> {
+    let error = |message: &str| {
+        let message = match function {
+            Some(function) => format!("`{}` takes a value", function),
+            None => message.to_string(),
+        };
+        nom::Err::Failure(Error {
+            input,
+            message: Some(message),
+        })
+    };
+    match operand {
+        Operand::Literal(literal) => Ok(Comparable::Literal(literal)),
+        Operand::Query(query) if query.is_singular() => Ok(Comparable::Query(query)),
+        Operand::Query(_) => Err(error("only singular queries can be compared")),
+        Operand::Function(function) if function.is_logical() => Err(error(
+            "a function returning a logical value can't be compared",
+        )),
+        Operand::Function(function) => Ok(Comparable::Function(Box::new(function))),
+    }
+}
+
+fn literal(input: &str) -> Res<'_, Literal> {
+    alt((
+        map(string_literal, Literal::String),
+        map(tag("true"), |_| Literal::Bool(true)),
+        map(tag("false"), |_| Literal::Bool(false)),
+        map(tag("null"), |_| Literal::Null),
+        number,
+    ))
+    .parse(input)
+}
+
+fn number(input: &str) -> Res<'_, Literal> {
+    let (rest, text) = recognize((
+        opt(char('-')),
+        alt((tag("0"), recognize((one_of("123456789"), digit0)))),
+        opt((char('.'), cut(nom::character::complete::digit1))),
+        opt((
+            one_of("eE"),
+            opt(one_of("+-")),
+            cut(nom::character::complete::digit1),
+        )),
+    ))
+    .parse(input)?;
+    match text.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok((rest, Literal::Number(n))),
+        _ => fail(input, "number out of range"),
+    }
+}
+
+fn function_expr(input: &str) -> Res<'_, Function> {
+    let (rest, name) = recognize((
+        satisfy(|c| c.is_ascii_lowercase()),
+        many0(satisfy(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_'
+        })),
+    ))
+    .parse(input)?;
+    let (rest, _) = (char('('), multispace0).parse(rest)?;
+
+    let (rest, function) = match name {
+        "length" => map(value_arg(name), Function::Length).parse(rest)?,
+        "count" => map(nodes_arg(name), Function::Count).parse(rest)?,
+        "value" => map(nodes_arg(name), Function::Value).parse(rest)?,
+        "match" | "search" => {
+            let full = name == "match";
+            let (rest, value) = value_arg(name).parse(rest)?;
+            let (rest, _) = cut((multispace0, char(','), multispace0)).parse(rest)?;
+            let (rest, pattern) = value_arg(name).parse(rest)?;
+            let pattern = match pattern {
+                Comparable::Literal(Literal::String(pattern)) => {
+                    match Regex::new(&to_regex(&pattern, full)) {
+                        Ok(regex) => Pattern::Compiled(regex),
+                        Err(_) => Pattern::Invalid,
+                    }
+                }
+                pattern => Pattern::Dynamic(pattern),
+            };
+            (
+                rest,
+                Function::Match {
+                    full,
+                    value,
+                    pattern,
+                },
+            )
+        }
+        _ => return fail(input, format!("unknown function `{}`", name)),
+    };
+    let (rest, _) = cut((multispace0, char(')'))).parse(rest)?;
+    Ok((rest, function))
+}
+
+/// Argument of `function` that must be a value.
+fn value_arg(function: &str) -> impl FnMut(&str) -> Res<'_, Comparable> + '_ {
+    move |input| match operand(input) {
+        Ok((rest, operand)) => Ok((rest, comparable(input, operand, Some(function))?)),
+        Err(nom::Err::Error(_)) => fail(input, format!("`{}` takes a value", function)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Argument of `function` that must be a query.
+fn nodes_arg(function: &str) -> impl FnMut(&str) -> Res<'_, Query> + '_ {
+    move |input| match filter_query(input) {
+        Err(_) => fail(input, format!("`{}` takes a query", function)),
+        result => result,
+    }
+}
+
+/// Translates an I-Regexp to the syntax of the `regex` crate, where `.` also
+/// matches `\r`, and anchors it for `match`.
+pub(super) fn to_regex(pattern: &str, full: bool) -> String {
+    let mut out = String::with_capacity(pattern.len() + 8);
+    if full {
+        out.push_str("^(?:");
+    }
+    let mut chars = pattern.chars();
+    let mut in_class = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                out.push(c);
+                if let Some(c) = chars.next() {
+                    out.push(c);
+                }
+            }
+            '[' => {
+                in_class = true;
+                out.push(c);
+            }
+            ']' => {
+                in_class = false;
+                out.push(c);
+            }
+            '.' if !in_class => out.push_str("[^\\n\\r]"),
+            c => out.push(c),
+        }
+    }
+    if full {
+        out.push_str(")$");
+    }
+    out
+}
+
+/// A string in single or double quotes.
+fn string_literal(input: &str) -> Res<'_, String> {
+    let Some(quote) = input.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+        return Err(nom::Err::Error(Error {
+            input,
+            message: None,
+        }));
+    };
+    let mut out = String::new();
+    let mut rest = &input[1..];
+    loop {
+        match rest.chars().next() {
+            Some(c) if c == quote => return Ok((&rest[1..], out)),
+            Some('\\') => {
+                let (next, c) = escape(&rest[1..], quote)?;
+                out.push(c);
+                rest = next;
+            }
+            Some(c) if c >= ' ' => {
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+            _ => return fail(rest, "unterminated string"),
+        }
+    }
+}
+
+/// The character escaped after a backslash.
+fn escape(input: &str, quote: char) -> Res<'_, char> {
+    let invalid = || fail(input, "invalid escape");
+    let Some(c) = input.chars().next() else {
+        return invalid();
+    };
+    let rest = &input[c.len_utf8()..];
+    let c = match c {
+        'b' => '\x08',
+        'f' => '\x0C',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '/' | '\\' => c,
+        c if c == quote => c,
+        'u' => {
+            let Some(high) = hex4(rest) else {
+                return invalid();
+            };
+            if !(0xD800..0xDC00).contains(&high) {
+                return match char::from_u32(high) {
+                    Some(c) => Ok((&rest[4..], c)),
+                    None => invalid(),
+                };
+            }
+            let low = rest[4..].strip_prefix("\\u").and_then(hex4);
+            return match low {
+                Some(low) if (0xDC00..0xE000).contains(&low) => {
+                    let c = ((high - 0xD800) << 10) + (low - 0xDC00) + 0x10000;
+                    Ok((&rest[10..], char::from_u32(c).unwrap_or_default()))
+                }
+                _ => invalid(),
+            };
+        }
+        _ => return invalid(),
+    };
+    Ok((rest, c))
+}
+
+fn hex4(input: &str) -> Option<u32> {
+    let digits = input.get(..4)?;
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u32::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{parse, to_regex};
+
+    #[test]
+    fn test_valid_queries() {
+        let queries = [
+            "$",
+            "$.a.b_c.é",
+            "$['a']['b\\'c'][\"d\\\"e\"]",
+            "$[0, -1, 'a', *]",
+            "$[1:3][:][::-1][ 1 : 5 : 2 ]",
+            "$..a..[0]..*",
+            "$ .a [0]",
+            "$[?@.a]",
+            "$[?(@.a)]",
+            "$[?!@.a && (@.b || !(@.c))]",
+            "$[?@.a == 'x' && @.b != 1 && @.c < 2.5 && @.d >= -1e3]",
+            "$[?@.a == $.b[0]]",
+            "$[?length(@.a) > 2 && count(@.*) == 1 && value(@..a) == null]",
+            "$[?match(@.a, '[a-z]+') || search(@.a, $.pattern)]",
+            "$['\\ud83d\\ude00']",
+        ];
+        for query in queries {
+            assert!(parse(query).is_ok(), "{}: {:?}", query, parse(query).err());
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("", "unexpected end of input at byte 0"),
+            ("a", "unexpected character `a` at byte 0"),
+            ("$.", "unexpected end of input at byte 2"),
+            ("$.1", "unexpected character `1` at byte 2"),
+            ("$[01]", "unexpected character `1` at byte 3"),
+            ("$[-0]", "unexpected character `0` at byte 3"),
+            ("$[9007199254740992]", "integer out of range at byte 2"),
+            ("$[1 2]", "unexpected character `2` at byte 4"),
+            ("$['a]", "unterminated string at byte 5"),
+            ("$['\\x']", "invalid escape at byte 4"),
+            ("$.a ", "unexpected character ` ` at byte 3"),
+            (
+                "$[?@.* == 1]",
+                "only singular queries can be compared at byte 3",
+            ),
+            (
+                "$[?length(@.a)]",
+                "a function returning a value must be compared at byte 14",
+            ),
+            (
+                "$[?match(@.a, 'x') == true]",
+                "a function returning a logical value can't be compared at byte 3",
+            ),
+            ("$[?length(@.*) > 1]", "`length` takes a value at byte 10"),
+            ("$[?count(1) > 1]", "`count` takes a query at byte 9"),
+            ("$[?foo(@)]", "unknown function `foo` at byte 3"),
+            ("$[?@.a == ]", "unexpected character `]` at byte 10"),
+        ];
+        for (query, expected) in cases {
+            let err = parse(query).unwrap_err();
+            assert_eq!(err.to_string(), expected, "{}", query);
+        }
+    }
+
+    #[test]
+    fn test_to_regex() {
+        assert_eq!(to_regex("a.[.]\\.", true), "^(?:a[^\\n\\r][.]\\.)$");
+        assert_eq!(to_regex("a.", false), "a[^\\n\\r]");
+    }
+}
@@ -0,0 +1,235 @@
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+use regex::Regex;
+
+use super::{eval, parse};
+use crate::jsonlike::{escape_token, JsonLike};
+
+/// A parsed JSONPath query, such as `$.data.users[?@.active].id`.
+#[derive(Debug, Clone)]
+pub struct JsonPath(pub(super) Query);
+
+impl JsonPath {
+    pub fn parse(input: &str) -> Result<JsonPath, JsonPathError> {
+        parse::parse(input).map(JsonPath)
+    }
+
+    /// Nodes selected from `root`, in the order defined by the RFC, together
+    /// with their normalized paths.
+    pub fn query<'json, A: JsonLike<'json>>(&self, root: &'json A) -> Vec<Node<'json, A>> {
+        eval::query(&self.0, root, true)
+            .into_iter()
+            .map(|(trail, value)| Node {
+                path: trail.to_path(),
+                value,
+            })
+            .collect()
+    }
+
+    /// Values selected from `root`, without computing their paths.
+    pub fn select<'json, A: JsonLike<'json>>(&self, root: &'json A) -> Vec<&'json A> {
+        eval::query(&self.0, root, false)
+            .into_iter()
+            .map(|(_, value)| value)
+            .collect()
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonPathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        JsonPath::parse(s)
+    }
+}
+
+/// A value selected by a [`JsonPath`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node<'json, A> {
+    pub path: NormalizedPath,
+    pub value: &'json A,
+}
+
+/// Location of a node, written as a normalized path like `$['a'][0]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct NormalizedPath(pub Vec<PathElement>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathElement {
+    Name(String),
+    Index(usize),
+}
+
+impl NormalizedPath {
+    /// The same location as a JSON Pointer.
+    pub fn to_pointer(&self) -> String {
+        let mut out = String::new();
+        for element in &self.0 {
+            out.push('/');
+            match element {
+                PathElement::Name(name) => out.push_str(&escape_token(name)),
+                PathElement::Index(index) => {
+                    let _ = write!(out, "{}", index);
+                }
+            }
+        }
+        out
+    }
+}
+
+impl Display for NormalizedPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_char('$')?;
+        for element in &self.0 {
+            match element {
+                PathElement::Index(index) => write!(f, "[{}]", index)?,
+                PathElement::Name(name) => {
+                    f.write_str("['")?;
+                    for c in name.chars() {
+                        match c {
+                            '\x08' => f.write_str("\\b")?,
+                            '\x0C' => f.write_str("\\f")?,
+                            '\n' => f.write_str("\\n")?,
+                            '\r' => f.write_str("\\r")?,
+                            '\t' => f.write_str("\\t")?,
+                            '\'' => f.write_str("\\'")?,
+                            '\\' => f.write_str("\\\\")?,
+                            c if c < ' ' => write!(f, "\\u{:04x}", c as u32)?,
+                            c => f.write_char(c)?,
+                        }
+                    }
+                    f.write_str("']")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error produced by [`JsonPath::parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonPathError {
+    pub message: String,
+    /// Byte offset of the error in the query.
+    pub offset: usize,
+}
+
+impl Display for JsonPathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for JsonPathError {}
+
+/// `$` or `@` followed by segments.
+#[derive(Debug, Clone)]
+pub(super) struct Query {
+    pub relative: bool,
+    pub segments: Vec<Segment>,
+}
+
+impl Query {
+    /// Whether the query selects at most one node.
+    pub fn is_singular(&self) -> bool {
+        self.segments.iter().all(|segment| match segment {
+            Segment::Child(selectors) => {
+                matches!(
+                    selectors.as_slice(),
+                    [Selector::Name(_) | Selector::Index(_)]
+                )
+            }
+            Segment::Descendant(_) => false,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Segment {
+    Child(Vec<Selector>),
+    Descendant(Vec<Selector>),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Selector {
+    Name(String),
+    Wildcard,
+    Index(i64),
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: Option<i64>,
+    },
+    Filter(Expr),
+}
+
+/// A logical expression of a filter selector.
+#[derive(Debug, Clone)]
+pub(super) enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    /// True if the query selects at least one node.
+    Exists(Query),
+    /// A function returning a logical value.
+    Test(Function),
+    Compare(Comparable, CompareOp, Comparable),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+/// Operand of a comparison or argument of a function taking a value.
+#[derive(Debug, Clone)]
+pub(super) enum Comparable {
+    Literal(Literal),
+    /// A singular query.
+    Query(Query),
+    /// A function returning a value.
+    Function(Box<Function>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Literal {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone)]
+pub(super) enum Function {
+    Length(Comparable),
+    Count(Query),
+    Value(Query),
+    /// `match` if `full`, else `search`.
+    Match {
+        full: bool,
+        value: Comparable,
+        pattern: Pattern,
+    },
+}
+
+impl Function {
+    /// Whether the function returns a logical value rather than a value.
+    pub fn is_logical(&self) -> bool {
+        matches!(self, Function::Match { .. })
+    }
+}
+
+/// Regex of `match` and `search`, compiled while parsing if it's a literal.
+#[derive(Debug, Clone)]
+pub(super) enum Pattern {
+    Compiled(Regex),
+    /// A literal that is not a valid regex: nothing matches it.
+    Invalid,
+    Dynamic(Comparable),
+}
@@ -1,4 +1,5 @@
 #![allow(clippy::module_inception)]
 pub mod jq;
 pub mod jsonlike;
+pub mod jsonpath;
 pub mod mustache;
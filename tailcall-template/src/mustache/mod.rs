@@ -4,3 +4,4 @@ mod parse;
 pub mod path;
 
 pub use mustache::*;
+pub use parse::MustacheError;
@@ -1,3 +1,6 @@
+use std::fmt::Display;
+use std::ops::Range;
+
 use nom::branch::alt;
 use nom::bytes::complete::{tag, take_until};
 use nom::character::complete::char;
@@ -17,8 +20,56 @@ impl Mustache {
             Err(_) => Mustache::from(vec![Segment::Literal(str.to_string())]),
         }
     }
+
+    /// Parses `str` like [`Mustache::parse`], but fails on a `{{` that does
+    /// not start a valid expression instead of keeping it as a literal.
+    pub fn try_parse(str: &str) -> Result<Mustache, MustacheError> {
+        let mut offset = 0;
+        while let Some(start) = str[offset..].find("{{").map(|i| offset + i) {
+            match parse_expression(&str[start..]) {
+                Ok((rest, _)) => offset = str.len() - rest.len(),
+                Err(_) => {
+                    let error = match str[start..].find("}}") {
+                        Some(end) => MustacheError {
+                            message: format!(
+                                "invalid expression `{}`",
+                                &str[start..start + end + 2]
+                            ),
+                            span: start..start + end + 2,
+                        },
+                        None => MustacheError {
+                            message: "unclosed expression, expected `}}`".to_string(),
+                            span: start..str.len(),
+                        },
+                    };
+                    return Err(error);
+                }
+            }
+        }
+        Ok(Mustache::parse(str))
+    }
+}
+
+/// Error produced by [`Mustache::try_parse`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct MustacheError {
+    pub message: String,
+    /// Byte range of the invalid expression in the template.
+    pub span: Range<usize>,
+}
+
+impl Display for MustacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
 }
 
+impl std::error::Error for MustacheError {}
+
 fn parse_name(input: &str) -> IResult<&str, String> {
     let spaces = nom::character::complete::multispace0;
     let alpha = nom::character::complete::alpha1;
@@ -87,6 +138,7 @@ fn parse_mustache(input: &str) -> IResult<&str, Mustache> {
 mod tests {
     use pretty_assertions::assert_eq;
 
+    use super::MustacheError;
     use crate::mustache::mustache::{Mustache, Segment};
 
     #[test]
@@ -273,4 +325,29 @@ mod tests {
             ])])
         );
     }
+
+    #[test]
+    fn test_try_parse() {
+        let s = "/users/{{ value.id }}/{{args.x}}";
+        assert_eq!(Mustache::try_parse(s), Ok(Mustache::parse(s)));
+        assert_eq!(
+            Mustache::try_parse("no expressions }}"),
+            Ok(Mustache::parse("no expressions }}"))
+        );
+
+        assert_eq!(
+            Mustache::try_parse("/users/{{value.1d}}"),
+            Err(MustacheError {
+                message: "invalid expression `{{value.1d}}`".to_string(),
+                span: 7..19
+            })
+        );
+        assert_eq!(
+            Mustache::try_parse("{{a}}/{{value.id"),
+            Err(MustacheError {
+                message: "unclosed expression, expected `}}`".to_string(),
+                span: 6..16
+            })
+        );
+    }
 }